@@ -1,24 +1,35 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex as StdMutex, RwLock as StdRwLock,
+};
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{Mutex, RwLock};
-use tokio::sync::broadcast::{self, Sender};
 use tokio::time::{self, Duration};
 
+use backlog::Backlog;
 pub use config::Config;
+use config::ProtoLimits;
 use connection::{Connection, Handler};
 use db::Db;
-use replica::{ReplicationMsg, Replinfo};
+use persistence::Persistence;
+use rdb::Rdb;
+use replica::{LinkState, Replinfo};
+use replicas::Replicas;
 use role::Role;
 
+mod backlog;
 mod cmd;
 mod connection;
 mod config;
 mod db;
 mod frame;
 mod parser;
+mod persistence;
+mod rdb;
 mod replica;
+mod replicas;
 mod role;
 mod utils;
 
@@ -36,67 +47,52 @@ impl Server {
             None => Role::Master
         };
 
+        let mut db = Db::new();
+
+        if let Some(rdb) = Rdb::load(&cfg.db_path())? {
+            db.load_rdb(rdb);
+        }
+
         Ok(
             Server {
                 listener: TcpListener::bind(cfg.addr.to_string()).await?,
-                db: Db::new(),
+                db,
                 info: ServerInfo::new(cfg, role),
             }
         )
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        let (sender_tx, _rx) = broadcast::channel(32);
-        let sender = Arc::new(sender_tx);
+        self.db.set_active_expire(self.info.is_master());
+        self.db.set_expire_hook(replica::expire_hook(&self.info.replinfo));
 
-        let master_conn = self.on_startup().await;
-        if let Some(connection) = master_conn {
-            // deal replication connection
-            self.handle_connection(connection, sender.clone()).await;
-        }
+        tokio::spawn(
+            self.info.persistence.clone().watch_save_points(self.db.clone())
+        );
 
-        loop {
-            let socket = self.accept().await?;
-            self.handle_connection(Connection::new(socket), sender.clone()).await;
-        }
-    }
+        tokio::spawn(replica::ping_replicas(self.info.clone()));
 
-    async fn on_startup(&mut self) -> Option<Connection> {
-        match self.info.role {
-            Role::Slave => Some(self.connect_to_master().await.ok()?),
-            Role::Master => None
+        if !self.info.is_master() {
+            replica::start_link(&self.info, &self.db);
         }
-    }
 
-    async fn connect_to_master(&self) -> Result<Connection> {
-        match &self.info.replinfo.master {
-            None => bail!("No master address"),
-            Some(master_addr) => {
-                Ok(replica::handshake(&self.info, master_addr).await?)
-            }
+        loop {
+            let socket = self.accept().await?;
+            self.handle_connection(Connection::new(socket)).await;
         }
     }
 
-    async fn handle_connection(
-        &self,
-        conn: Connection,
-        sender: Arc<Sender<ReplicationMsg>>,
-    ) {
+    async fn handle_connection(&self, conn: Connection) {
         let mut handler = Handler::new(
             conn,
             self.db.clone(),
             self.info.clone(),
-            sender,
         );
 
         tokio::spawn(async move {
             if let Err(e) = handler.handle_connection().await {
                 eprintln!("Error while handling connection: {}", e);
             };
-
-            if handler.connection.is_repl_conn && handler.server_info.is_master() {
-                handler.server_info.replinfo.drop_replica().await;
-            }
         });
     }
 
@@ -123,33 +119,51 @@ impl Server {
 #[derive(Clone)]
 pub struct ServerInfo {
     addr: utils::Addr,
-    role: Role,
+    role: Arc<StdRwLock<Role>>,
     dir: String,
     db_file: String,
     replinfo: Replinfo,
+    persistence: Persistence,
+    client_ids: Arc<AtomicU64>,
+    proto_limits: ProtoLimits,
 }
 
 impl ServerInfo {
     fn new(cfg: Config, role: Role) -> ServerInfo {
         ServerInfo {
+            persistence: Persistence::new(&cfg),
+            client_ids: Arc::new(AtomicU64::new(0)),
+            proto_limits: cfg.proto_limits.clone(),
             addr: cfg.addr,
-            role,
+            role: Arc::new(StdRwLock::new(role)),
             dir: cfg.dir,
             db_file: cfg.dbfilename,
             replinfo: Replinfo {
-                id: String::from("8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb"),
-                offset: Arc::new(Mutex::new(0)),
-                count: Arc::new(RwLock::new(0)),
-                master: cfg.master_addr,
-                wait_lock: Arc::new(Mutex::new(false)),
-                repl_completed: Arc::new(RwLock::new(0)),
-                pending_commands: Arc::new(RwLock::new(false))
+                id: Arc::new(RwLock::new(String::from("8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb"))),
+                second_id: Arc::new(RwLock::new(None)),
+                backlog: Arc::new(StdMutex::new(Backlog::new(cfg.repl_backlog_size))),
+                replicas: Replicas::new(cfg.replica_output_limit.clone()),
+                master: Arc::new(StdMutex::new(cfg.master_addr)),
+                link: Arc::new(StdMutex::new(LinkState::default())),
+                link_task: Arc::new(StdMutex::new(None)),
+                sync_lock: Arc::new(Mutex::new(())),
+                timeout: cfg.repl_timeout,
+                ping_period: cfg.repl_ping_period,
             },
         }
     }
 
+    pub fn role(&self) -> Role {
+        self.role.read().unwrap().clone()
+    }
+
     pub fn is_master(&self) -> bool {
-        self.role == Role::Master
+        self.role() == Role::Master
+    }
+
+    // ids start at 1 and are never reused
+    pub fn next_client_id(&self) -> u64 {
+        self.client_ids.fetch_add(1, Ordering::Relaxed) + 1
     }
 }
 
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+// ring buffer holding the tail of the replication stream
+#[derive(Debug)]
+pub(crate) struct Backlog {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    // replication offset of the last byte written to the stream
+    offset: u64,
+}
+
+impl Backlog {
+    pub fn new(capacity: usize) -> Backlog {
+        Backlog {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+            offset: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // replication offset of the first byte still held
+    pub fn first_byte_offset(&self) -> u64 {
+        self.offset - self.buffer.len() as u64 + 1
+    }
+
+    pub fn histlen(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.offset += data.len() as u64;
+
+        if data.len() >= self.capacity {
+            self.buffer.clear();
+            self.buffer.extend(&data[data.len() - self.capacity..]);
+            return;
+        }
+
+        let overflow = (self.buffer.len() + data.len()).saturating_sub(self.capacity);
+        self.buffer.drain(..overflow);
+        self.buffer.extend(data);
+    }
+
+    // drop the history and continue the stream from `offset`
+    pub fn reset(&mut self, offset: u64) {
+        self.buffer.clear();
+        self.offset = offset;
+    }
+
+    // everything written after `offset`, if the backlog still holds it
+    pub fn since(&self, offset: u64) -> Option<Vec<u8>> {
+        let start = self.offset - self.buffer.len() as u64;
+
+        if offset < start || offset > self.offset {
+            return None;
+        }
+
+        Some(self.buffer.range((offset - start) as usize..).copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backlog_since() {
+        let mut backlog = Backlog::new(8);
+        backlog.push(b"abc");
+        backlog.push(b"def");
+
+        assert_eq!(backlog.offset(), 6);
+        assert_eq!(backlog.since(6), Some(vec![]));
+        assert_eq!(backlog.since(2), Some(b"cdef".to_vec()));
+        assert_eq!(backlog.since(0), Some(b"abcdef".to_vec()));
+        assert_eq!(backlog.since(7), None);
+    }
+
+    #[test]
+    fn test_backlog_wraps() {
+        let mut backlog = Backlog::new(4);
+        backlog.push(b"abc");
+        backlog.push(b"def");
+
+        assert_eq!(backlog.histlen(), 4);
+        assert_eq!(backlog.first_byte_offset(), 3);
+        assert_eq!(backlog.since(1), None);
+        assert_eq!(backlog.since(2), Some(b"cdef".to_vec()));
+
+        backlog.push(b"0123456789");
+
+        assert_eq!(backlog.offset(), 16);
+        assert_eq!(backlog.since(12), Some(b"6789".to_vec()));
+        assert_eq!(backlog.since(11), None);
+    }
+
+    #[test]
+    fn test_backlog_reset() {
+        let mut backlog = Backlog::new(4);
+        backlog.push(b"abc");
+        backlog.reset(100);
+
+        assert_eq!(backlog.offset(), 100);
+        assert_eq!(backlog.since(100), Some(vec![]));
+        assert_eq!(backlog.since(99), None);
+    }
+}
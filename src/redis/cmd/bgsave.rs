@@ -0,0 +1,48 @@
+use anyhow::Result;
+
+use crate::redis::{
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    ServerInfo,
+    utils::Named,
+};
+
+use super::ClientCmd;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bgsave {}
+
+impl Named for Bgsave {
+    const NAME: &'static str = "BGSAVE";
+}
+
+impl Bgsave {
+    pub fn new() -> Bgsave {
+        Bgsave {}
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Bgsave> {
+        parser.finish()?;
+
+        Ok(Bgsave::new())
+    }
+
+    pub fn apply(&self, db: &Db, server_info: &ServerInfo) -> Frame {
+        if server_info.persistence.bgsave(db) {
+            Frame::Simple("Background saving started".to_string())
+        } else {
+            Frame::Error("ERR Background save already in progress".to_string())
+        }
+    }
+}
+
+impl ClientCmd for Bgsave {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Bgsave::NAME.into()));
+
+        frame
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+
+use crate::redis::{
+    cmd::CommandError,
+    connection::ClientInfo,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Client {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Subcommand {
+    Id,
+    Getname,
+    Setname(String),
+}
+
+impl Named for Client {
+    const NAME: &'static str = "CLIENT";
+}
+
+impl Client {
+    pub fn parse_args(parser: &mut Parser) -> Result<Client> {
+        let subcommand_as_str = parser.next_string()?.to_uppercase();
+        let subcommand = match &subcommand_as_str[..] {
+            "ID" => Subcommand::Id,
+            "GETNAME" => Subcommand::Getname,
+            "SETNAME" => Subcommand::Setname(parser.next_string()?),
+            unknown => return Err(CommandError::Other(
+                format!("unknown subcommand '{}'. Try CLIENT HELP.", unknown)
+            ).into()),
+        };
+        parser.finish()?;
+
+        Ok(Client { subcommand })
+    }
+
+    pub fn apply(&self, client: &mut ClientInfo) -> Frame {
+        match &self.subcommand {
+            Subcommand::Id => Frame::Integer(client.id as i64),
+            Subcommand::Getname => match &client.name {
+                Some(name) => Frame::Bulk(name.clone().into()),
+                None => Frame::Null,
+            },
+            Subcommand::Setname(name) => match client.set_name(name) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(e) => e.to_frame(),
+            },
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt;
 
 use anyhow::Result;
 
+use crate::redis::cmd::CommandError;
 use crate::redis::frame::Frame;
 use crate::redis::parser::Parser;
 use crate::redis::ServerInfo;
@@ -17,24 +18,21 @@ impl Config {
         let subcommand_as_str = parser.next_string()?.to_uppercase();
         let subcommand = match &subcommand_as_str[..] {
             "GET" => {Subcommand::Get(GetParams::parse(parser)?)},
-            _ => unimplemented!()
+            unknown => return Err(CommandError::Other(
+                format!("unknown subcommand '{}'. Try CONFIG HELP.", unknown)
+            ).into()),
         };
 
         Ok(Config{ subcommand })
     }
 
+    // a map for RESP3 clients, flattened to name/value pairs for RESP2
     pub(crate) fn apply (&self, server_info: &ServerInfo) -> Frame {
-        let mut resp = Frame::array();
-
         match &self.subcommand {
             Subcommand::Get(params) => {
-                for param in params {
-                    resp.extend(param.to_frame(server_info))
-                }
+                Frame::Map(params.iter().map(|param| param.to_frame(server_info)).collect())
             }
         }
-
-        resp
     }
 }
 
@@ -47,36 +45,49 @@ pub enum Subcommand {
 #[derive(Debug, PartialEq, Clone)]
 pub enum GetParams {
     Dir,
-    DBfilename
+    DBfilename,
+    Save,
 }
 
 impl GetParams {
     fn parse(parser: &mut Parser) -> Result<Vec<GetParams>> {
-        let mut params = vec![];
+        // at least one parameter is required
+        let mut names = vec![parser.next_string()?];
         while let Ok(param) = parser.next_string() {
-            match param.to_lowercase().as_str() {
+            names.push(param);
+        }
+
+        let mut params = vec![];
+        for name in names {
+            match name.to_lowercase().as_str() {
                 "dir" => params.push(GetParams::Dir),
                 "dbfilename" => params.push(GetParams::DBfilename),
-                _ => unimplemented!()
+                "save" => params.push(GetParams::Save),
+                // parameters we don't have are simply left out of the reply
+                _ => {}
             }
         };
 
         Ok(params)
     }
-    fn to_frame(&self, server_info: &ServerInfo) -> Vec<Frame> {
-        let mut result = vec![];
+    fn to_frame(&self, server_info: &ServerInfo) -> (Frame, Frame) {
         match self {
-            GetParams::Dir => {
-                result.push(Frame::Bulk("dir".into()));
-                result.push(Frame::Bulk(server_info.dir.clone().into()));
-            },
-            GetParams::DBfilename => {
-                result.push(Frame::Bulk("dbfilename".into()));
-                result.push(Frame::Bulk(server_info.db_file.clone().into()));
+            GetParams::Dir => (
+                Frame::Bulk("dir".into()),
+                Frame::Bulk(server_info.dir.clone().into()),
+            ),
+            GetParams::DBfilename => (
+                Frame::Bulk("dbfilename".into()),
+                Frame::Bulk(server_info.db_file.clone().into()),
+            ),
+            GetParams::Save => {
+                let save_points: Vec<String> = server_info.persistence.save_points.iter()
+                    .map(|point| format!("{} {}", point.seconds, point.changes))
+                    .collect();
+
+                (Frame::Bulk("save".into()), Frame::Bulk(save_points.join(" ").into()))
             }
         }
-
-        result
     }
 }
 
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    db::Db,
+    frame::Frame,
+    parser::{
+        Parser,
+        ParserError,
+    },
+};
+use crate::redis::cmd::ClientCmd;
+use crate::redis::utils::Named;
+
+// DEL, UNLINK
+#[derive(Debug, PartialEq, Clone)]
+pub struct Del {
+    keys: Vec<Bytes>,
+}
+
+impl Named for Del {
+    const NAME: &'static str = "DEL";
+}
+
+impl Del {
+    pub fn new(keys: Vec<Bytes>) -> Del {
+        Del { keys }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Del> {
+        let mut keys = vec![parser.next_bytes()?];
+
+        loop {
+            match parser.next_bytes() {
+                Ok(key) => keys.push(key),
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Del::new(keys))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        let deleted = self.keys.iter().filter(|key| db.delete(key)).count();
+
+        Frame::Integer(deleted as i64)
+    }
+}
+
+impl ClientCmd for Del {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Del::NAME.into()));
+
+        for key in &self.keys {
+            frame.add(Frame::Bulk(key.clone()));
+        }
+
+        frame
+    }
+}
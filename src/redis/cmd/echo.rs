@@ -24,10 +24,10 @@ impl Echo {
     }
 
     pub fn parse_args(parser: &mut Parser) -> Result<Echo> {
-        match parser.next_string() {
-            Ok(msg) => Ok(Echo::new(msg.into())),
-            Err(e) => Err(e.into())
-        }
+        let msg = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Echo::new(msg))
     }
 
     pub fn apply(&self) -> Frame {
@@ -1,16 +1,17 @@
 use anyhow::Result;
+use bytes::Bytes;
 
 use crate::redis::{
     db::Db,
     frame::Frame,
     parser::Parser,
 };
-use crate::redis::cmd::ClientCmd;
+use crate::redis::cmd::{ClientCmd, CommandError};
 use crate::redis::utils::Named;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Get {
-    key: String,
+    key: Bytes,
 }
 
 impl Named for Get {
@@ -18,21 +19,22 @@ impl Named for Get {
 }
 
 impl Get {
-    pub fn new(key: String) -> Get {
+    pub fn new(key: Bytes) -> Get {
         Get { key }
     }
 
     pub fn parse_args(parser: &mut Parser) -> Result<Get> {
-        match parser.next_string() {
-            Ok(key) => Ok(Get::new(key)),
-            Err(e) => Err(e.into())
-        }
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Get::new(key))
     }
 
     pub fn apply(&self, db: &mut Db) -> Frame {
         match db.get(&self.key) {
-            Some(data) => Frame::Bulk(data),
-            None => Frame::Null,
+            Ok(Some(data)) => Frame::Bulk(data),
+            Ok(None) => Frame::Null,
+            Err(e) => CommandError::from(e).to_frame(),
         }
     }
 }
@@ -42,7 +44,7 @@ impl ClientCmd for Get {
         let mut frame = Frame::array();
 
         frame.add(Frame::Bulk(Get::NAME.into()));
-        frame.add(Frame::Bulk(self.key.clone().into()));
+        frame.add(Frame::Bulk(self.key.clone()));
 
         frame
     }
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hdel {
+    key: Bytes,
+    fields: Vec<Bytes>,
+}
+
+impl Named for Hdel {
+    const NAME: &'static str = "HDEL";
+}
+
+impl Hdel {
+    pub fn new(key: Bytes, fields: Vec<Bytes>) -> Hdel {
+        Hdel { key, fields }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hdel> {
+        let key = parser.next_bytes()?;
+        let mut fields = vec![parser.next_bytes()?];
+
+        loop {
+            match parser.next_bytes() {
+                Ok(field) => fields.push(field),
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Hdel::new(key, fields))
+    }
+
+    // number of fields removed, the key goes with the last one
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_hash_mut()?,
+                None => return Ok(Frame::Integer(0)),
+            };
+
+            let removed = self.fields.iter().filter(|field| hash.remove(field).is_some()).count();
+            if removed == 0 {
+                keyspace.unchanged();
+            }
+
+            Ok(Frame::Integer(removed as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hdel {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hdel::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for field in &self.fields {
+            frame.add(Frame::Bulk(field.clone()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hexists {
+    key: Bytes,
+    field: Bytes,
+}
+
+impl Named for Hexists {
+    const NAME: &'static str = "HEXISTS";
+}
+
+impl Hexists {
+    pub fn new(key: Bytes, field: Bytes) -> Hexists {
+        Hexists { key, field }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hexists> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hexists::new(key, field))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let exists = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?.contains_key(&self.field),
+                None => false,
+            };
+
+            Ok(Frame::Integer(exists as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hexists {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hexists::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::redis::{
+    cmd::{set::Expiry, ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+// field deadlines are kept to 48 bits of milliseconds, as in redis
+const EXPIRE_MAX_MILLIS: u64 = (1 << 48) - 1;
+
+// HEXPIRE, HPEXPIRE, HEXPIREAT, HPEXPIREAT
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hexpire {
+    key: Bytes,
+    expire: Expiry,
+    condition: Option<Condition>,
+    fields: Vec<Bytes>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Condition {
+    // only fields without a TTL
+    Nx,
+    // only fields with a TTL
+    Xx,
+    // only a later deadline, a field without TTL never expires so it can't get one
+    Gt,
+    // only an earlier deadline, fields without TTL always get one
+    Lt,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExpireArg {
+    Seconds,
+    Millis,
+    UnixSeconds,
+    UnixMillis,
+}
+
+impl Named for Hexpire {
+    const NAME: &'static str = "HEXPIRE";
+}
+
+impl Hexpire {
+    pub fn new(key: Bytes, expire: Expiry, condition: Option<Condition>, fields: Vec<Bytes>) -> Hexpire {
+        Hexpire { key, expire, condition, fields }
+    }
+
+    pub fn parse_args(arg: ExpireArg, parser: &mut Parser) -> Result<Hexpire> {
+        let key = parser.next_bytes()?;
+        let expire = parse_expire(arg, parser.next_signed_int()?)?;
+
+        let condition = match &parser.next_string()?.to_uppercase()[..] {
+            "FIELDS" => None,
+            option => {
+                let condition = match option {
+                    "NX" => Condition::Nx,
+                    "XX" => Condition::Xx,
+                    "GT" => Condition::Gt,
+                    "LT" => Condition::Lt,
+                    _ => return Err(super::fields_missing().into()),
+                };
+                if !parser.next_string()?.eq_ignore_ascii_case("FIELDS") {
+                    return Err(super::fields_missing().into());
+                }
+
+                Some(condition)
+            }
+        };
+        let fields = super::parse_fields(parser)?;
+
+        Ok(Hexpire::new(key, expire, condition, fields))
+    }
+
+    // relative expiry turned into an absolute one, so replicas expire the
+    // fields at the same moment however late they apply the command
+    pub fn absolute(&self) -> Hexpire {
+        let expire = match self.expire {
+            Expiry::In(duration) => Expiry::At(SystemTime::now() + duration),
+            ref expire => expire.clone(),
+        };
+
+        Hexpire { expire, ..self.clone() }
+    }
+
+    // per field: -2 no such field, 0 condition not met, 1 deadline set,
+    // 2 deleted right away as the deadline already passed
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let now = Instant::now();
+            let left = match self.expire {
+                Expiry::In(duration) => duration,
+                Expiry::At(at) => at.duration_since(SystemTime::now()).unwrap_or_default(),
+            };
+            let at = now + left;
+
+            let hash = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_hash_mut()?,
+                None => return Ok(Frame::Array(vec![Frame::Integer(-2); self.fields.len()])),
+            };
+
+            let mut replies = Vec::with_capacity(self.fields.len());
+            let mut expiring = vec![];
+
+            for field in &self.fields {
+                if !hash.contains_key(field) {
+                    replies.push(Frame::Integer(-2));
+                    continue;
+                }
+
+                let current = hash.expires_at(field);
+                let allowed = match self.condition {
+                    None => true,
+                    Some(Condition::Nx) => current.is_none(),
+                    Some(Condition::Xx) => current.is_some(),
+                    Some(Condition::Gt) => current.is_some_and(|current| at > current),
+                    Some(Condition::Lt) => current.is_none_or(|current| at < current),
+                };
+
+                if !allowed {
+                    replies.push(Frame::Integer(0));
+                } else if left.is_zero() {
+                    hash.remove(field);
+                    replies.push(Frame::Integer(2));
+                } else {
+                    expiring.push(field);
+                    replies.push(Frame::Integer(1));
+                }
+            }
+
+            if expiring.is_empty() && !replies.contains(&Frame::Integer(2)) {
+                keyspace.unchanged();
+            }
+            for field in expiring {
+                keyspace.expire_field(&self.key, field, at);
+            }
+
+            Ok(Frame::Array(replies))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+fn parse_expire(arg: ExpireArg, value: i64) -> Result<Expiry, CommandError> {
+    let name = match arg {
+        ExpireArg::Seconds => "hexpire",
+        ExpireArg::Millis => "hpexpire",
+        ExpireArg::UnixSeconds => "hexpireat",
+        ExpireArg::UnixMillis => "hpexpireat",
+    };
+    let out_of_range = || CommandError::Other(format!("invalid expire time in '{}' command", name));
+
+    if value < 0 {
+        return Err(CommandError::Other("invalid expire time, must be >= 0".to_string()));
+    }
+
+    let millis = match arg {
+        ExpireArg::Seconds | ExpireArg::UnixSeconds => value.checked_mul(1000).ok_or_else(out_of_range)?,
+        ExpireArg::Millis | ExpireArg::UnixMillis => value,
+    } as u64;
+    if millis > EXPIRE_MAX_MILLIS {
+        return Err(out_of_range());
+    }
+
+    let expire = match arg {
+        ExpireArg::Seconds | ExpireArg::Millis => Expiry::In(Duration::from_millis(millis)),
+        ExpireArg::UnixSeconds | ExpireArg::UnixMillis => Expiry::At(UNIX_EPOCH + Duration::from_millis(millis)),
+    };
+
+    Ok(expire)
+}
+
+impl ClientCmd for Hexpire {
+    fn to_frame(&self) -> Frame {
+        let (name, millis) = match self.expire {
+            Expiry::In(duration) => ("HPEXPIRE", duration.as_millis()),
+            Expiry::At(at) => ("HPEXPIREAT", at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()),
+        };
+
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(name.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(millis.to_string().into()));
+        if let Some(condition) = self.condition {
+            let condition = match condition {
+                Condition::Nx => "NX",
+                Condition::Xx => "XX",
+                Condition::Gt => "GT",
+                Condition::Lt => "LT",
+            };
+            frame.add(Frame::Bulk(condition.into()));
+        }
+        super::add_fields(&mut frame, &self.fields);
+
+        frame
+    }
+}
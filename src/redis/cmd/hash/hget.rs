@@ -0,0 +1,57 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hget {
+    key: Bytes,
+    field: Bytes,
+}
+
+impl Named for Hget {
+    const NAME: &'static str = "HGET";
+}
+
+impl Hget {
+    pub fn new(key: Bytes, field: Bytes) -> Hget {
+        Hget { key, field }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hget> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hget::new(key, field))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let value = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?.get(&self.field),
+                None => None,
+            };
+
+            Ok(super::bulk_or_null(value))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hget {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hget::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+
+        frame
+    }
+}
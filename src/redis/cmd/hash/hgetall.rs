@@ -0,0 +1,79 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+// HGETALL, HKEYS, HVALS
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hgetall {
+    part: Part,
+    key: Bytes,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Part {
+    Fields,
+    Values,
+    // a map for RESP3 clients, flattened to field/value pairs for RESP2
+    Both,
+}
+
+impl Named for Hgetall {
+    const NAME: &'static str = "HGETALL";
+}
+
+impl Hgetall {
+    pub fn new(part: Part, key: Bytes) -> Hgetall {
+        Hgetall { part, key }
+    }
+
+    pub fn parse_args(part: Part, parser: &mut Parser) -> Result<Hgetall> {
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hgetall::new(part, key))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?,
+                None if self.part == Part::Both => return Ok(Frame::Map(vec![])),
+                None => return Ok(Frame::array()),
+            };
+
+            let frame = match self.part {
+                Part::Fields => Frame::Array(hash.keys().cloned().map(Frame::Bulk).collect()),
+                Part::Values => Frame::Array(hash.values().cloned().map(Frame::Bulk).collect()),
+                Part::Both => Frame::Map(hash.iter().map(|(field, value)| {
+                    (Frame::Bulk(field.clone()), Frame::Bulk(value.clone()))
+                }).collect()),
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hgetall {
+    fn to_frame(&self) -> Frame {
+        let name = match self.part {
+            Part::Fields => "HKEYS",
+            Part::Values => "HVALS",
+            Part::Both => "HGETALL",
+        };
+
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(name.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Hash, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hincrby {
+    key: Bytes,
+    field: Bytes,
+    increment: i64,
+}
+
+impl Named for Hincrby {
+    const NAME: &'static str = "HINCRBY";
+}
+
+impl Hincrby {
+    pub fn new(key: Bytes, field: Bytes, increment: i64) -> Hincrby {
+        Hincrby { key, field, increment }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hincrby> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        let increment = parser.next_signed_int()?;
+        parser.finish()?;
+
+        Ok(Hincrby::new(key, field, increment))
+    }
+
+    // a missing field counts as 0
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = keyspace.get_or_insert(&self.key, || Value::Hash(Hash::new())).as_hash_mut()?;
+
+            let current: i64 = match hash.get(&self.field) {
+                Some(value) => std::str::from_utf8(value).ok()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| CommandError::Other("hash value is not an integer".to_string()))?,
+                None => 0,
+            };
+
+            let result = current.checked_add(self.increment)
+                .ok_or_else(|| CommandError::Other("increment or decrement would overflow".to_string()))?;
+            hash.update(self.field.clone(), result.to_string().into());
+
+            Ok(Frame::Integer(result))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hincrby {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hincrby::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+        frame.add(Frame::Bulk(self.increment.to_string().into()));
+
+        frame
+    }
+}
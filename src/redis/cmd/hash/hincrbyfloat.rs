@@ -0,0 +1,93 @@
+use std::time::SystemTime;
+
+use anyhow::Result;
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::redis::{
+    cmd::{set::Expiry, ClientCmd, CommandError, Hexpire, Hset},
+    db::{Db, Hash, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hincrbyfloat {
+    key: Bytes,
+    field: Bytes,
+    increment: f64,
+}
+
+impl Named for Hincrbyfloat {
+    const NAME: &'static str = "HINCRBYFLOAT";
+}
+
+impl Hincrbyfloat {
+    pub fn new(key: Bytes, field: Bytes, increment: f64) -> Hincrbyfloat {
+        Hincrbyfloat { key, field, increment }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hincrbyfloat> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        let increment = parse_float(&parser.next_bytes()?)
+            .ok_or_else(|| CommandError::Other("value is not a valid float".to_string()))?;
+        parser.finish()?;
+
+        Ok(Hincrbyfloat::new(key, field, increment))
+    }
+
+    // the new value as a bulk string, a missing field counts as 0, along with
+    // the writes replicas get instead: HSET of that value, as they could round
+    // the sum differently, and HPEXPIREAT to give the field its TTL back
+    pub fn apply(&self, db: &mut Db) -> (Frame, Vec<Frame>) {
+        db.with_keyspace(|keyspace| {
+            let hash = keyspace.get_or_insert(&self.key, || Value::Hash(Hash::new())).as_hash_mut()?;
+
+            let current = match hash.get(&self.field) {
+                Some(value) => parse_float(value)
+                    .ok_or_else(|| CommandError::Other("hash value is not a float".to_string()))?,
+                None => 0.0,
+            };
+
+            let result = current + self.increment;
+            if !result.is_finite() {
+                return Err(CommandError::Other("increment would produce NaN or Infinity".to_string()));
+            }
+
+            // shortest form that reads back as the same double, `3` rather than `3.0`
+            let result = Bytes::from(result.to_string());
+            hash.update(self.field.clone(), result.clone());
+
+            let mut writes = vec![
+                Hset::new(self.key.clone(), vec![(self.field.clone(), result.clone())]).to_frame(),
+            ];
+            if let Some(at) = hash.expires_at(&self.field) {
+                let at = SystemTime::now() + at.saturating_duration_since(Instant::now());
+                let hexpire = Hexpire::new(self.key.clone(), Expiry::At(at), None, vec![self.field.clone()]);
+                writes.push(hexpire.to_frame());
+            }
+
+            Ok((Frame::Bulk(result), writes))
+        }).unwrap_or_else(|e: CommandError| (e.to_frame(), vec![]))
+    }
+}
+
+// finite numbers only, as stored values can't hold nan or inf
+fn parse_float(value: &[u8]) -> Option<f64> {
+    std::str::from_utf8(value).ok()?.parse().ok().filter(|value: &f64| value.is_finite())
+}
+
+impl ClientCmd for Hincrbyfloat {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hincrbyfloat::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+        frame.add(Frame::Bulk(self.increment.to_string().into()));
+
+        frame
+    }
+}
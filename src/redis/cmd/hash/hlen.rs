@@ -0,0 +1,54 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hlen {
+    key: Bytes,
+}
+
+impl Named for Hlen {
+    const NAME: &'static str = "HLEN";
+}
+
+impl Hlen {
+    pub fn new(key: Bytes) -> Hlen {
+        Hlen { key }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hlen> {
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hlen::new(key))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let len = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?.len(),
+                None => 0,
+            };
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hlen {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hlen::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hmget {
+    key: Bytes,
+    fields: Vec<Bytes>,
+}
+
+impl Named for Hmget {
+    const NAME: &'static str = "HMGET";
+}
+
+impl Hmget {
+    pub fn new(key: Bytes, fields: Vec<Bytes>) -> Hmget {
+        Hmget { key, fields }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hmget> {
+        let key = parser.next_bytes()?;
+        let mut fields = vec![parser.next_bytes()?];
+
+        loop {
+            match parser.next_bytes() {
+                Ok(field) => fields.push(field),
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Hmget::new(key, fields))
+    }
+
+    // a null for every field that isn't there
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get(&self.key) {
+                Some(value) => Some(value.as_hash()?),
+                None => None,
+            };
+
+            Ok(Frame::Array(self.fields.iter().map(|field| {
+                super::bulk_or_null(hash.and_then(|hash| hash.get(field)))
+            }).collect()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hmget {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hmget::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for field in &self.fields {
+            frame.add(Frame::Bulk(field.clone()));
+        }
+
+        frame
+    }
+}
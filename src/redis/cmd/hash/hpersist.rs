@@ -0,0 +1,73 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hpersist {
+    key: Bytes,
+    fields: Vec<Bytes>,
+}
+
+impl Named for Hpersist {
+    const NAME: &'static str = "HPERSIST";
+}
+
+impl Hpersist {
+    pub fn new(key: Bytes, fields: Vec<Bytes>) -> Hpersist {
+        Hpersist { key, fields }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hpersist> {
+        let key = parser.next_bytes()?;
+        if !parser.next_string()?.eq_ignore_ascii_case("FIELDS") {
+            return Err(super::fields_missing().into());
+        }
+        let fields = super::parse_fields(parser)?;
+
+        Ok(Hpersist::new(key, fields))
+    }
+
+    // per field: -2 no such field, -1 no TTL to remove, 1 TTL removed
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_hash_mut()?,
+                None => return Ok(Frame::Array(vec![Frame::Integer(-2); self.fields.len()])),
+            };
+
+            let replies: Vec<Frame> = self.fields.iter().map(|field| {
+                if !hash.contains_key(field) {
+                    Frame::Integer(-2)
+                } else if hash.persist(field) {
+                    Frame::Integer(1)
+                } else {
+                    Frame::Integer(-1)
+                }
+            }).collect();
+            if !replies.contains(&Frame::Integer(1)) {
+                keyspace.unchanged();
+            }
+
+            Ok(Frame::Array(replies))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hpersist {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hpersist::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        super::add_fields(&mut frame, &self.fields);
+
+        frame
+    }
+}
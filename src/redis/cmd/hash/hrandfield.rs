@@ -0,0 +1,108 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::{pick_iter, sample_iter, Named, MAX_PICKS},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hrandfield {
+    key: Bytes,
+    // with a count the reply is an array; distinct fields when positive,
+    // possibly repeated ones when negative
+    count: Option<i64>,
+    withvalues: bool,
+}
+
+impl Named for Hrandfield {
+    const NAME: &'static str = "HRANDFIELD";
+}
+
+impl Hrandfield {
+    pub fn new(key: Bytes, count: Option<i64>, withvalues: bool) -> Hrandfield {
+        Hrandfield { key, count, withvalues }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hrandfield> {
+        let key = parser.next_bytes()?;
+
+        let count = match parser.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParserError::EndOfStream) => return Ok(Hrandfield::new(key, None, false)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let withvalues = match parser.next_string() {
+            Ok(option) if option.eq_ignore_ascii_case("withvalues") => true,
+            Ok(_) => return Err(CommandError::Syntax.into()),
+            Err(ParserError::EndOfStream) => false,
+            Err(e) => return Err(e.into()),
+        };
+        if parser.finish().is_err() {
+            return Err(CommandError::Syntax.into());
+        }
+        if count.is_some_and(|count| count < 0 && count.unsigned_abs() > MAX_PICKS) {
+            return Err(CommandError::Other("value is out of range".to_string()).into());
+        }
+
+        Ok(Hrandfield::new(key, count, withvalues))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?,
+                None if self.count.is_some() => return Ok(Frame::array()),
+                None => return Ok(Frame::Null),
+            };
+
+            let count = match self.count {
+                // a replica may hold nothing but fields past their deadline
+                None => return Ok(sample_iter(hash.keys(), 1).pop().cloned().map_or(Frame::Null, Frame::Bulk)),
+                Some(count) => count,
+            };
+
+            let picked: Vec<(&Bytes, &Bytes)> = if count >= 0 {
+                sample_iter(hash.iter(), count as usize)
+            } else {
+                // `len` counts the hidden fields too
+                let len = hash.iter().count();
+                if len == 0 {
+                    return Ok(Frame::array());
+                }
+                pick_iter(hash.iter(), len, count.unsigned_abs() as usize)
+            };
+
+            let mut frame = Frame::array();
+            for (field, value) in picked {
+                frame.add(Frame::Bulk(field.clone()));
+                if self.withvalues {
+                    frame.add(Frame::Bulk(value.clone()));
+                }
+            }
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hrandfield {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hrandfield::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        if let Some(count) = self.count {
+            frame.add(Frame::Bulk(count.to_string().into()));
+        }
+        if self.withvalues {
+            frame.add(Frame::Bulk("WITHVALUES".into()));
+        }
+
+        frame
+    }
+}
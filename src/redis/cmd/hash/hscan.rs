@@ -0,0 +1,66 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{scan::{self, ScanArgs}, ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hscan {
+    key: Bytes,
+    args: ScanArgs,
+}
+
+impl Named for Hscan {
+    const NAME: &'static str = "HSCAN";
+}
+
+impl Hscan {
+    pub fn new(key: Bytes, args: ScanArgs) -> Hscan {
+        Hscan { key, args }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hscan> {
+        let key = parser.next_bytes()?;
+        let args = ScanArgs::parse(parser, true)?;
+
+        Ok(Hscan::new(key, args))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?,
+                None => return Ok(scan::reply(0, vec![])),
+            };
+
+            let (cursor, fields) = self.args.scan(hash.keys());
+
+            let mut items = vec![];
+            for field in fields {
+                items.push(Frame::Bulk(field.clone()));
+                if !self.args.novalues {
+                    items.push(Frame::Bulk(hash[field].clone()));
+                }
+            }
+
+            Ok(scan::reply(cursor, items))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hscan {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hscan::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        self.args.add_to(&mut frame);
+
+        frame
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Hash, Value},
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hset {
+    key: Bytes,
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+impl Named for Hset {
+    const NAME: &'static str = "HSET";
+}
+
+impl Hset {
+    pub fn new(key: Bytes, pairs: Vec<(Bytes, Bytes)>) -> Hset {
+        Hset { key, pairs }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hset> {
+        let key = parser.next_bytes()?;
+        let mut pairs = vec![(parser.next_bytes()?, parser.next_bytes()?)];
+
+        loop {
+            let field = match parser.next_bytes() {
+                Ok(field) => field,
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            };
+            pairs.push((field, parser.next_bytes()?));
+        }
+
+        Ok(Hset::new(key, pairs))
+    }
+
+    // number of fields that were added rather than updated
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = keyspace.get_or_insert(&self.key, || Value::Hash(Hash::new())).as_hash_mut()?;
+
+            let added = self.pairs.iter()
+                .filter(|(field, value)| hash.insert(field.clone(), value.clone()).is_none())
+                .count();
+
+            Ok(Frame::Integer(added as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hset {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hset::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for (field, value) in &self.pairs {
+            frame.add(Frame::Bulk(field.clone()));
+            frame.add(Frame::Bulk(value.clone()));
+        }
+
+        frame
+    }
+}
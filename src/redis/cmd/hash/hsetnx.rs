@@ -0,0 +1,63 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Hash, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hsetnx {
+    key: Bytes,
+    field: Bytes,
+    value: Bytes,
+}
+
+impl Named for Hsetnx {
+    const NAME: &'static str = "HSETNX";
+}
+
+impl Hsetnx {
+    pub fn new(key: Bytes, field: Bytes, value: Bytes) -> Hsetnx {
+        Hsetnx { key, field, value }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hsetnx> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        let value = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hsetnx::new(key, field, value))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = keyspace.get_or_insert(&self.key, || Value::Hash(Hash::new())).as_hash_mut()?;
+
+            if hash.contains_key(&self.field) {
+                keyspace.unchanged();
+                return Ok(Frame::Integer(0));
+            }
+            hash.insert(self.field.clone(), self.value.clone());
+
+            Ok(Frame::Integer(1))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hsetnx {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hsetnx::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+        frame.add(Frame::Bulk(self.value.clone()));
+
+        frame
+    }
+}
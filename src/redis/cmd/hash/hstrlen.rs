@@ -0,0 +1,57 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hstrlen {
+    key: Bytes,
+    field: Bytes,
+}
+
+impl Named for Hstrlen {
+    const NAME: &'static str = "HSTRLEN";
+}
+
+impl Hstrlen {
+    pub fn new(key: Bytes, field: Bytes) -> Hstrlen {
+        Hstrlen { key, field }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hstrlen> {
+        let key = parser.next_bytes()?;
+        let field = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Hstrlen::new(key, field))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let len = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?.get(&self.field).map_or(0, |value| value.len()),
+                None => 0,
+            };
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Hstrlen {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hstrlen::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.field.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+// HTTL, HPTTL, HEXPIRETIME, HPEXPIRETIME
+#[derive(Debug, PartialEq, Clone)]
+pub struct Httl {
+    key: Bytes,
+    report: TtlReport,
+    fields: Vec<Bytes>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TtlReport {
+    Seconds,
+    Millis,
+    UnixSeconds,
+    UnixMillis,
+}
+
+impl Named for Httl {
+    const NAME: &'static str = "HTTL";
+}
+
+impl Httl {
+    pub fn new(key: Bytes, report: TtlReport, fields: Vec<Bytes>) -> Httl {
+        Httl { key, report, fields }
+    }
+
+    pub fn parse_args(report: TtlReport, parser: &mut Parser) -> Result<Httl> {
+        let key = parser.next_bytes()?;
+        if !parser.next_string()?.eq_ignore_ascii_case("FIELDS") {
+            return Err(super::fields_missing().into());
+        }
+        let fields = super::parse_fields(parser)?;
+
+        Ok(Httl::new(key, report, fields))
+    }
+
+    // per field: -2 no such field, -1 no TTL, the TTL or deadline otherwise
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let hash = match keyspace.get(&self.key) {
+                Some(value) => value.as_hash()?,
+                None => return Ok(Frame::Array(vec![Frame::Integer(-2); self.fields.len()])),
+            };
+
+            let now = Instant::now();
+            let wall_now = SystemTime::now();
+
+            Ok(Frame::Array(self.fields.iter().map(|field| {
+                if !hash.contains_key(field) {
+                    return Frame::Integer(-2);
+                }
+                let at = match hash.expires_at(field) {
+                    Some(at) => at,
+                    None => return Frame::Integer(-1),
+                };
+
+                let left = at.saturating_duration_since(now);
+                let unix = || (wall_now + left).duration_since(UNIX_EPOCH).unwrap_or_default();
+
+                // seconds are rounded up, a field about to expire still has 1 left
+                let value = match self.report {
+                    TtlReport::Seconds => left.as_millis().div_ceil(1000),
+                    TtlReport::Millis => left.as_millis(),
+                    TtlReport::UnixSeconds => unix().as_millis().div_ceil(1000),
+                    TtlReport::UnixMillis => unix().as_millis(),
+                };
+
+                Frame::Integer(value as i64)
+            }).collect()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Httl {
+    fn to_frame(&self) -> Frame {
+        let name = match self.report {
+            TtlReport::Seconds => "HTTL",
+            TtlReport::Millis => "HPTTL",
+            TtlReport::UnixSeconds => "HEXPIRETIME",
+            TtlReport::UnixMillis => "HPEXPIRETIME",
+        };
+
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(name.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        super::add_fields(&mut frame, &self.fields);
+
+        frame
+    }
+}
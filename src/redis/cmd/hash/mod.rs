@@ -0,0 +1,77 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{cmd::CommandError, frame::Frame, parser::Parser};
+
+pub use hdel::Hdel;
+pub use hexists::Hexists;
+pub use hexpire::{ExpireArg, Hexpire};
+pub use hget::Hget;
+pub use hgetall::{Hgetall, Part};
+pub use hincrby::Hincrby;
+pub use hincrbyfloat::Hincrbyfloat;
+pub use hlen::Hlen;
+pub use hmget::Hmget;
+pub use hpersist::Hpersist;
+pub use hrandfield::Hrandfield;
+pub use hscan::Hscan;
+pub use hset::Hset;
+pub use hsetnx::Hsetnx;
+pub use hstrlen::Hstrlen;
+pub use httl::{Httl, TtlReport};
+
+mod hdel;
+mod hexists;
+mod hexpire;
+mod hget;
+mod hgetall;
+mod hincrby;
+mod hincrbyfloat;
+mod hlen;
+mod hmget;
+mod hpersist;
+mod hrandfield;
+mod hscan;
+mod hset;
+mod hsetnx;
+mod hstrlen;
+mod httl;
+
+fn bulk_or_null(value: Option<&Bytes>) -> Frame {
+    value.cloned().map(Frame::Bulk).unwrap_or(Frame::Null)
+}
+
+// `numfields field [field ...]`, following the FIELDS keyword
+fn parse_fields(parser: &mut Parser) -> Result<Vec<Bytes>> {
+    let numfields = parser.next_int().unwrap_or(0);
+    if numfields == 0 {
+        return Err(CommandError::Other("Parameter `numFields` should be greater than 0".to_string()).into());
+    }
+
+    let mut fields = vec![];
+    for _ in 0..numfields {
+        fields.push(parser.next_bytes().map_err(|_| numfields_mismatch())?);
+    }
+    parser.finish().map_err(|_| numfields_mismatch())?;
+
+    Ok(fields)
+}
+
+fn add_fields(frame: &mut Frame, fields: &[Bytes]) {
+    frame.add(Frame::Bulk("FIELDS".into()));
+    frame.add(Frame::Bulk(fields.len().to_string().into()));
+    for field in fields {
+        frame.add(Frame::Bulk(field.clone()));
+    }
+}
+
+fn fields_missing() -> CommandError {
+    CommandError::Other("Mandatory argument FIELDS is missing or not at the right position".to_string())
+}
+
+fn numfields_mismatch() -> CommandError {
+    CommandError::Other("The `numfields` parameter must match the number of arguments".to_string())
+}
+
+#[cfg(test)]
+mod tests;
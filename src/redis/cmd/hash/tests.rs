@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use tokio::time::{sleep, Duration};
+
+use crate::redis::cmd::tests::{bulks, items, prepare_conn, send, sorted, start_server};
+use crate::redis::cmd::{ClientCmd, Command, Psync};
+use crate::redis::frame::Frame;
+
+fn map(pairs: &[(&str, &str)]) -> Frame {
+    Frame::Map(pairs.iter().map(|(field, value)| {
+        (Frame::Bulk(field.to_string().into()), Frame::Bulk(value.to_string().into()))
+    }).collect())
+}
+
+#[tokio::test]
+async fn test_set_get_del() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(send(&mut conn, &["HSET", "user", "name", "ann", "age", "30"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["HSET", "user", "age", "31", "city", "oslo"]).await, Frame::Integer(1));
+    assert_eq!(
+        send(&mut conn, &["HSET", "user", "odd"]).await,
+        Frame::Error("ERR wrong number of arguments for 'hset' command".to_string()),
+    );
+
+    assert_eq!(send(&mut conn, &["HGET", "user", "age"]).await, Frame::Bulk("31".into()));
+    assert_eq!(send(&mut conn, &["HGET", "user", "missing"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["HGET", "nohash", "age"]).await, Frame::Null);
+    assert_eq!(
+        send(&mut conn, &["HMGET", "user", "name", "missing", "city"]).await,
+        Frame::Array(vec![Frame::Bulk("ann".into()), Frame::Null, Frame::Bulk("oslo".into())]),
+    );
+    assert_eq!(send(&mut conn, &["HLEN", "user"]).await, Frame::Integer(3));
+    assert_eq!(send(&mut conn, &["HEXISTS", "user", "city"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["HEXISTS", "user", "zip"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["HSTRLEN", "user", "city"]).await, Frame::Integer(4));
+    assert_eq!(send(&mut conn, &["HSTRLEN", "user", "zip"]).await, Frame::Integer(0));
+
+    assert_eq!(send(&mut conn, &["HSETNX", "user", "name", "bob"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["HSETNX", "user", "zip", "0150"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["HGET", "user", "name"]).await, Frame::Bulk("ann".into()));
+
+    assert_eq!(send(&mut conn, &["HDEL", "user", "zip", "city", "nope"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["HDEL", "user", "name", "age"]).await, Frame::Integer(2));
+    // the last field takes the key with it
+    assert_eq!(send(&mut conn, &["HLEN", "user"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["RPUSH", "user", "a"]).await, Frame::Integer(1));
+}
+
+#[tokio::test]
+async fn test_getall_keys_vals() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["HSET", "h", "a", "1", "b", "2"]).await;
+
+    assert_eq!(sorted(send(&mut conn, &["HKEYS", "h"]).await), sorted(bulks(&["a", "b"])));
+    assert_eq!(sorted(send(&mut conn, &["HVALS", "h"]).await), sorted(bulks(&["1", "2"])));
+    assert_eq!(send(&mut conn, &["HKEYS", "missing"]).await, Frame::array());
+
+    // flattened pairs for RESP2, a map for RESP3
+    let pairs: Vec<Frame> = items(send(&mut conn, &["HGETALL", "h"]).await).chunks(2)
+        .map(|pair| Frame::Array(pair.to_vec()))
+        .collect();
+    assert_eq!(sorted(Frame::Array(pairs)), vec![bulks(&["a", "1"]), bulks(&["b", "2"])]);
+
+    send(&mut conn, &["HELLO", "3"]).await;
+    assert!(matches!(send(&mut conn, &["HGETALL", "h"]).await, Frame::Map(pairs) if pairs.len() == 2));
+    assert_eq!(send(&mut conn, &["HGETALL", "missing"]).await, map(&[]));
+}
+
+#[tokio::test]
+async fn test_incr() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(send(&mut conn, &["HINCRBY", "h", "n", "5"]).await, Frame::Integer(5));
+    assert_eq!(send(&mut conn, &["HINCRBY", "h", "n", "-7"]).await, Frame::Integer(-2));
+    send(&mut conn, &["HSET", "h", "s", "abc", "max", &i64::MAX.to_string()]).await;
+    assert_eq!(
+        send(&mut conn, &["HINCRBY", "h", "s", "1"]).await,
+        Frame::Error("ERR hash value is not an integer".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["HINCRBY", "h", "max", "1"]).await,
+        Frame::Error("ERR increment or decrement would overflow".to_string()),
+    );
+
+    assert_eq!(send(&mut conn, &["HINCRBYFLOAT", "h", "f", "10.5"]).await, Frame::Bulk("10.5".into()));
+    assert_eq!(send(&mut conn, &["HINCRBYFLOAT", "h", "f", "0.1"]).await, Frame::Bulk("10.6".into()));
+    assert_eq!(send(&mut conn, &["HINCRBYFLOAT", "h", "n", "2"]).await, Frame::Bulk("0".into()));
+    assert_eq!(
+        send(&mut conn, &["HINCRBYFLOAT", "h", "s", "1"]).await,
+        Frame::Error("ERR hash value is not a float".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["HINCRBYFLOAT", "h", "f", "nan"]).await,
+        Frame::Error("ERR value is not a valid float".to_string()),
+    );
+    send(&mut conn, &["HSET", "h", "big", "1.7e308"]).await;
+    assert_eq!(
+        send(&mut conn, &["HINCRBYFLOAT", "h", "big", "1.7e308"]).await,
+        Frame::Error("ERR increment would produce NaN or Infinity".to_string()),
+    );
+}
+
+// replicas get the value the master computed, and nothing for writes that
+// failed or changed nothing
+#[tokio::test]
+async fn test_incrbyfloat_propagated_as_hset() {
+    let addr = start_server().await;
+    let mut client = prepare_conn(addr).await;
+
+    let mut replica = prepare_conn(addr).await;
+    replica.write_frame(&Psync::default().to_frame()).await.unwrap();
+    replica.read_frame().await.unwrap().unwrap();
+    replica.read_rdb().await.unwrap().unwrap();
+
+    send(&mut client, &["HSET", "h", "f", "1.5"]).await;
+    assert_eq!(replica.read_frame().await.unwrap().unwrap(), bulks(&["HSET", "h", "f", "1.5"]));
+
+    assert_eq!(send(&mut client, &["HDEL", "h", "missing"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut client, &["HSETNX", "h", "f", "0"]).await, Frame::Integer(0));
+    assert!(matches!(send(&mut client, &["SADD", "h", "m"]).await, Frame::Error(_)));
+
+    assert_eq!(send(&mut client, &["HINCRBYFLOAT", "h", "f", "0.1"]).await, Frame::Bulk("1.6".into()));
+    assert_eq!(replica.read_frame().await.unwrap().unwrap(), bulks(&["HSET", "h", "f", "1.6"]));
+
+    // the field keeps its TTL on replicas too
+    send(&mut client, &["HPEXPIREAT", "h", "99999999999999", "FIELDS", "1", "f"]).await;
+    replica.read_frame().await.unwrap().unwrap();
+
+    send(&mut client, &["HINCRBYFLOAT", "h", "f", "1"]).await;
+    assert_eq!(replica.read_frame().await.unwrap().unwrap(), bulks(&["HSET", "h", "f", "2.6"]));
+    let hexpire = items(replica.read_frame().await.unwrap().unwrap());
+    assert_eq!(hexpire[0], Frame::Bulk(Bytes::from_static(b"HPEXPIREAT")));
+    assert_eq!(hexpire[3..], items(bulks(&["FIELDS", "1", "f"]))[..]);
+}
+
+#[tokio::test]
+async fn test_randfield() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["HSET", "h", "a", "1", "b", "2", "c", "3"]).await;
+    let fields = [Frame::Bulk("a".into()), Frame::Bulk("b".into()), Frame::Bulk("c".into())];
+
+    assert!(fields.contains(&send(&mut conn, &["HRANDFIELD", "h"]).await));
+    assert_eq!(send(&mut conn, &["HRANDFIELD", "missing"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["HRANDFIELD", "missing", "2"]).await, Frame::array());
+
+    // distinct fields, never more than there are
+    assert_eq!(sorted(send(&mut conn, &["HRANDFIELD", "h", "10"]).await), fields.to_vec());
+    let two = items(send(&mut conn, &["HRANDFIELD", "h", "2"]).await);
+    assert_eq!(two.len(), 2);
+    assert_ne!(two[0], two[1]);
+
+    // repeats are allowed with a negative count
+    let many = items(send(&mut conn, &["HRANDFIELD", "h", "-7"]).await);
+    assert_eq!(many.len(), 7);
+    assert!(many.iter().all(|field| fields.contains(field)));
+
+    assert_eq!(items(send(&mut conn, &["HRANDFIELD", "h", "-2", "WITHVALUES"]).await).len(), 4);
+    for count in ["-1000000000000", &i64::MIN.to_string()] {
+        assert_eq!(
+            send(&mut conn, &["HRANDFIELD", "h", count]).await,
+            Frame::Error("ERR value is out of range".to_string()),
+        );
+    }
+    assert_eq!(
+        send(&mut conn, &["HRANDFIELD", "h", "1", "WITHSCORES"]).await,
+        Frame::Error("ERR syntax error".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_scan() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    let fields: Vec<String> = (0..50).map(|i| format!("field:{}", i)).collect();
+    let mut args = vec!["HSET", "h"];
+    for field in &fields {
+        args.extend([field.as_str(), "v"]);
+    }
+    send(&mut conn, &args).await;
+
+    let mut seen = HashSet::new();
+    let mut cursor = "0".to_string();
+    let mut calls = 0;
+    loop {
+        let reply = items(send(&mut conn, &["HSCAN", "h", &cursor, "COUNT", "7", "NOVALUES"]).await);
+        for field in items(reply[1].clone()) {
+            match field {
+                Frame::Bulk(field) => seen.insert(field),
+                frame => panic!("not a field: {:?}", frame),
+            };
+        }
+        calls += 1;
+
+        // a field removed halfway doesn't make the scan skip the others
+        if calls == 2 {
+            send(&mut conn, &["HDEL", "h", "field:0"]).await;
+        }
+
+        cursor = match &reply[0] {
+            Frame::Bulk(next) => String::from_utf8(next.to_vec()).unwrap(),
+            frame => panic!("not a cursor: {:?}", frame),
+        };
+        if cursor == "0" {
+            break;
+        }
+    }
+    assert!(calls >= 7);
+    for field in &fields[1..] {
+        assert!(seen.contains(&Bytes::from(field.clone())));
+    }
+
+    let reply = items(send(&mut conn, &["HSCAN", "h", "0", "MATCH", "field:1?", "COUNT", "100"]).await);
+    assert_eq!(reply[0], Frame::Bulk("0".into()));
+    // ten fields and their values
+    assert_eq!(items(reply[1].clone()).len(), 20);
+
+    assert_eq!(
+        send(&mut conn, &["HSCAN", "missing", "0"]).await,
+        Frame::Array(vec![Frame::Bulk("0".into()), Frame::array()]),
+    );
+    assert_eq!(
+        send(&mut conn, &["HSCAN", "h", "nope"]).await,
+        Frame::Error("ERR invalid cursor".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_type() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+    let wrongtype = Frame::Error("WRONGTYPE Operation against a key holding the wrong kind of value".to_string());
+
+    send(&mut conn, &["HSET", "h", "a", "1"]).await;
+    send(&mut conn, &["SET", "string", "x"]).await;
+
+    assert_eq!(send(&mut conn, &["GET", "h"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["LPUSH", "h", "x"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HSET", "string", "a", "1"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HGET", "string", "a"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HGETALL", "string"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HINCRBY", "string", "a", "1"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HSCAN", "string", "0"]).await, wrongtype);
+}
+
+fn ints(values: &[i64]) -> Frame {
+    Frame::Array(values.iter().map(|value| Frame::Integer(*value)).collect())
+}
+
+#[tokio::test]
+async fn test_field_expire() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["HSET", "h", "a", "1", "b", "2", "c", "3"]).await;
+
+    assert_eq!(send(&mut conn, &["HEXPIRE", "h", "100", "FIELDS", "2", "a", "nope"]).await, ints(&[1, -2]));
+    assert_eq!(send(&mut conn, &["HEXPIRE", "nohash", "100", "FIELDS", "1", "a"]).await, ints(&[-2]));
+    assert_eq!(send(&mut conn, &["HTTL", "h", "FIELDS", "3", "a", "b", "nope"]).await, ints(&[100, -1, -2]));
+
+    // conditions against the current TTL
+    assert_eq!(send(&mut conn, &["HEXPIRE", "h", "200", "NX", "FIELDS", "2", "a", "b"]).await, ints(&[0, 1]));
+    assert_eq!(send(&mut conn, &["HEXPIRE", "h", "50", "XX", "FIELDS", "2", "a", "c"]).await, ints(&[1, 0]));
+    assert_eq!(send(&mut conn, &["HEXPIRE", "h", "10", "GT", "FIELDS", "2", "a", "c"]).await, ints(&[0, 0]));
+    assert_eq!(send(&mut conn, &["HEXPIRE", "h", "10", "LT", "FIELDS", "2", "a", "c"]).await, ints(&[1, 1]));
+
+    let pttl = items(send(&mut conn, &["HPTTL", "h", "FIELDS", "1", "a"]).await);
+    assert!(matches!(pttl[0], Frame::Integer(millis) if millis > 9000 && millis <= 10000));
+
+    let now = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs() as i64;
+    let at = items(send(&mut conn, &["HEXPIRETIME", "h", "FIELDS", "1", "b"]).await);
+    assert!(matches!(at[0], Frame::Integer(secs) if secs >= now + 199 && secs <= now + 201));
+
+    assert_eq!(send(&mut conn, &["HPERSIST", "h", "FIELDS", "3", "a", "b", "nope"]).await, ints(&[1, 1, -2]));
+    assert_eq!(send(&mut conn, &["HPERSIST", "h", "FIELDS", "1", "a"]).await, ints(&[-1]));
+    // a new value drops the TTL, an increment keeps it
+    send(&mut conn, &["HSET", "h", "c", "4"]).await;
+    assert_eq!(send(&mut conn, &["HTTL", "h", "FIELDS", "1", "c"]).await, ints(&[-1]));
+
+    // a deadline in the past deletes the field right away
+    assert_eq!(send(&mut conn, &["HEXPIREAT", "h", "1", "FIELDS", "1", "a"]).await, ints(&[2]));
+    assert_eq!(send(&mut conn, &["HEXISTS", "h", "a"]).await, Frame::Integer(0));
+
+    assert_eq!(send(&mut conn, &["HPEXPIRE", "h", "50", "FIELDS", "1", "b"]).await, ints(&[1]));
+    send(&mut conn, &["HINCRBY", "h", "b", "1"]).await;
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(send(&mut conn, &["HGET", "h", "b"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["HGETALL", "h"]).await, bulks(&["c", "4"]));
+
+    // the key goes along with its last field
+    assert_eq!(send(&mut conn, &["HPEXPIRE", "h", "50", "FIELDS", "1", "c"]).await, ints(&[1]));
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(send(&mut conn, &["HLEN", "h"]).await, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn test_field_expire_errors() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+    let error = |message: &str| Frame::Error(format!("ERR {}", message));
+
+    send(&mut conn, &["HSET", "h", "a", "1"]).await;
+
+    assert_eq!(
+        send(&mut conn, &["HEXPIRE", "h", "10", "FIELDS", "0"]).await,
+        error("Parameter `numFields` should be greater than 0"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HEXPIRE", "h", "10", "FIELDS", "2", "a"]).await,
+        error("The `numfields` parameter must match the number of arguments"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HTTL", "h", "FIELDS", "1", "a", "b"]).await,
+        error("The `numfields` parameter must match the number of arguments"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HEXPIRE", "h", "10", "1", "a"]).await,
+        error("Mandatory argument FIELDS is missing or not at the right position"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HPERSIST", "h", "a"]).await,
+        error("Mandatory argument FIELDS is missing or not at the right position"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HEXPIRE", "h", "-1", "FIELDS", "1", "a"]).await,
+        error("invalid expire time, must be >= 0"),
+    );
+    assert_eq!(
+        send(&mut conn, &["HEXPIRE", "h", "9223372036854775807", "FIELDS", "1", "a"]).await,
+        error("invalid expire time in 'hexpire' command"),
+    );
+}
+
+#[test]
+fn test_field_expire_rewrite() {
+    let frame = bulks(&["HEXPIRE", "h", "10", "NX", "FIELDS", "1", "a"]);
+
+    let now = std::time::UNIX_EPOCH.elapsed().unwrap().as_millis() as u64;
+    let (_, propagated) = Command::from_frame(&frame).unwrap().rewrite(frame);
+
+    // replicas get the absolute deadline in milliseconds
+    let propagated = items(propagated);
+    assert_eq!(propagated[0], Frame::Bulk(Bytes::from_static(b"HPEXPIREAT")));
+    assert_eq!(propagated[3..], items(bulks(&["NX", "FIELDS", "1", "a"]))[..]);
+
+    let at: u64 = match &propagated[2] {
+        Frame::Bulk(millis) => std::str::from_utf8(millis).unwrap().parse().unwrap(),
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+    assert!(at >= now + 10_000 && at < now + 11_000);
+}
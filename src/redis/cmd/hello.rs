@@ -0,0 +1,126 @@
+use anyhow::Result;
+
+use crate::redis::{
+    cmd::CommandError,
+    connection::ClientInfo,
+    frame::{Frame, Protocol},
+    parser::{Parser, ParserError},
+    role::Role,
+    ServerInfo,
+    utils::Named,
+};
+
+use super::ClientCmd;
+
+// the only user there is while ACLs are not supported
+const DEFAULT_USER: &str = "default";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hello {
+    pub protover: Option<u64>,
+    pub auth: Option<(String, String)>,
+    pub setname: Option<String>,
+}
+
+impl Named for Hello {
+    const NAME: &'static str = "HELLO";
+}
+
+impl Hello {
+    pub fn new(protover: Option<u64>) -> Hello {
+        Hello { protover, auth: None, setname: None }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Hello> {
+        let protover = match parser.next_string() {
+            Ok(protover) => protover.parse().map_err(|_| CommandError::Other(
+                "Protocol version is not an integer or out of range".to_string()
+            ))?,
+            // plain HELLO just reports the connection state
+            Err(ParserError::EndOfStream) => return Ok(Hello::new(None)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hello = Hello::new(Some(protover));
+
+        while let Ok(option) = parser.next_string() {
+            match option.to_lowercase().as_str() {
+                "auth" => hello.auth = Some((parser.next_string()?, parser.next_string()?)),
+                "setname" => hello.setname = Some(parser.next_string()?),
+                _ => return Err(CommandError::Other(
+                    format!("Syntax error in HELLO option '{}'", option)
+                ).into()),
+            }
+        }
+
+        Ok(hello)
+    }
+
+    // switches the connection protocol, the reply is already written with it
+    pub fn apply(&self, server_info: &ServerInfo, client: &mut ClientInfo, protocol: &mut Protocol) -> Frame {
+        let new_protocol = match self.protover {
+            None => *protocol,
+            Some(2) => Protocol::Resp2,
+            Some(3) => Protocol::Resp3,
+            Some(_) => return Frame::Error("NOPROTO unsupported protocol version".to_string()),
+        };
+
+        if let Some((user, _)) = &self.auth {
+            if user != DEFAULT_USER {
+                return Frame::Error(
+                    "WRONGPASS invalid username-password pair or user is disabled.".to_string()
+                );
+            }
+        }
+
+        if let Some(name) = &self.setname {
+            if let Err(e) = client.set_name(name) {
+                return e.to_frame();
+            }
+        }
+
+        *protocol = new_protocol;
+
+        let role = match server_info.role() {
+            Role::Master => "master",
+            Role::Slave => "replica",
+        };
+        let proto = match new_protocol {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        };
+
+        Frame::Map(vec![
+            (Frame::Bulk("server".into()), Frame::Bulk("redis".into())),
+            (Frame::Bulk("version".into()), Frame::Bulk("7.2.0".into())),
+            (Frame::Bulk("proto".into()), Frame::Integer(proto)),
+            (Frame::Bulk("id".into()), Frame::Integer(client.id as i64)),
+            (Frame::Bulk("mode".into()), Frame::Bulk("standalone".into())),
+            (Frame::Bulk("role".into()), Frame::Bulk(role.into())),
+            (Frame::Bulk("modules".into()), Frame::array()),
+        ])
+    }
+}
+
+impl ClientCmd for Hello {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Hello::NAME.into()));
+
+        if let Some(protover) = self.protover {
+            frame.add(Frame::Bulk(protover.to_string().into()));
+        }
+        if let Some((user, pass)) = &self.auth {
+            frame.add(Frame::Bulk("AUTH".into()));
+            frame.add(Frame::Bulk(user.clone().into()));
+            frame.add(Frame::Bulk(pass.clone().into()));
+        }
+        if let Some(name) = &self.setname {
+            frame.add(Frame::Bulk("SETNAME".into()));
+            frame.add(Frame::Bulk(name.clone().into()));
+        }
+
+        frame
+    }
+}
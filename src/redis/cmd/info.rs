@@ -1,8 +1,10 @@
 use anyhow::Result;
 
-use crate::redis::{frame::Frame, ServerInfo};
+use crate::redis::{db::Db, frame::Frame, ServerInfo};
 use crate::redis::cmd::ClientCmd;
-use crate::redis::utils::Named;
+use crate::redis::replica::LinkState;
+use crate::redis::replicas::ReplicaInfo;
+use crate::redis::utils::{Addr, Named};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Info {}
@@ -20,17 +22,79 @@ impl Info {
         Ok(Info::new())
     }
 
-    pub async fn apply(&self, server_info: &ServerInfo) -> Frame {
-        let string = Info::build_info_string(server_info).await;
+    pub async fn apply(&self, db: &Db, server_info: &ServerInfo) -> Frame {
+        let mut string = Info::build_info_string(server_info).await;
+        string.push_str(&format!("\nexpired_keys:{}", db.expired_keys()));
+
         Frame::Bulk(string.into())
     }
 
     async fn build_info_string(server_info: &ServerInfo) -> String {
+        let replinfo = &server_info.replinfo;
+        let (backlog_size, first_byte_offset, histlen) = {
+            let backlog = replinfo.backlog.lock().unwrap();
+            (backlog.capacity(), backlog.first_byte_offset(), backlog.histlen())
+        };
+
+        let mut info = format!("role:{}\n", server_info.role());
+
+        if let Some(master) = replinfo.master() {
+            info.push_str(&Info::build_link_string(&master, &replinfo.link.lock().unwrap()));
+        }
+
+        info.push_str(&Info::build_replicas_string(&replinfo.replicas.list()));
+
+        let (replid2, second_offset) = match &*replinfo.second_id.read().await {
+            Some((id, offset)) => (id.clone(), *offset as i64),
+            None => ("0".repeat(40), -1),
+        };
+
+        info.push_str(&format!(
+            "master_replid:{replid}\nmaster_replid2:{replid2}\n\
+            master_repl_offset:{reploffset}\nsecond_repl_offset:{second_offset}\n\
+            repl_backlog_active:1\nrepl_backlog_size:{backlog_size}\n\
+            repl_backlog_first_byte_offset:{first_byte_offset}\nrepl_backlog_histlen:{histlen}",
+            replid = replinfo.id.read().await,
+            reploffset = replinfo.offset(),
+        ));
+
+        info
+    }
+
+    fn build_replicas_string(replicas: &[ReplicaInfo]) -> String {
+        let mut info = format!("connected_slaves:{}\n", replicas.len());
+
+        for (i, replica) in replicas.iter().enumerate() {
+            let port = replica.listening_port.map(|port| port.to_string()).unwrap_or_default();
+            let lag = match replica.last_ack {
+                Some(at) => at.elapsed().as_secs() as i64,
+                None => -1,
+            };
+
+            info.push_str(&format!(
+                "slave{i}:ip={ip},port={port},state={state},offset={offset},lag={lag}\n",
+                ip = replica.ip,
+                state = replica.state,
+                offset = replica.ack_offset,
+            ));
+        }
+
+        info
+    }
+
+    fn build_link_string(master: &Addr, link: &LinkState) -> String {
+        let last_io = match link.last_io {
+            Some(at) => at.elapsed().as_secs() as i64,
+            None => -1,
+        };
+
         format!(
-            "role:{role}\nmaster_replid:{replid}\nmaster_repl_offset:{reploffset}",
-            role = server_info.role,
-            replid = server_info.replinfo.id,
-            reploffset = server_info.replinfo.offset.lock().await,
+            "master_host:{host}\nmaster_port:{port}\nmaster_link_status:{status}\n\
+            master_last_io_seconds_ago:{last_io}\nmaster_sync_in_progress:{sync}\n",
+            host = master.host,
+            port = master.port,
+            status = if link.up { "up" } else { "down" },
+            sync = link.sync_in_progress as u8,
         )
     }
 }
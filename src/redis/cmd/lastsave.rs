@@ -0,0 +1,49 @@
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+
+use crate::redis::{
+    frame::Frame,
+    parser::Parser,
+    ServerInfo,
+    utils::Named,
+};
+
+use super::ClientCmd;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lastsave {}
+
+impl Named for Lastsave {
+    const NAME: &'static str = "LASTSAVE";
+}
+
+impl Lastsave {
+    pub fn new() -> Lastsave {
+        Lastsave {}
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lastsave> {
+        parser.finish()?;
+
+        Ok(Lastsave::new())
+    }
+
+    pub fn apply(&self, server_info: &ServerInfo) -> Frame {
+        let lastsave = server_info.persistence.lastsave()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Frame::Integer(lastsave.as_secs() as i64)
+    }
+}
+
+impl ClientCmd for Lastsave {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lastsave::NAME.into()));
+
+        frame
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use bytes::Bytes;
+use tokio::time::Duration;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Blocked, Keyspace},
+    frame::Frame,
+    parser::{Parser, ParserError},
+};
+
+use super::{End, Lmove, Lmpop, Pop};
+
+// BLPOP, BRPOP, BLMOVE, BLMPOP: the non-blocking pop is tried again each
+// time the client's turn comes on one of the keys, until the timeout passes
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    op: BlockingOp,
+    // None blocks forever
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum BlockingOp {
+    Pop { keys: Vec<Bytes>, end: End },
+    Move(Lmove),
+    Mpop(Lmpop),
+}
+
+impl Block {
+    // BLPOP key [key ...] timeout
+    pub fn parse_pop(end: End, parser: &mut Parser) -> Result<Block> {
+        let mut args = vec![parser.next_bytes()?];
+        while let Ok(arg) = parser.next_bytes() {
+            args.push(arg);
+        }
+
+        // a single argument is the timeout missing its keys
+        let timeout = match args.pop() {
+            Some(timeout) if !args.is_empty() => parse_timeout(&timeout)?,
+            _ => return Err(ParserError::EndOfStream.into()),
+        };
+
+        Ok(Block { op: BlockingOp::Pop { keys: args, end }, timeout })
+    }
+
+    // BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout
+    pub fn parse_move(parser: &mut Parser) -> Result<Block> {
+        let source = parser.next_bytes()?;
+        let destination = parser.next_bytes()?;
+        let from = End::parse(parser)?;
+        let to = End::parse(parser)?;
+        let timeout = parse_timeout(&parser.next_bytes()?)?;
+        parser.finish()?;
+
+        Ok(Block { op: BlockingOp::Move(Lmove::new(source, destination, from, to)), timeout })
+    }
+
+    // BLMPOP timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]
+    pub fn parse_mpop(parser: &mut Parser) -> Result<Block> {
+        let timeout = parse_timeout(&parser.next_bytes()?)?;
+        let lmpop = Lmpop::parse_args(parser)?;
+
+        Ok(Block { op: BlockingOp::Mpop(lmpop), timeout })
+    }
+
+    pub fn keys(&self) -> Vec<Bytes> {
+        match &self.op {
+            BlockingOp::Pop { keys, .. } => keys.clone(),
+            BlockingOp::Move(lmove) => vec![lmove.source.clone()],
+            BlockingOp::Mpop(lmpop) => lmpop.keys.clone(),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    // the reply along with the non-blocking command to propagate,
+    // None while none of the keys can be served to this client
+    pub fn serve(&self, keyspace: &mut Keyspace, blocked: &Blocked) -> Result<Option<(Frame, Frame)>, CommandError> {
+        let served = match &self.op {
+            BlockingOp::Pop { keys, end } => {
+                let keys = keys.iter().filter(|key| keyspace.is_next(key, blocked)).cloned().collect();
+
+                Lmpop::new(keys, *end, 1).pop(keyspace)?.map(|(key, elements)| {
+                    let pop = Pop::new(*end, key.clone(), None);
+                    let mut reply = vec![Frame::Bulk(key)];
+                    reply.extend(elements.into_iter().map(Frame::Bulk));
+
+                    (Frame::Array(reply), pop.to_frame())
+                })
+            }
+            BlockingOp::Move(lmove) if keyspace.is_next(&lmove.source, blocked) => {
+                lmove.move_element(keyspace)?.map(|element| (Frame::Bulk(element), lmove.to_frame()))
+            }
+            BlockingOp::Move(_) => None,
+            BlockingOp::Mpop(lmpop) => {
+                let keys = lmpop.keys.iter().filter(|key| keyspace.is_next(key, blocked)).cloned().collect();
+
+                Lmpop::new(keys, lmpop.end, lmpop.count).pop(keyspace)?.map(|(key, elements)| {
+                    let pop = Pop::new(lmpop.end, key.clone(), Some(elements.len() as u64));
+                    let reply = Frame::Array(vec![Frame::Bulk(key), super::bulk_array(elements)]);
+
+                    (reply, pop.to_frame())
+                })
+            }
+        };
+
+        Ok(served)
+    }
+
+    // reply once the timeout passed
+    pub fn timed_out(&self) -> Frame {
+        match self.op {
+            BlockingOp::Move(_) => Frame::Null,
+            _ => Frame::NullArray,
+        }
+    }
+}
+
+// seconds, fractions allowed, 0 blocks forever
+fn parse_timeout(arg: &[u8]) -> Result<Option<Duration>, CommandError> {
+    let timeout: f64 = std::str::from_utf8(arg).ok()
+        .and_then(|arg| arg.parse().ok())
+        .filter(|timeout: &f64| timeout.is_finite())
+        .ok_or_else(|| CommandError::Other("timeout is not a float or out of range".to_string()))?;
+
+    if timeout < 0.0 {
+        return Err(CommandError::Other("timeout is negative".to_string()));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+
+    // the deadline has to stay within a millisecond unix time, as in redis
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    if timeout * 1000.0 + now.as_millis() as f64 > i64::MAX as f64 {
+        return Err(CommandError::Other("timeout is out of range".to_string()));
+    }
+
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::Other("timeout is out of range".to_string()))
+}
+
+impl ClientCmd for Block {
+    fn to_frame(&self) -> Frame {
+        let timeout = Frame::Bulk(self.timeout.unwrap_or_default().as_secs_f64().to_string().into());
+
+        match &self.op {
+            BlockingOp::Pop { keys, end } => {
+                let name = match end {
+                    End::Left => "BLPOP",
+                    End::Right => "BRPOP",
+                };
+
+                let mut frame = Frame::array();
+                frame.add(Frame::Bulk(name.into()));
+                for key in keys {
+                    frame.add(Frame::Bulk(key.clone()));
+                }
+                frame.add(timeout);
+
+                frame
+            }
+            BlockingOp::Move(lmove) => {
+                let mut frame = lmove.to_frame();
+                if let Frame::Array(items) = &mut frame {
+                    items[0] = Frame::Bulk("BLMOVE".into());
+                }
+                frame.add(timeout);
+
+                frame
+            }
+            BlockingOp::Mpop(lmpop) => {
+                let mut frame = lmpop.to_frame();
+                if let Frame::Array(items) = &mut frame {
+                    items[0] = Frame::Bulk("BLMPOP".into());
+                    items.insert(1, timeout);
+                }
+
+                frame
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lindex {
+    key: Bytes,
+    index: i64,
+}
+
+impl Named for Lindex {
+    const NAME: &'static str = "LINDEX";
+}
+
+impl Lindex {
+    pub fn new(key: Bytes, index: i64) -> Lindex {
+        Lindex { key, index }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lindex> {
+        let key = parser.next_bytes()?;
+        let index = parser.next_signed_int()?;
+        parser.finish()?;
+
+        Ok(Lindex::new(key, index))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get(&self.key) {
+                Some(value) => value.as_list()?,
+                None => return Ok(Frame::Null),
+            };
+
+            let frame = match super::index(list.len(), self.index) {
+                Some(index) => Frame::Bulk(list[index].clone()),
+                None => Frame::Null,
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Lindex {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lindex::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.index.to_string().into()));
+
+        frame
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Linsert {
+    key: Bytes,
+    before: bool,
+    pivot: Bytes,
+    element: Bytes,
+}
+
+impl Named for Linsert {
+    const NAME: &'static str = "LINSERT";
+}
+
+impl Linsert {
+    pub fn new(key: Bytes, before: bool, pivot: Bytes, element: Bytes) -> Linsert {
+        Linsert { key, before, pivot, element }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Linsert> {
+        let key = parser.next_bytes()?;
+        let before = match &parser.next_string()?.to_uppercase()[..] {
+            "BEFORE" => true,
+            "AFTER" => false,
+            _ => return Err(CommandError::Syntax.into()),
+        };
+        let pivot = parser.next_bytes()?;
+        let element = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Linsert::new(key, before, pivot, element))
+    }
+
+    // length after the insert, -1 when the pivot is not there
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_list_mut()?,
+                None => return Ok(Frame::Integer(0)),
+            };
+
+            let pivot = match list.iter().position(|item| *item == self.pivot) {
+                Some(pivot) => pivot,
+                None => {
+                    keyspace.unchanged();
+                    return Ok(Frame::Integer(-1));
+                }
+            };
+
+            let at = if self.before { pivot } else { pivot + 1 };
+            list.insert(at, self.element.clone());
+
+            Ok(Frame::Integer(list.len() as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Linsert {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Linsert::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(if self.before { "BEFORE" } else { "AFTER" }.into()));
+        frame.add(Frame::Bulk(self.pivot.clone()));
+        frame.add(Frame::Bulk(self.element.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Llen {
+    key: Bytes,
+}
+
+impl Named for Llen {
+    const NAME: &'static str = "LLEN";
+}
+
+impl Llen {
+    pub fn new(key: Bytes) -> Llen {
+        Llen { key }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Llen> {
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Llen::new(key))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let len = match keyspace.get(&self.key) {
+                Some(value) => value.as_list()?.len(),
+                None => 0,
+            };
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Llen {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Llen::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Keyspace, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+use super::End;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lmove {
+    pub(super) source: Bytes,
+    pub(super) destination: Bytes,
+    pub(super) from: End,
+    pub(super) to: End,
+}
+
+impl Named for Lmove {
+    const NAME: &'static str = "LMOVE";
+}
+
+impl Lmove {
+    pub fn new(source: Bytes, destination: Bytes, from: End, to: End) -> Lmove {
+        Lmove { source, destination, from, to }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lmove> {
+        let source = parser.next_bytes()?;
+        let destination = parser.next_bytes()?;
+        let from = End::parse(parser)?;
+        let to = End::parse(parser)?;
+        parser.finish()?;
+
+        Ok(Lmove::new(source, destination, from, to))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let frame = match self.move_element(keyspace)? {
+                Some(element) => Frame::Bulk(element),
+                None => Frame::Null,
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+
+    // the element moved, None when the source list doesn't exist
+    pub fn move_element(&self, keyspace: &mut Keyspace) -> Result<Option<Bytes>, CommandError> {
+        match keyspace.get(&self.source) {
+            Some(value) => value.as_list()?,
+            None => return Ok(None),
+        };
+        // nothing is popped when the element can't be pushed
+        if let Some(value) = keyspace.get(&self.destination) {
+            value.as_list()?;
+        }
+
+        let element = keyspace.get_mut(&self.source)
+            .and_then(|value| super::pop(value.as_list_mut().ok()?, self.from));
+
+        if let Some(element) = &element {
+            let destination = keyspace.get_or_insert(&self.destination, || Value::List(VecDeque::new()));
+            super::push(destination.as_list_mut()?, self.to, element.clone());
+        }
+
+        Ok(element)
+    }
+}
+
+impl ClientCmd for Lmove {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lmove::NAME.into()));
+        frame.add(Frame::Bulk(self.source.clone()));
+        frame.add(Frame::Bulk(self.destination.clone()));
+        frame.add(Frame::Bulk(self.from.as_str().into()));
+        frame.add(Frame::Bulk(self.to.as_str().into()));
+
+        frame
+    }
+}
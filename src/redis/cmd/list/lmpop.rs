@@ -0,0 +1,111 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Keyspace},
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+use super::End;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lmpop {
+    pub(super) keys: Vec<Bytes>,
+    pub(super) end: End,
+    pub(super) count: u64,
+}
+
+impl Named for Lmpop {
+    const NAME: &'static str = "LMPOP";
+}
+
+impl Lmpop {
+    pub fn new(keys: Vec<Bytes>, end: End, count: u64) -> Lmpop {
+        Lmpop { keys, end, count }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lmpop> {
+        let numkeys = parser.next_int().map_err(|_| positive("numkeys"))?;
+        if numkeys == 0 {
+            return Err(positive("numkeys").into());
+        }
+
+        let mut keys = vec![];
+        for _ in 0..numkeys {
+            keys.push(parser.next_bytes()?);
+        }
+        let end = End::parse(parser)?;
+
+        let count = match parser.next_string() {
+            Ok(option) if option.eq_ignore_ascii_case("count") => {
+                match parser.next_int() {
+                    Ok(count) if count > 0 => count,
+                    _ => return Err(positive("count").into()),
+                }
+            }
+            Ok(_) => return Err(CommandError::Syntax.into()),
+            Err(ParserError::EndOfStream) => 1,
+            Err(e) => return Err(e.into()),
+        };
+        if parser.finish().is_err() {
+            return Err(CommandError::Syntax.into());
+        }
+
+        Ok(Lmpop::new(keys, end, count))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let frame = match self.pop(keyspace)? {
+                Some((key, elements)) => Frame::Array(vec![
+                    Frame::Bulk(key),
+                    super::bulk_array(elements),
+                ]),
+                None => Frame::NullArray,
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+
+    // pops from the first key holding a list, None when there is none
+    pub fn pop(&self, keyspace: &mut Keyspace) -> Result<Option<(Bytes, Vec<Bytes>)>, CommandError> {
+        for key in &self.keys {
+            let list = match keyspace.get_mut(key) {
+                Some(value) => value.as_list_mut()?,
+                None => continue,
+            };
+
+            let count = (self.count as usize).min(list.len());
+            let elements = (0..count).filter_map(|_| super::pop(list, self.end)).collect();
+
+            return Ok(Some((key.clone(), elements)));
+        }
+
+        Ok(None)
+    }
+}
+
+fn positive(name: &str) -> CommandError {
+    CommandError::Other(format!("{} should be greater than 0", name))
+}
+
+impl ClientCmd for Lmpop {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lmpop::NAME.into()));
+        frame.add(Frame::Bulk(self.keys.len().to_string().into()));
+        for key in &self.keys {
+            frame.add(Frame::Bulk(key.clone()));
+        }
+        frame.add(Frame::Bulk(self.end.as_str().into()));
+        frame.add(Frame::Bulk("COUNT".into()));
+        frame.add(Frame::Bulk(self.count.to_string().into()));
+
+        frame
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lpos {
+    key: Bytes,
+    element: Bytes,
+    // which match to start from, negative ranks search from the tail
+    rank: i64,
+    // with a count the reply is an array, zero asks for every match
+    count: Option<u64>,
+    // elements compared at most, zero for the whole list
+    maxlen: u64,
+}
+
+impl Named for Lpos {
+    const NAME: &'static str = "LPOS";
+}
+
+impl Lpos {
+    pub fn new(key: Bytes, element: Bytes) -> Lpos {
+        Lpos { key, element, rank: 1, count: None, maxlen: 0 }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lpos> {
+        let mut lpos = Lpos::new(parser.next_bytes()?, parser.next_bytes()?);
+
+        loop {
+            let option = match parser.next_string() {
+                Ok(option) => option,
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            match &option.to_uppercase()[..] {
+                "RANK" => {
+                    lpos.rank = parser.next_signed_int()?;
+                    if lpos.rank == 0 || lpos.rank == i64::MIN {
+                        return Err(CommandError::Other(
+                            "RANK can't be zero: use 1 to start from the first match, \
+                            2 from the second ... or use negative to start from the end of the list".to_string()
+                        ).into());
+                    }
+                }
+                "COUNT" => {
+                    lpos.count = Some(non_negative(parser, "COUNT")?);
+                }
+                "MAXLEN" => {
+                    lpos.maxlen = non_negative(parser, "MAXLEN")?;
+                }
+                _ => return Err(CommandError::Syntax.into()),
+            }
+        }
+
+        Ok(lpos)
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get(&self.key) {
+                Some(value) => value.as_list()?,
+                None if self.count.is_some() => return Ok(Frame::array()),
+                None => return Ok(Frame::Null),
+            };
+
+            let maxlen = match self.maxlen {
+                0 => list.len(),
+                maxlen => (maxlen as usize).min(list.len()),
+            };
+            let indexes: Box<dyn Iterator<Item = usize>> = if self.rank > 0 {
+                Box::new(0..maxlen)
+            } else {
+                Box::new((list.len() - maxlen..list.len()).rev())
+            };
+
+            let limit = match self.count {
+                None => 1,
+                Some(0) => usize::MAX,
+                Some(count) => count as usize,
+            };
+
+            let positions: Vec<usize> = indexes
+                .filter(|&i| list[i] == self.element)
+                .skip(self.rank.unsigned_abs() as usize - 1)
+                .take(limit)
+                .collect();
+
+            let frame = match self.count {
+                Some(_) => Frame::Array(positions.into_iter().map(|i| Frame::Integer(i as i64)).collect()),
+                None => positions.first().map(|&i| Frame::Integer(i as i64)).unwrap_or(Frame::Null),
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+fn non_negative(parser: &mut Parser, option: &str) -> Result<u64> {
+    let val = parser.next_signed_int()?;
+
+    u64::try_from(val).map_err(|_| {
+        CommandError::Other(format!("{} can't be negative", option)).into()
+    })
+}
+
+impl ClientCmd for Lpos {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lpos::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.element.clone()));
+        frame.add(Frame::Bulk("RANK".into()));
+        frame.add(Frame::Bulk(self.rank.to_string().into()));
+        if let Some(count) = self.count {
+            frame.add(Frame::Bulk("COUNT".into()));
+            frame.add(Frame::Bulk(count.to_string().into()));
+        }
+        frame.add(Frame::Bulk("MAXLEN".into()));
+        frame.add(Frame::Bulk(self.maxlen.to_string().into()));
+
+        frame
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lrange {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+impl Named for Lrange {
+    const NAME: &'static str = "LRANGE";
+}
+
+impl Lrange {
+    pub fn new(key: Bytes, start: i64, stop: i64) -> Lrange {
+        Lrange { key, start, stop }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lrange> {
+        let key = parser.next_bytes()?;
+        let start = parser.next_signed_int()?;
+        let stop = parser.next_signed_int()?;
+        parser.finish()?;
+
+        Ok(Lrange::new(key, start, stop))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get(&self.key) {
+                Some(value) => value.as_list()?,
+                None => return Ok(Frame::array()),
+            };
+
+            let frame = match super::range(list.len(), self.start, self.stop) {
+                Some((start, stop)) => super::bulk_array(list.range(start..=stop).cloned()),
+                None => Frame::array(),
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Lrange {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lrange::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.start.to_string().into()));
+        frame.add(Frame::Bulk(self.stop.to_string().into()));
+
+        frame
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lrem {
+    key: Bytes,
+    // positive removes from the head, negative from the tail, zero all
+    count: i64,
+    element: Bytes,
+}
+
+impl Named for Lrem {
+    const NAME: &'static str = "LREM";
+}
+
+impl Lrem {
+    pub fn new(key: Bytes, count: i64, element: Bytes) -> Lrem {
+        Lrem { key, count, element }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lrem> {
+        let key = parser.next_bytes()?;
+        let count = parser.next_signed_int()?;
+        let element = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Lrem::new(key, count, element))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_list_mut()?,
+                None => return Ok(Frame::Integer(0)),
+            };
+
+            let limit = match self.count {
+                0 => usize::MAX,
+                count => count.unsigned_abs() as usize,
+            };
+
+            let mut matches: Vec<usize> = list.iter().enumerate()
+                .filter(|(_, item)| **item == self.element)
+                .map(|(i, _)| i)
+                .collect();
+            if self.count < 0 {
+                matches.reverse();
+            }
+            matches.truncate(limit);
+            matches.sort_unstable();
+
+            // back to front, so the remaining indexes stay valid
+            for &i in matches.iter().rev() {
+                list.remove(i);
+            }
+            if matches.is_empty() {
+                keyspace.unchanged();
+            }
+
+            Ok(Frame::Integer(matches.len() as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Lrem {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lrem::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.count.to_string().into()));
+        frame.add(Frame::Bulk(self.element.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lset {
+    key: Bytes,
+    index: i64,
+    element: Bytes,
+}
+
+impl Named for Lset {
+    const NAME: &'static str = "LSET";
+}
+
+impl Lset {
+    pub fn new(key: Bytes, index: i64, element: Bytes) -> Lset {
+        Lset { key, index, element }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Lset> {
+        let key = parser.next_bytes()?;
+        let index = parser.next_signed_int()?;
+        let element = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Lset::new(key, index, element))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_list_mut()?,
+                None => return Err(CommandError::Other("no such key".to_string())),
+            };
+
+            let index = super::index(list.len(), self.index)
+                .ok_or_else(|| CommandError::Other("index out of range".to_string()))?;
+            list[index] = self.element.clone();
+
+            Ok(Frame::Simple("OK".to_string()))
+        }).unwrap_or_else(|e| e.to_frame())
+    }
+}
+
+impl ClientCmd for Lset {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Lset::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.index.to_string().into()));
+        frame.add(Frame::Bulk(self.element.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ltrim {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+impl Named for Ltrim {
+    const NAME: &'static str = "LTRIM";
+}
+
+impl Ltrim {
+    pub fn new(key: Bytes, start: i64, stop: i64) -> Ltrim {
+        Ltrim { key, start, stop }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Ltrim> {
+        let key = parser.next_bytes()?;
+        let start = parser.next_signed_int()?;
+        let stop = parser.next_signed_int()?;
+        parser.finish()?;
+
+        Ok(Ltrim::new(key, start, stop))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            if let Some(value) = keyspace.get_mut(&self.key) {
+                let list = value.as_list_mut()?;
+
+                match super::range(list.len(), self.start, self.stop) {
+                    // the range covers the whole list, nothing to trim
+                    Some((0, stop)) if stop + 1 == list.len() => keyspace.unchanged(),
+                    Some((start, stop)) => {
+                        list.truncate(stop + 1);
+                        list.drain(..start);
+                    }
+                    // an empty range empties the list, which removes the key
+                    None => list.clear(),
+                }
+            }
+
+            Ok(Frame::Simple("OK".to_string()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Ltrim {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Ltrim::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.start.to_string().into()));
+        frame.add(Frame::Bulk(self.stop.to_string().into()));
+
+        frame
+    }
+}
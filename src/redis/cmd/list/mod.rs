@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::cmd::CommandError;
+use crate::redis::frame::Frame;
+use crate::redis::parser::Parser;
+
+pub use block::Block;
+pub use lindex::Lindex;
+pub use linsert::Linsert;
+pub use llen::Llen;
+pub use lmove::Lmove;
+pub use lmpop::Lmpop;
+pub use lpos::Lpos;
+pub use lrange::Lrange;
+pub use lrem::Lrem;
+pub use lset::Lset;
+pub use ltrim::Ltrim;
+pub use pop::Pop;
+pub use push::Push;
+
+mod block;
+mod lindex;
+mod linsert;
+mod llen;
+mod lmove;
+mod lmpop;
+mod lpos;
+mod lrange;
+mod lrem;
+mod lset;
+mod ltrim;
+mod pop;
+mod push;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum End {
+    Left,
+    Right,
+}
+
+impl End {
+    pub fn parse(parser: &mut Parser) -> Result<End> {
+        match &parser.next_string()?.to_uppercase()[..] {
+            "LEFT" => Ok(End::Left),
+            "RIGHT" => Ok(End::Right),
+            _ => Err(CommandError::Syntax.into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            End::Left => "LEFT",
+            End::Right => "RIGHT",
+        }
+    }
+}
+
+pub(crate) fn push(list: &mut VecDeque<Bytes>, end: End, item: Bytes) {
+    match end {
+        End::Left => list.push_front(item),
+        End::Right => list.push_back(item),
+    }
+}
+
+pub(crate) fn pop(list: &mut VecDeque<Bytes>, end: End) -> Option<Bytes> {
+    match end {
+        End::Left => list.pop_front(),
+        End::Right => list.pop_back(),
+    }
+}
+
+// negative indexes count from the tail, None when out of range
+fn index(len: usize, index: i64) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+
+    if index < 0 || index >= len as i64 {
+        return None;
+    }
+
+    Some(index as usize)
+}
+
+// inclusive range as LRANGE and LTRIM take it, clamped to the list,
+// None when nothing is left of it
+fn range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+
+    if start > stop || start >= len {
+        return None;
+    }
+
+    Some((start as usize, stop as usize))
+}
+
+fn bulk_array(items: impl IntoIterator<Item = Bytes>) -> Frame {
+    Frame::Array(items.into_iter().map(Frame::Bulk).collect())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+use super::End;
+
+// LPOP, RPOP
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pop {
+    end: End,
+    key: Bytes,
+    // with a count the reply is an array, even of one element
+    count: Option<u64>,
+}
+
+impl Named for Pop {
+    const NAME: &'static str = "LPOP";
+}
+
+impl Pop {
+    pub fn new(end: End, key: Bytes, count: Option<u64>) -> Pop {
+        Pop { end, key, count }
+    }
+
+    pub fn parse_args(end: End, parser: &mut Parser) -> Result<Pop> {
+        let key = parser.next_bytes()?;
+
+        let count = match parser.next_int() {
+            Ok(count) => Some(count),
+            Err(ParserError::EndOfStream) => None,
+            Err(_) => return Err(CommandError::Other(
+                "value is out of range, must be positive".to_string()
+            ).into()),
+        };
+        parser.finish()?;
+
+        Ok(Pop::new(end, key, count))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_list_mut()?,
+                None if self.count.is_some() => return Ok(Frame::NullArray),
+                None => return Ok(Frame::Null),
+            };
+
+            let frame = match self.count {
+                None => super::pop(list, self.end).map(Frame::Bulk).unwrap_or(Frame::Null),
+                Some(0) => {
+                    keyspace.unchanged();
+                    Frame::Array(vec![])
+                }
+                Some(count) => {
+                    let count = (count as usize).min(list.len());
+                    super::bulk_array((0..count).filter_map(|_| super::pop(list, self.end)))
+                }
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Pop {
+    fn to_frame(&self) -> Frame {
+        let name = match self.end {
+            End::Left => "LPOP",
+            End::Right => "RPOP",
+        };
+
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(name.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        if let Some(count) = self.count {
+            frame.add(Frame::Bulk(count.to_string().into()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Value},
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+use super::End;
+
+// LPUSH, RPUSH, LPUSHX, RPUSHX
+#[derive(Debug, PartialEq, Clone)]
+pub struct Push {
+    end: End,
+    // the X variants only push to a list that already exists
+    only_existing: bool,
+    key: Bytes,
+    elements: Vec<Bytes>,
+}
+
+impl Named for Push {
+    const NAME: &'static str = "LPUSH";
+}
+
+impl Push {
+    pub fn new(end: End, only_existing: bool, key: Bytes, elements: Vec<Bytes>) -> Push {
+        Push { end, only_existing, key, elements }
+    }
+
+    pub fn parse_args(end: End, only_existing: bool, parser: &mut Parser) -> Result<Push> {
+        let key = parser.next_bytes()?;
+        let mut elements = vec![parser.next_bytes()?];
+
+        loop {
+            match parser.next_bytes() {
+                Ok(element) => elements.push(element),
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Push::new(end, only_existing, key, elements))
+    }
+
+    // length of the list after the push
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let list = if self.only_existing {
+                match keyspace.get_mut(&self.key) {
+                    Some(value) => value.as_list_mut()?,
+                    None => return Ok(Frame::Integer(0)),
+                }
+            } else {
+                keyspace.get_or_insert(&self.key, || Value::List(VecDeque::new())).as_list_mut()?
+            };
+
+            for element in &self.elements {
+                super::push(list, self.end, element.clone());
+            }
+
+            Ok(Frame::Integer(list.len() as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Push {
+    fn to_frame(&self) -> Frame {
+        let name = match (self.end, self.only_existing) {
+            (End::Left, false) => "LPUSH",
+            (End::Right, false) => "RPUSH",
+            (End::Left, true) => "LPUSHX",
+            (End::Right, true) => "RPUSHX",
+        };
+
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(name.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for element in &self.elements {
+            frame.add(Frame::Bulk(element.clone()));
+        }
+
+        frame
+    }
+}
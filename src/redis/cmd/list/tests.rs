@@ -0,0 +1,325 @@
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::redis::cmd::tests::{bulks, prepare_conn, send, start_server, start_server_with};
+use crate::redis::cmd::Command;
+use crate::redis::config::{Config, ProtoLimits};
+use crate::redis::connection::Connection;
+use crate::redis::db::{Db, Value};
+
+use super::*;
+
+// sends a blocking command, its reply is read later
+async fn send_blocking(conn: &mut Connection, words: &[&str]) {
+    conn.write_frame(&bulks(words)).await.unwrap();
+    // lets the server queue the client before the test goes on
+    sleep(Duration::from_millis(50)).await;
+}
+
+fn block(words: &[&str]) -> Block {
+    match Command::from_frame(&bulks(words)).unwrap() {
+        Command::Block(block) => block,
+        cmd => panic!("not a blocking command: {:?}", cmd),
+    }
+}
+
+#[test]
+fn test_range_and_index() {
+    assert_eq!(range(5, 0, -1), Some((0, 4)));
+    assert_eq!(range(5, -2, 100), Some((3, 4)));
+    assert_eq!(range(5, -100, 1), Some((0, 1)));
+    assert_eq!(range(5, 3, 1), None);
+    assert_eq!(range(5, 5, 10), None);
+    assert_eq!(range(0, 0, -1), None);
+
+    assert_eq!(index(3, -1), Some(2));
+    assert_eq!(index(3, 3), None);
+    assert_eq!(index(3, -4), None);
+}
+
+#[tokio::test]
+async fn test_push_pop_range() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(send(&mut conn, &["RPUSH", "list", "a", "b", "c"]).await, Frame::Integer(3));
+    assert_eq!(send(&mut conn, &["LPUSH", "list", "x", "y"]).await, Frame::Integer(5));
+    assert_eq!(send(&mut conn, &["LPUSHX", "nolist", "a"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["RPUSHX", "list", "z"]).await, Frame::Integer(6));
+
+    assert_eq!(send(&mut conn, &["LRANGE", "list", "0", "-1"]).await, bulks(&["y", "x", "a", "b", "c", "z"]));
+    assert_eq!(send(&mut conn, &["LRANGE", "list", "-3", "100"]).await, bulks(&["b", "c", "z"]));
+    assert_eq!(send(&mut conn, &["LLEN", "list"]).await, Frame::Integer(6));
+    assert_eq!(send(&mut conn, &["LINDEX", "list", "-1"]).await, Frame::Bulk("z".into()));
+    assert_eq!(send(&mut conn, &["LINDEX", "list", "6"]).await, Frame::Null);
+
+    assert_eq!(send(&mut conn, &["LPOP", "list"]).await, Frame::Bulk("y".into()));
+    assert_eq!(send(&mut conn, &["RPOP", "list", "2"]).await, bulks(&["z", "c"]));
+    assert_eq!(send(&mut conn, &["LPOP", "missing"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["LPOP", "missing", "2"]).await, Frame::NullArray);
+
+    // popping the last element removes the key
+    assert_eq!(send(&mut conn, &["LPOP", "list", "10"]).await, bulks(&["x", "a", "b"]));
+    assert_eq!(send(&mut conn, &["LLEN", "list"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["SET", "list", "string"]).await, Frame::Simple("OK".to_string()));
+}
+
+#[tokio::test]
+async fn test_set_insert_rem_trim() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["RPUSH", "list", "a", "b", "a", "c", "a"]).await;
+
+    assert_eq!(send(&mut conn, &["LSET", "list", "1", "B"]).await, Frame::Simple("OK".to_string()));
+    assert_eq!(
+        send(&mut conn, &["LSET", "list", "9", "x"]).await,
+        Frame::Error("ERR index out of range".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["LSET", "nolist", "0", "x"]).await,
+        Frame::Error("ERR no such key".to_string()),
+    );
+
+    assert_eq!(send(&mut conn, &["LINSERT", "list", "BEFORE", "c", "x"]).await, Frame::Integer(6));
+    assert_eq!(send(&mut conn, &["LINSERT", "list", "AFTER", "nope", "x"]).await, Frame::Integer(-1));
+    assert_eq!(send(&mut conn, &["LINSERT", "nolist", "AFTER", "a", "x"]).await, Frame::Integer(0));
+    assert_eq!(
+        send(&mut conn, &["LRANGE", "list", "0", "-1"]).await,
+        bulks(&["a", "B", "a", "x", "c", "a"]),
+    );
+
+    assert_eq!(send(&mut conn, &["LREM", "list", "-2", "a"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["LRANGE", "list", "0", "-1"]).await, bulks(&["a", "B", "x", "c"]));
+    assert_eq!(send(&mut conn, &["LREM", "list", "0", "a"]).await, Frame::Integer(1));
+
+    assert_eq!(send(&mut conn, &["LTRIM", "list", "1", "-1"]).await, Frame::Simple("OK".to_string()));
+    assert_eq!(send(&mut conn, &["LRANGE", "list", "0", "-1"]).await, bulks(&["x", "c"]));
+    assert_eq!(send(&mut conn, &["LTRIM", "list", "5", "10"]).await, Frame::Simple("OK".to_string()));
+    assert_eq!(send(&mut conn, &["LLEN", "list"]).await, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn test_lpos() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["RPUSH", "list", "a", "b", "c", "1", "2", "3", "c", "c"]).await;
+
+    assert_eq!(send(&mut conn, &["LPOS", "list", "c"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["LPOS", "list", "c", "RANK", "2"]).await, Frame::Integer(6));
+    assert_eq!(send(&mut conn, &["LPOS", "list", "c", "RANK", "-1"]).await, Frame::Integer(7));
+    assert_eq!(
+        send(&mut conn, &["LPOS", "list", "c", "COUNT", "0"]).await,
+        Frame::Array(vec![Frame::Integer(2), Frame::Integer(6), Frame::Integer(7)]),
+    );
+    assert_eq!(
+        send(&mut conn, &["LPOS", "list", "c", "RANK", "-1", "COUNT", "2"]).await,
+        Frame::Array(vec![Frame::Integer(7), Frame::Integer(6)]),
+    );
+    assert_eq!(send(&mut conn, &["LPOS", "list", "c", "MAXLEN", "2"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["LPOS", "list", "x", "COUNT", "1"]).await, Frame::array());
+    assert!(matches!(
+        send(&mut conn, &["LPOS", "list", "c", "RANK", "0"]).await,
+        Frame::Error(msg) if msg.starts_with("ERR RANK can't be zero")
+    ));
+}
+
+#[tokio::test]
+async fn test_lmove_lmpop() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["RPUSH", "src", "a", "b", "c"]).await;
+
+    assert_eq!(send(&mut conn, &["LMOVE", "src", "dst", "LEFT", "RIGHT"]).await, Frame::Bulk("a".into()));
+    assert_eq!(send(&mut conn, &["LMOVE", "src", "dst", "RIGHT", "LEFT"]).await, Frame::Bulk("c".into()));
+    assert_eq!(send(&mut conn, &["LRANGE", "dst", "0", "-1"]).await, bulks(&["c", "a"]));
+    // rotation
+    assert_eq!(send(&mut conn, &["LMOVE", "dst", "dst", "LEFT", "RIGHT"]).await, Frame::Bulk("c".into()));
+    assert_eq!(send(&mut conn, &["LRANGE", "dst", "0", "-1"]).await, bulks(&["a", "c"]));
+    assert_eq!(send(&mut conn, &["LMOVE", "missing", "dst", "LEFT", "LEFT"]).await, Frame::Null);
+
+    // nothing is popped when the destination has the wrong type
+    send(&mut conn, &["SET", "string", "x"]).await;
+    assert_eq!(send(&mut conn, &["LMOVE", "src", "string", "LEFT", "LEFT"]).await, CommandError::WrongType.to_frame());
+    assert_eq!(send(&mut conn, &["LLEN", "src"]).await, Frame::Integer(1));
+
+    assert_eq!(
+        send(&mut conn, &["LMPOP", "3", "missing", "dst", "src", "RIGHT", "COUNT", "5"]).await,
+        Frame::Array(vec![Frame::Bulk("dst".into()), bulks(&["c", "a"])]),
+    );
+    assert_eq!(
+        send(&mut conn, &["LMPOP", "2", "missing", "dst", "LEFT"]).await,
+        Frame::NullArray,
+    );
+    assert_eq!(
+        send(&mut conn, &["LMPOP", "0", "LEFT"]).await,
+        Frame::Error("ERR numkeys should be greater than 0".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_type() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+    let wrongtype = Frame::Error("WRONGTYPE Operation against a key holding the wrong kind of value".to_string());
+
+    send(&mut conn, &["RPUSH", "list", "a"]).await;
+    send(&mut conn, &["SET", "string", "x"]).await;
+
+    assert_eq!(send(&mut conn, &["GET", "list"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["LPUSH", "string", "a"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["LRANGE", "string", "0", "-1"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["LPOP", "string"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["LLEN", "string"]).await, wrongtype);
+
+    // SET replaces a value of any type
+    assert_eq!(send(&mut conn, &["SET", "list", "y"]).await, Frame::Simple("OK".to_string()));
+    assert_eq!(send(&mut conn, &["GET", "list"]).await, Frame::Bulk("y".into()));
+    assert_eq!(send(&mut conn, &["DEL", "string"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["RPUSH", "string", "a"]).await, Frame::Integer(1));
+}
+
+#[test]
+fn test_parse_blocking() {
+    assert_eq!(block(&["BLPOP", "a", "b", "0"]).keys(), vec![Bytes::from("a"), Bytes::from("b")]);
+    assert_eq!(block(&["BLPOP", "a", "0"]).timeout(), None);
+    assert_eq!(block(&["BRPOP", "a", "0.25"]).timeout(), Some(Duration::from_millis(250)));
+    assert_eq!(block(&["BLMOVE", "a", "b", "LEFT", "RIGHT", "1"]).keys(), vec![Bytes::from("a")]);
+    assert_eq!(block(&["BLMPOP", "1.5", "2", "a", "b", "LEFT"]).timeout(), Some(Duration::from_millis(1500)));
+
+    let parse = |words: &[&str]| Command::from_frame(&bulks(words)).unwrap_err().to_string();
+
+    assert_eq!(parse(&["BLPOP", "a", "-1"]), "ERR timeout is negative");
+    assert_eq!(parse(&["BLPOP", "a", "soon"]), "ERR timeout is not a float or out of range");
+    assert_eq!(parse(&["BLPOP", "a", "inf"]), "ERR timeout is not a float or out of range");
+    assert_eq!(parse(&["BLPOP", "0"]), "ERR wrong number of arguments for 'blpop' command");
+    assert_eq!(parse(&["BLMOVE", "a", "b", "UP", "LEFT", "0"]), "ERR syntax error");
+}
+
+#[tokio::test]
+async fn test_serve_propagates_non_blocking_form() {
+    let db = Db::new();
+    db.with_keyspace(|keyspace| {
+        keyspace.insert(Bytes::from("list"), Value::List(["a", "b", "c"].into_iter().map(Bytes::from).collect()));
+    });
+
+    let cases = [
+        (block(&["BRPOP", "missing", "list", "0"]), bulks(&["list", "c"]), bulks(&["RPOP", "list"])),
+        (
+            block(&["BLMPOP", "0", "1", "list", "LEFT", "COUNT", "5"]),
+            Frame::Array(vec![Frame::Bulk("list".into()), bulks(&["a", "b"])]),
+            bulks(&["LPOP", "list", "2"]),
+        ),
+    ];
+
+    for (block, reply, propagated) in cases {
+        let blocked = db.block(block.keys());
+        let served = db.with_keyspace(|keyspace| block.serve(keyspace, &blocked)).unwrap();
+
+        assert_eq!(served, Some((reply, propagated)));
+    }
+
+    let block = block(&["BLMOVE", "list", "other", "RIGHT", "LEFT", "0"]);
+    let blocked = db.block(block.keys());
+    assert_eq!(db.with_keyspace(|keyspace| block.serve(keyspace, &blocked)).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_blocking_pop_fifo() {
+    let addr = start_server().await;
+    let mut first = prepare_conn(addr).await;
+    let mut second = prepare_conn(addr).await;
+    let mut conn = prepare_conn(addr).await;
+
+    send_blocking(&mut first, &["BLPOP", "jobs", "0"]).await;
+    send_blocking(&mut second, &["BLPOP", "other", "jobs", "0"]).await;
+
+    assert_eq!(send(&mut conn, &["RPUSH", "jobs", "a", "b", "c"]).await, Frame::Integer(3));
+
+    assert_eq!(first.read_frame().await.unwrap().unwrap(), bulks(&["jobs", "a"]));
+    assert_eq!(second.read_frame().await.unwrap().unwrap(), bulks(&["jobs", "b"]));
+    assert_eq!(send(&mut conn, &["LRANGE", "jobs", "0", "-1"]).await, bulks(&["c"]));
+
+    // served right away while the list has elements
+    assert_eq!(send(&mut first, &["BRPOP", "jobs", "0"]).await, bulks(&["jobs", "c"]));
+    assert_eq!(send(&mut conn, &["LLEN", "jobs"]).await, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn test_blocking_timeout() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    let started = Instant::now();
+    assert_eq!(send(&mut conn, &["BLPOP", "missing", "0.1"]).await, Frame::NullArray);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    assert_eq!(send(&mut conn, &["BLMOVE", "missing", "dst", "LEFT", "LEFT", "0.05"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["BLMPOP", "0.05", "1", "missing", "LEFT"]).await, Frame::NullArray);
+
+    // the connection is still usable and the client no longer queued
+    send(&mut conn, &["RPUSH", "missing", "a"]).await;
+    assert_eq!(send(&mut conn, &["LLEN", "missing"]).await, Frame::Integer(1));
+
+    send(&mut conn, &["SET", "string", "x"]).await;
+    assert_eq!(send(&mut conn, &["BLPOP", "string", "0"]).await, CommandError::WrongType.to_frame());
+}
+
+#[tokio::test]
+async fn test_blocking_far_timeout() {
+    let addr = start_server().await;
+    let mut blocked = prepare_conn(addr).await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(send(&mut conn, &["BLPOP", "jobs", "1e18"]).await, CommandError::Other(
+        "timeout is out of range".to_string()
+    ).to_frame());
+
+    send_blocking(&mut blocked, &["BLPOP", "jobs", "1e15"]).await;
+    send(&mut conn, &["RPUSH", "jobs", "a"]).await;
+    assert_eq!(blocked.read_frame().await.unwrap().unwrap(), bulks(&["jobs", "a"]));
+}
+
+// what a blocked client sends is buffered no more than any other query
+#[tokio::test]
+async fn test_blocked_client_query_buffer_limit() {
+    let addr = start_server_with(Config {
+        proto_limits: ProtoLimits { query_buffer_limit: 1024, ..Default::default() },
+        ..Default::default()
+    }).await;
+    let mut blocked = prepare_conn(addr).await;
+
+    send_blocking(&mut blocked, &["BLPOP", "jobs", "0"]).await;
+    blocked.write_bytes(&[b'a'; 4096]).await.unwrap();
+
+    // disconnected instead
+    assert!(!matches!(blocked.read_frame().await, Ok(Some(_))));
+}
+
+#[tokio::test]
+async fn test_blmove_blmpop_wakeup() {
+    let addr = start_server().await;
+    let mut mover = prepare_conn(addr).await;
+    let mut popper = prepare_conn(addr).await;
+    let mut conn = prepare_conn(addr).await;
+
+    send_blocking(&mut mover, &["BLMOVE", "src", "dst", "RIGHT", "LEFT", "5"]).await;
+    send(&mut conn, &["LPUSH", "src", "a"]).await;
+    assert_eq!(mover.read_frame().await.unwrap().unwrap(), Frame::Bulk("a".into()));
+    assert_eq!(send(&mut conn, &["LRANGE", "dst", "0", "-1"]).await, bulks(&["a"]));
+
+    send_blocking(&mut popper, &["BLMPOP", "5", "2", "first", "second", "RIGHT", "COUNT", "2"]).await;
+    send(&mut conn, &["RPUSH", "second", "x", "y", "z"]).await;
+    assert_eq!(
+        popper.read_frame().await.unwrap().unwrap(),
+        Frame::Array(vec![Frame::Bulk("second".into()), bulks(&["z", "y"])]),
+    );
+
+    // a client that went away doesn't take elements
+    send_blocking(&mut popper, &["BLPOP", "queue", "0"]).await;
+    drop(popper);
+    sleep(Duration::from_millis(50)).await;
+    send(&mut conn, &["RPUSH", "queue", "a"]).await;
+    assert_eq!(send(&mut conn, &["LLEN", "queue"]).await, Frame::Integer(1));
+}
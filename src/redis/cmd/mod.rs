@@ -1,29 +1,65 @@
+use std::{fmt, num::ParseIntError};
+
 use anyhow::Result;
+use thiserror::Error;
 
+use bgsave::Bgsave;
+use client::Client;
 use config::Config as ConfigCmd;
+pub(crate) use del::Del;
 use echo::Echo;
 use get::Get;
+pub(crate) use hash::Hdel;
+use hash::{
+    ExpireArg, Hexists, Hexpire, Hget, Hgetall, Hincrby, Hincrbyfloat, Hlen, Hmget, Hpersist,
+    Hrandfield, Hscan, Hset, Hsetnx, Hstrlen, Httl, Part, TtlReport,
+};
+pub(crate) use hello::Hello;
 use info::Info;
+use lastsave::Lastsave;
+pub(crate) use list::Block;
+use list::{
+    End, Lindex, Linsert, Llen, Lmove, Lmpop, Lpos, Lrange, Lrem, Lset, Ltrim, Pop, Push,
+};
 pub(crate) use ping::Ping;
 pub(crate) use psync::Psync;
 use replconf::Replconf;
+pub(crate) use replicaof::Replicaof;
+use save::Save;
 use set::Set;
+pub(crate) use sets::Spop;
+use sets::{
+    Combine, Sadd, Scard, SetOp, Sintercard, Sismember, Smembers, Smismember, Smove,
+    Srandmember, Srem, Sscan,
+};
 pub(crate) use wait::Wait;
 
 use super::{
+    db::WrongType,
     frame::Frame,
-    parser::Parser,
+    parser::{NOT_AN_INTEGER, Parser, ParserError},
 };
 
 pub mod get;
+pub mod info;
+pub mod psync;
 pub mod replconf;
 
+mod bgsave;
+mod client;
 mod config;
+mod del;
 mod echo;
-mod info;
+mod hash;
+mod hello;
+mod lastsave;
+mod list;
 mod ping;
+mod replicaof;
+mod save;
+mod scan;
 mod set;
-mod psync;
+mod sets;
 mod wait;
 
 
@@ -33,34 +69,327 @@ pub enum Command {
     Echo(Echo),
     Set(Set),
     Get(Get),
+    Del(Del),
     Info(Info),
     Replconf(Replconf),
     Psync(Psync),
     Wait(Wait),
-    Config(ConfigCmd)
+    Config(ConfigCmd),
+    Save(Save),
+    Bgsave(Bgsave),
+    Lastsave(Lastsave),
+    Replicaof(Replicaof),
+    Hello(Hello),
+    Client(Client),
+    Push(Push),
+    Pop(Pop),
+    Lrange(Lrange),
+    Llen(Llen),
+    Lindex(Lindex),
+    Lset(Lset),
+    Linsert(Linsert),
+    Lrem(Lrem),
+    Ltrim(Ltrim),
+    Lpos(Lpos),
+    Lmove(Lmove),
+    Lmpop(Lmpop),
+    Block(Block),
+    Hset(Hset),
+    Hsetnx(Hsetnx),
+    Hget(Hget),
+    Hmget(Hmget),
+    Hdel(Hdel),
+    Hexists(Hexists),
+    Hlen(Hlen),
+    Hgetall(Hgetall),
+    Hincrby(Hincrby),
+    Hincrbyfloat(Hincrbyfloat),
+    Hstrlen(Hstrlen),
+    Hrandfield(Hrandfield),
+    Hscan(Hscan),
+    Hexpire(Hexpire),
+    Httl(Httl),
+    Hpersist(Hpersist),
+    Sadd(Sadd),
+    Srem(Srem),
+    Sismember(Sismember),
+    Smismember(Smismember),
+    Smembers(Smembers),
+    Scard(Scard),
+    Spop(Spop),
+    Srandmember(Srandmember),
+    Smove(Smove),
+    Sscan(Sscan),
+    Combine(Combine),
+    Sintercard(Sintercard),
 }
 
 impl Command {
-    pub fn from_frame(frame: &Frame) -> Result<Command> {
+    // writes change the dataset and are propagated to replicas; every
+    // command has to be listed here so a new one can't be forgotten
+    pub fn is_write(&self) -> bool {
+        match self {
+            Command::Set(_)
+            | Command::Del(_)
+            | Command::Push(_)
+            | Command::Pop(_)
+            | Command::Lset(_)
+            | Command::Linsert(_)
+            | Command::Lrem(_)
+            | Command::Ltrim(_)
+            | Command::Lmove(_)
+            | Command::Lmpop(_)
+            | Command::Block(_)
+            | Command::Hset(_)
+            | Command::Hsetnx(_)
+            | Command::Hdel(_)
+            | Command::Hincrby(_)
+            | Command::Hincrbyfloat(_)
+            | Command::Hexpire(_)
+            | Command::Hpersist(_)
+            | Command::Sadd(_)
+            | Command::Srem(_)
+            | Command::Spop(_)
+            | Command::Smove(_) => true,
+            // only the *STORE forms write
+            Command::Combine(combine) => combine.is_store(),
+            Command::Ping(_)
+            | Command::Echo(_)
+            | Command::Get(_)
+            | Command::Info(_)
+            | Command::Replconf(_)
+            | Command::Psync(_)
+            | Command::Wait(_)
+            | Command::Config(_)
+            | Command::Save(_)
+            | Command::Bgsave(_)
+            | Command::Lastsave(_)
+            | Command::Replicaof(_)
+            | Command::Hello(_)
+            | Command::Client(_)
+            | Command::Lrange(_)
+            | Command::Llen(_)
+            | Command::Lindex(_)
+            | Command::Lpos(_)
+            | Command::Hget(_)
+            | Command::Hmget(_)
+            | Command::Hexists(_)
+            | Command::Hlen(_)
+            | Command::Hgetall(_)
+            | Command::Hstrlen(_)
+            | Command::Hrandfield(_)
+            | Command::Hscan(_)
+            | Command::Httl(_)
+            | Command::Sismember(_)
+            | Command::Smismember(_)
+            | Command::Smembers(_)
+            | Command::Scard(_)
+            | Command::Srandmember(_)
+            | Command::Sscan(_)
+            | Command::Sintercard(_) => false,
+        }
+    }
+
+    // deterministic form of a write along with the frame to propagate,
+    // so replicas end up with the same dataset whenever they apply it
+    pub fn rewrite(self, frame: Frame) -> (Command, Frame) {
+        match self {
+            Command::Set(set) => {
+                let set = set.absolute();
+                let frame = set.to_frame();
+
+                (Command::Set(set), frame)
+            }
+            Command::Hexpire(hexpire) => {
+                let hexpire = hexpire.absolute();
+                let frame = hexpire.to_frame();
+
+                (Command::Hexpire(hexpire), frame)
+            }
+            cmd => (cmd, frame),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Result<Command, CommandError> {
         // all redis commands come in form of RESP arrays
-        let mut parser = Parser::new(frame)?;
+        let mut parser = Parser::new(frame).map_err(|e| CommandError::Other(e.to_string()))?;
 
-        let command_name = parser.next_string()?.to_lowercase();
+        let command_name = parser.next_string()
+            .map_err(|_| CommandError::Other("empty command".to_string()))?
+            .to_lowercase();
+
+        Command::parse(&command_name, &mut parser)
+            .map_err(|e| CommandError::from_parse_error(&command_name, e))?
+            .ok_or_else(|| CommandError::unknown_command(frame))
+    }
 
-        let command = match &command_name[..] {
-            "ping" => Command::Ping(Ping::parse_args(&mut parser)?),
-            "echo" => Command::Echo(Echo::parse_args(&mut parser)?),
-            "set" => Command::Set(Set::parse_args(&mut parser)?),
-            "get" => Command::Get(Get::parse_args(&mut parser)?),
+    // None for commands we don't know about
+    fn parse(command_name: &str, parser: &mut Parser) -> Result<Option<Command>> {
+        let command = match command_name {
+            "ping" => Command::Ping(Ping::parse_args(parser)?),
+            "echo" => Command::Echo(Echo::parse_args(parser)?),
+            "set" => Command::Set(Set::parse_args(parser)?),
+            "get" => Command::Get(Get::parse_args(parser)?),
+            "del" | "unlink" => Command::Del(Del::parse_args(parser)?),
             "info" => Command::Info(Info::parse_args()?),
-            "replconf" => Command::Replconf(Replconf::parse_args(&mut parser)?),
-            "psync" => Command::Psync(Psync::parse_args(&mut parser)?),
-            "wait" => Command::Wait(Wait::parse_args(&mut parser)?),
-            "config" => Command::Config(ConfigCmd::parse_args(&mut parser)?),
-            _ => unimplemented!(),
+            "replconf" => Command::Replconf(Replconf::parse_args(parser)?),
+            "psync" => Command::Psync(Psync::parse_args(parser)?),
+            "wait" => Command::Wait(Wait::parse_args(parser)?),
+            "config" => Command::Config(ConfigCmd::parse_args(parser)?),
+            "save" => Command::Save(Save::parse_args(parser)?),
+            "bgsave" => Command::Bgsave(Bgsave::parse_args(parser)?),
+            "lastsave" => Command::Lastsave(Lastsave::parse_args(parser)?),
+            "replicaof" | "slaveof" => Command::Replicaof(Replicaof::parse_args(parser)?),
+            "hello" => Command::Hello(Hello::parse_args(parser)?),
+            "client" => Command::Client(Client::parse_args(parser)?),
+            "lpush" => Command::Push(Push::parse_args(End::Left, false, parser)?),
+            "rpush" => Command::Push(Push::parse_args(End::Right, false, parser)?),
+            "lpushx" => Command::Push(Push::parse_args(End::Left, true, parser)?),
+            "rpushx" => Command::Push(Push::parse_args(End::Right, true, parser)?),
+            "lpop" => Command::Pop(Pop::parse_args(End::Left, parser)?),
+            "rpop" => Command::Pop(Pop::parse_args(End::Right, parser)?),
+            "lrange" => Command::Lrange(Lrange::parse_args(parser)?),
+            "llen" => Command::Llen(Llen::parse_args(parser)?),
+            "lindex" => Command::Lindex(Lindex::parse_args(parser)?),
+            "lset" => Command::Lset(Lset::parse_args(parser)?),
+            "linsert" => Command::Linsert(Linsert::parse_args(parser)?),
+            "lrem" => Command::Lrem(Lrem::parse_args(parser)?),
+            "ltrim" => Command::Ltrim(Ltrim::parse_args(parser)?),
+            "lpos" => Command::Lpos(Lpos::parse_args(parser)?),
+            "lmove" => Command::Lmove(Lmove::parse_args(parser)?),
+            "lmpop" => Command::Lmpop(Lmpop::parse_args(parser)?),
+            "blpop" => Command::Block(Block::parse_pop(End::Left, parser)?),
+            "brpop" => Command::Block(Block::parse_pop(End::Right, parser)?),
+            "blmove" => Command::Block(Block::parse_move(parser)?),
+            "blmpop" => Command::Block(Block::parse_mpop(parser)?),
+            "hset" => Command::Hset(Hset::parse_args(parser)?),
+            "hsetnx" => Command::Hsetnx(Hsetnx::parse_args(parser)?),
+            "hget" => Command::Hget(Hget::parse_args(parser)?),
+            "hmget" => Command::Hmget(Hmget::parse_args(parser)?),
+            "hdel" => Command::Hdel(Hdel::parse_args(parser)?),
+            "hexists" => Command::Hexists(Hexists::parse_args(parser)?),
+            "hlen" => Command::Hlen(Hlen::parse_args(parser)?),
+            "hkeys" => Command::Hgetall(Hgetall::parse_args(Part::Fields, parser)?),
+            "hvals" => Command::Hgetall(Hgetall::parse_args(Part::Values, parser)?),
+            "hgetall" => Command::Hgetall(Hgetall::parse_args(Part::Both, parser)?),
+            "hincrby" => Command::Hincrby(Hincrby::parse_args(parser)?),
+            "hincrbyfloat" => Command::Hincrbyfloat(Hincrbyfloat::parse_args(parser)?),
+            "hstrlen" => Command::Hstrlen(Hstrlen::parse_args(parser)?),
+            "hrandfield" => Command::Hrandfield(Hrandfield::parse_args(parser)?),
+            "hscan" => Command::Hscan(Hscan::parse_args(parser)?),
+            "hexpire" => Command::Hexpire(Hexpire::parse_args(ExpireArg::Seconds, parser)?),
+            "hpexpire" => Command::Hexpire(Hexpire::parse_args(ExpireArg::Millis, parser)?),
+            "hexpireat" => Command::Hexpire(Hexpire::parse_args(ExpireArg::UnixSeconds, parser)?),
+            "hpexpireat" => Command::Hexpire(Hexpire::parse_args(ExpireArg::UnixMillis, parser)?),
+            "httl" => Command::Httl(Httl::parse_args(TtlReport::Seconds, parser)?),
+            "hpttl" => Command::Httl(Httl::parse_args(TtlReport::Millis, parser)?),
+            "hexpiretime" => Command::Httl(Httl::parse_args(TtlReport::UnixSeconds, parser)?),
+            "hpexpiretime" => Command::Httl(Httl::parse_args(TtlReport::UnixMillis, parser)?),
+            "hpersist" => Command::Hpersist(Hpersist::parse_args(parser)?),
+            "sadd" => Command::Sadd(Sadd::parse_args(parser)?),
+            "srem" => Command::Srem(Srem::parse_args(parser)?),
+            "sismember" => Command::Sismember(Sismember::parse_args(parser)?),
+            "smismember" => Command::Smismember(Smismember::parse_args(parser)?),
+            "smembers" => Command::Smembers(Smembers::parse_args(parser)?),
+            "scard" => Command::Scard(Scard::parse_args(parser)?),
+            "spop" => Command::Spop(Spop::parse_args(parser)?),
+            "srandmember" => Command::Srandmember(Srandmember::parse_args(parser)?),
+            "smove" => Command::Smove(Smove::parse_args(parser)?),
+            "sscan" => Command::Sscan(Sscan::parse_args(parser)?),
+            "sinter" => Command::Combine(Combine::parse_args(SetOp::Inter, false, parser)?),
+            "sunion" => Command::Combine(Combine::parse_args(SetOp::Union, false, parser)?),
+            "sdiff" => Command::Combine(Combine::parse_args(SetOp::Diff, false, parser)?),
+            "sinterstore" => Command::Combine(Combine::parse_args(SetOp::Inter, true, parser)?),
+            "sunionstore" => Command::Combine(Combine::parse_args(SetOp::Union, true, parser)?),
+            "sdiffstore" => Command::Combine(Combine::parse_args(SetOp::Diff, true, parser)?),
+            "sintercard" => Command::Sintercard(Sintercard::parse_args(parser)?),
+            _ => return Ok(None),
         };
 
-        Ok(command)
+        Ok(Some(command))
+    }
+}
+
+// replied to the client as an error frame, the connection stays open
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum CommandError {
+    UnknownCommand { name: String, args: Vec<String> },
+    WrongArity(String),
+    Syntax,
+    WrongType,
+    ReadOnly,
+    Other(String),
+}
+
+impl CommandError {
+    fn from_parse_error(command_name: &str, err: anyhow::Error) -> CommandError {
+        if let Some(err) = err.downcast_ref::<CommandError>() {
+            return err.clone();
+        }
+
+        if let Some(err) = err.downcast_ref::<ParserError>() {
+            return match err {
+                ParserError::EndOfStream | ParserError::TrailingArgs => {
+                    CommandError::WrongArity(command_name.to_string())
+                }
+                ParserError::Other(msg) => CommandError::Other(msg.clone()),
+            };
+        }
+
+        if err.downcast_ref::<ParseIntError>().is_some() {
+            return CommandError::Other(NOT_AN_INTEGER.to_string());
+        }
+
+        CommandError::Other(err.to_string())
+    }
+
+    fn unknown_command(frame: &Frame) -> CommandError {
+        let mut words = match frame {
+            Frame::Array(items) => items.iter().map(|item| match item {
+                Frame::Simple(s) => s.clone(),
+                Frame::Bulk(b) => String::from_utf8_lossy(b).to_string(),
+                _ => String::new(),
+            }).collect(),
+            _ => vec![],
+        }.into_iter();
+
+        CommandError::UnknownCommand {
+            name: words.next().unwrap_or_default(),
+            args: words.collect(),
+        }
+    }
+
+    pub fn to_frame(&self) -> Frame {
+        Frame::Error(self.to_string())
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand { name, args } => {
+                write!(f, "ERR unknown command '{}', with args beginning with: ", name)?;
+                for arg in args {
+                    write!(f, "'{}' ", arg)?;
+                }
+                Ok(())
+            }
+            CommandError::WrongArity(name) => {
+                write!(f, "ERR wrong number of arguments for '{}' command", name)
+            }
+            CommandError::Syntax => write!(f, "ERR syntax error"),
+            CommandError::WrongType => {
+                write!(f, "WRONGTYPE Operation against a key holding the wrong kind of value")
+            }
+            CommandError::ReadOnly => write!(f, "READONLY You can't write against a read only replica."),
+            CommandError::Other(msg) => write!(f, "ERR {}", msg),
+        }
+    }
+}
+
+impl From<WrongType> for CommandError {
+    fn from(_: WrongType) -> CommandError {
+        CommandError::WrongType
     }
 }
 
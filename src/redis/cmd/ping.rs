@@ -26,11 +26,14 @@ impl Ping {
     }
 
     pub fn parse_args(parser: &mut Parser) -> Result<Ping> {
-        match parser.next_string() {
-            Ok(args) => Ok(Ping::new(Some(args))),
-            Err(ParserError::EndOfStream) => Ok(Ping::new(None)),
-            Err(e) => Err(e.into()),
-        }
+        let msg = match parser.next_string() {
+            Ok(msg) => Some(msg),
+            Err(ParserError::EndOfStream) => None,
+            Err(e) => return Err(e.into()),
+        };
+        parser.finish()?;
+
+        Ok(Ping::new(msg))
     }
 
     pub fn apply(&self) -> Frame {
@@ -1,6 +1,7 @@
 use anyhow::Result;
 
 use crate::redis::{
+    db::Db,
     frame::Frame,
     parser::Parser,
     ServerInfo, utils::Named,
@@ -11,7 +12,15 @@ use super::ClientCmd;
 #[derive(Debug, PartialEq, Clone)]
 pub struct Psync {
     pub replication_id: String,
-    pub offset: i32,
+    // offset of the first byte the replica is missing
+    pub offset: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Resync {
+    Full { offset: u64, rdb: Vec<u8> },
+    // tail of the stream to send after +CONTINUE
+    Partial(Vec<u8>),
 }
 
 impl Named for Psync {
@@ -19,14 +28,54 @@ impl Named for Psync {
 }
 
 impl Psync {
-    pub fn parse_args(_: &mut Parser) -> Result<Psync> {
-        // TODO implement
-        Ok(Psync { replication_id: "replication_id".to_string(), offset: 1 })
+    pub fn parse_args(parser: &mut Parser) -> Result<Psync> {
+        let replication_id = parser.next_string()?;
+        let offset = parser.next_string()?.parse::<i64>()?;
+        parser.finish()?;
+
+        Ok(Psync { replication_id, offset })
+    }
+
+    // must be called under `sync_lock`, so nothing is propagated
+    // between the snapshot (or backlog tail) and the replica subscription
+    pub(crate) async fn resync(&self, server_info: &ServerInfo, db: &Db) -> Resync {
+        let replinfo = &server_info.replinfo;
+
+        if self.offset > 0 && self.same_history(server_info).await {
+            let backlog = replinfo.backlog.lock().unwrap();
+
+            if let Some(tail) = backlog.since(self.offset as u64 - 1) {
+                return Resync::Partial(tail);
+            }
+        }
+
+        Resync::Full {
+            offset: replinfo.offset(),
+            rdb: db.build_rdb_frame(),
+        }
     }
 
-    pub async fn apply(&self, server_info: &mut ServerInfo) -> Frame {
-        server_info.replinfo.add_replica().await;
-        Frame::Simple(format!("FULLRESYNC {} 0", server_info.replinfo.id))
+    // replica follows our current history or the one we had before a promotion
+    async fn same_history(&self, server_info: &ServerInfo) -> bool {
+        let replinfo = &server_info.replinfo;
+
+        if self.replication_id == *replinfo.id.read().await {
+            return true;
+        }
+
+        match &*replinfo.second_id.read().await {
+            Some((id, offset)) => self.replication_id == *id && self.offset as u64 <= *offset,
+            None => false,
+        }
+    }
+
+    pub(crate) async fn apply(&self, server_info: &ServerInfo, resync: &Resync) -> Frame {
+        let id = server_info.replinfo.id.read().await;
+
+        match resync {
+            Resync::Full { offset, .. } => Frame::Simple(format!("FULLRESYNC {} {}", id, offset)),
+            Resync::Partial(_) => Frame::Simple(format!("CONTINUE {}", id)),
+        }
     }
 }
 
@@ -5,6 +5,7 @@ use anyhow::{Error, Result};
 use crate::redis::{
     frame::Frame,
     parser::Parser,
+    replicas::ReplicaInfo,
     ServerInfo,
     utils::Named
 };
@@ -29,6 +30,7 @@ impl Replconf {
             "listening-port" => ReplconfParam::ListeningPort,
             "capa" => ReplconfParam::Capa,
             "getack" => ReplconfParam::Getack,
+            "ack" => ReplconfParam::Ack,
             unknown => return Err(Error::msg(
                 format!("Unknown replconf param `{unknown}`")
             )),
@@ -36,16 +38,35 @@ impl Replconf {
         Ok(Replconf { param, arg })
     }
 
-    pub async fn apply(&self, server_info: &ServerInfo) -> Frame {
+    // `replica` collects what a replica announces about itself before PSYNC
+    pub fn apply(&self, server_info: &ServerInfo, replica: &mut ReplicaInfo) -> Frame {
         match self.param {
-            ReplconfParam::ListeningPort | ReplconfParam::Capa => Frame::Simple("OK".to_string()),
-            ReplconfParam::Getack => {
-                Frame::Array(vec![
-                    Frame::Simple("replconf".to_string()),
-                    Frame::Simple("ACK".to_string()),
-                    Frame::Simple(server_info.replinfo.offset.lock().await.to_string()),
-                ])
+            ReplconfParam::ListeningPort => {
+                replica.listening_port = self.arg.parse().ok();
+                Frame::Simple("OK".to_string())
             }
+            ReplconfParam::Capa => {
+                replica.capa.push(self.arg.clone());
+                Frame::Simple("OK".to_string())
+            }
+            ReplconfParam::Getack => Replconf::ack(server_info.replinfo.offset()).to_frame(),
+            // acks are consumed by the replication stream, never replied to
+            ReplconfParam::Ack => Frame::Null,
+        }
+    }
+
+    pub fn ack(offset: u64) -> Replconf {
+        Replconf {
+            param: ReplconfParam::Ack,
+            arg: offset.to_string(),
+        }
+    }
+
+    // offset acknowledged by REPLCONF ACK
+    pub fn ack_offset(&self) -> Option<u64> {
+        match self.param {
+            ReplconfParam::Ack => self.arg.parse().ok(),
+            _ => None,
         }
     }
 
@@ -80,6 +101,7 @@ pub enum ReplconfParam {
     ListeningPort,
     Capa,
     Getack,
+    Ack,
 }
 
 impl fmt::Display for ReplconfParam {
@@ -87,7 +109,8 @@ impl fmt::Display for ReplconfParam {
         match self {
             ReplconfParam::ListeningPort => write!(f, "listening-port"),
             ReplconfParam::Capa => write!(f, "capa"),
-            ReplconfParam::Getack => write!(f, "GETACK")
+            ReplconfParam::Getack => write!(f, "GETACK"),
+            ReplconfParam::Ack => write!(f, "ACK"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use anyhow::Result;
+
+use crate::redis::{
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    replica,
+    ServerInfo,
+    utils::{Addr, Named},
+};
+
+use super::ClientCmd;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Replicaof {
+    // None stands for `NO ONE`
+    pub master: Option<Addr>,
+}
+
+impl Named for Replicaof {
+    const NAME: &'static str = "REPLICAOF";
+}
+
+impl Replicaof {
+    pub fn parse_args(parser: &mut Parser) -> Result<Replicaof> {
+        let host = parser.next_string()?;
+        let port = parser.next_string()?;
+        parser.finish()?;
+
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(Replicaof { master: None });
+        }
+
+        port.parse::<u16>()?;
+
+        Ok(Replicaof { master: Some(Addr { host, port }) })
+    }
+
+    pub async fn apply(
+        &self,
+        db: &mut Db,
+        server_info: &ServerInfo,
+    ) -> Frame {
+        match &self.master {
+            None => replica::promote(server_info, db).await,
+            Some(master) => {
+                let current = server_info.replinfo.master();
+                if current.as_ref() == Some(master) {
+                    return Frame::Simple("OK Already connected to specified master".to_string());
+                }
+
+                replica::replicate(server_info, db, master.clone());
+            }
+        }
+
+        Frame::Simple("OK".to_string())
+    }
+}
+
+impl ClientCmd for Replicaof {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Replicaof::NAME.into()));
+
+        match &self.master {
+            Some(addr) => {
+                frame.add(Frame::Bulk(addr.host.clone().into()));
+                frame.add(Frame::Bulk(addr.port.clone().into()));
+            }
+            None => {
+                frame.add(Frame::Bulk("NO".into()));
+                frame.add(Frame::Bulk("ONE".into()));
+            }
+        }
+
+        frame
+    }
+}
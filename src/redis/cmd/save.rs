@@ -0,0 +1,47 @@
+use anyhow::Result;
+
+use crate::redis::{
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    ServerInfo,
+    utils::Named,
+};
+
+use super::ClientCmd;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Save {}
+
+impl Named for Save {
+    const NAME: &'static str = "SAVE";
+}
+
+impl Save {
+    pub fn new() -> Save {
+        Save {}
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Save> {
+        parser.finish()?;
+
+        Ok(Save::new())
+    }
+
+    pub async fn apply(&self, db: &Db, server_info: &ServerInfo) -> Frame {
+        match server_info.persistence.save(db).await {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        }
+    }
+}
+
+impl ClientCmd for Save {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Save::NAME.into()));
+
+        frame
+    }
+}
@@ -0,0 +1,115 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::CommandError,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::glob_match,
+};
+
+// Cursor and options shared by the *SCAN commands. Elements are visited in
+// the order of a hash that doesn't depend on the collection, and the cursor
+// is the hash to resume from, so anything present during the whole scan is
+// returned at least once however the collection changes between calls.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScanArgs {
+    pub cursor: u64,
+    pub pattern: Option<Bytes>,
+    pub count: usize,
+    // HSCAN only: fields without their values
+    pub novalues: bool,
+}
+
+const DEFAULT_COUNT: usize = 10;
+
+impl ScanArgs {
+    pub fn parse(parser: &mut Parser, allow_novalues: bool) -> Result<ScanArgs> {
+        let cursor = parser.next_string()?
+            .parse()
+            .map_err(|_| CommandError::Other("invalid cursor".to_string()))?;
+
+        let mut args = ScanArgs { cursor, pattern: None, count: DEFAULT_COUNT, novalues: false };
+
+        loop {
+            let option = match parser.next_string() {
+                Ok(option) => option,
+                Err(ParserError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            match &option.to_uppercase()[..] {
+                "MATCH" => args.pattern = Some(parser.next_bytes()?),
+                "COUNT" => {
+                    args.count = match parser.next_int() {
+                        Ok(count) if count > 0 => count as usize,
+                        _ => return Err(CommandError::Syntax.into()),
+                    };
+                }
+                "NOVALUES" if allow_novalues => args.novalues = true,
+                _ => return Err(CommandError::Syntax.into()),
+            }
+        }
+
+        Ok(args)
+    }
+
+    pub fn add_to(&self, frame: &mut Frame) {
+        frame.add(Frame::Bulk(self.cursor.to_string().into()));
+        if let Some(pattern) = &self.pattern {
+            frame.add(Frame::Bulk("MATCH".into()));
+            frame.add(Frame::Bulk(pattern.clone()));
+        }
+        frame.add(Frame::Bulk("COUNT".into()));
+        frame.add(Frame::Bulk(self.count.to_string().into()));
+        if self.novalues {
+            frame.add(Frame::Bulk("NOVALUES".into()));
+        }
+    }
+
+    // the next cursor, zero once everything was visited, and about `count`
+    // elements from this one; MATCH filters a batch after it is taken,
+    // so a batch can come back empty before the scan is over
+    pub fn scan<'a>(&self, elements: impl Iterator<Item = &'a Bytes>) -> (u64, Vec<&'a Bytes>) {
+        let mut batch: Vec<(u64, &Bytes)> = elements
+            .map(|element| (position(element), element))
+            .filter(|(position, _)| *position >= self.cursor)
+            .collect();
+
+        // the `count` lowest positions, without sorting the rest
+        let mut cursor = 0;
+        if batch.len() > self.count {
+            let (_, &mut (last, _), rest) = batch
+                .select_nth_unstable_by_key(self.count - 1, |(position, _)| *position);
+            cursor = rest.iter()
+                .map(|(position, _)| *position)
+                .filter(|position| *position > last)
+                .min()
+                .unwrap_or(0);
+
+            // elements sharing a position can't be told apart by the cursor
+            batch.retain(|(position, _)| *position <= last);
+        }
+
+        let elements = batch.into_iter()
+            .map(|(_, element)| element)
+            .filter(|element| self.pattern.as_ref().is_none_or(|pattern| glob_match(pattern, element)))
+            .collect();
+
+        (cursor, elements)
+    }
+}
+
+pub fn reply(cursor: u64, items: Vec<Frame>) -> Frame {
+    Frame::Array(vec![Frame::Bulk(cursor.to_string().into()), Frame::Array(items)])
+}
+
+fn position(element: &[u8]) -> u64 {
+    // fixed keys, positions stay the same for the life of the process
+    let mut hasher = DefaultHasher::new();
+    element.hash(&mut hasher);
+
+    hasher.finish()
+}
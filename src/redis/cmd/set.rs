@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use bytes::Bytes;
@@ -11,15 +11,23 @@ use crate::redis::{
         ParserError,
     },
 };
-use crate::redis::cmd::ClientCmd;
+use crate::redis::cmd::{ClientCmd, CommandError};
 use crate::redis::utils::{int_as_bytes, Named};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Set {
-    key: String,
+    key: Bytes,
     value: Bytes,
 
-    expire: Option<Duration>,
+    expire: Option<Expiry>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expiry {
+    // EX, PX
+    In(Duration),
+    // EXAT, PXAT
+    At(SystemTime),
 }
 
 impl Named for Set {
@@ -27,54 +35,107 @@ impl Named for Set {
 }
 
 impl Set {
-    pub fn new(key: String, value: Bytes, expire: Option<Duration>) -> Set {
+    pub fn new(key: Bytes, value: Bytes, expire: Option<Expiry>) -> Set {
         Set { key, value, expire }
     }
 
     pub fn parse_args(parser: &mut Parser) -> Result<Set> {
-        let key = parser.next_string()?;
+        let key = parser.next_bytes()?;
         let value = parser.next_bytes()?;
 
-        let mut expire = None;
-
-        match parser.next_string() {
-            Ok(s) if s.to_lowercase() == "px" => {
-                let millis = parser.next_int()?;
-                expire = Some(Duration::from_millis(millis))
-            }
-            Ok(_) => {}
-            Err(ParserError::EndOfStream) => {}
+        let expire = match parser.next_string() {
+            Ok(s) => match &s.to_lowercase()[..] {
+                option @ ("ex" | "px" | "exat" | "pxat") => {
+                    Some(parse_expire(option, parser.next_signed_int()?)?)
+                }
+                _ => return Err(CommandError::Syntax.into()),
+            },
+            Err(ParserError::EndOfStream) => None,
             Err(e) => return Err(e.into())
+        };
+
+        if parser.finish().is_err() {
+            return Err(CommandError::Syntax.into());
         }
 
         Ok(Set::new(key, value, expire))
     }
 
+    // relative expiry turned into an absolute one, so replicas expire the key
+    // at the same moment however late they apply the write
+    pub fn absolute(&self) -> Set {
+        let expire = match self.expire {
+            // checked by `parse_expire`, only a deadline past the clock's range is left relative
+            Some(Expiry::In(duration)) => SystemTime::now().checked_add(duration).map(Expiry::At),
+            ref expire => expire.clone(),
+        };
+
+        Set { expire, ..self.clone() }
+    }
+
     pub fn apply(&self, db: &mut Db) -> Frame {
-        db.set(
-            self.key.clone(),
-            self.value.clone(),
-            self.expire,
-        );
+        let expire = match self.expire {
+            None => None,
+            Some(Expiry::In(duration)) => Some(duration),
+            // a deadline in the past leaves nothing behind
+            Some(Expiry::At(at)) => Some(at.duration_since(SystemTime::now()).unwrap_or_default()),
+        };
+
+        if expire == Some(Duration::ZERO) {
+            db.delete(&self.key);
+        } else {
+            db.set(self.key.clone(), self.value.clone(), expire);
+        }
 
         Frame::Simple("OK".to_string())
     }
 }
 
+// positive amount of seconds or milliseconds, relative or since the epoch, that
+// stays within a millisecond unix time once added to the current time as redis does
+fn parse_expire(option: &str, value: i64) -> Result<Expiry, CommandError> {
+    let invalid = || CommandError::Other("invalid expire time in 'set' command".to_string());
+
+    if value <= 0 {
+        return Err(invalid());
+    }
+
+    let millis = match option {
+        "ex" | "exat" => value.checked_mul(1000).ok_or_else(invalid)?,
+        _ => value,
+    };
+
+    let expire = match option {
+        "ex" | "px" => {
+            let now = UNIX_EPOCH.elapsed().unwrap_or_default().as_millis() as i64;
+            now.checked_add(millis).ok_or_else(invalid)?;
+
+            Expiry::In(Duration::from_millis(millis as u64))
+        }
+        _ => Expiry::At(UNIX_EPOCH.checked_add(Duration::from_millis(millis as u64)).ok_or_else(invalid)?),
+    };
+
+    Ok(expire)
+}
+
 impl ClientCmd for Set {
     fn to_frame(&self) -> Frame {
         let mut frame = Frame::array();
 
         frame.add(Frame::Bulk(Set::NAME.into()));
-        frame.add(Frame::Bulk(self.key.clone().into()));
+        frame.add(Frame::Bulk(self.key.clone()));
         frame.add(Frame::Bulk(self.value.clone()));
 
-        if let Some(duration) = self.expire {
-            frame.add(Frame::Bulk("PX".into()));
-            frame.add(Frame::Bulk(
-                Bytes::from(int_as_bytes(&(duration.as_millis() as usize)))
-            ));
-        }
+        let (option, millis) = match self.expire {
+            None => return frame,
+            Some(Expiry::In(duration)) => ("PX", duration.as_millis()),
+            Some(Expiry::At(at)) => ("PXAT", at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()),
+        };
+
+        frame.add(Frame::Bulk(option.into()));
+        frame.add(Frame::Bulk(
+            Bytes::from(int_as_bytes(&(millis as usize)))
+        ));
 
         frame
     }
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+// SINTER, SUNION, SDIFF and their *STORE forms
+#[derive(Debug, PartialEq, Clone)]
+pub struct Combine {
+    op: SetOp,
+    keys: Vec<Bytes>,
+    // the *STORE forms keep the result there and reply with its size
+    destination: Option<Bytes>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SetOp {
+    Inter,
+    Union,
+    // members of the first set found in none of the others
+    Diff,
+}
+
+impl Named for Combine {
+    const NAME: &'static str = "SINTER";
+}
+
+impl SetOp {
+    // only the members of the result are copied, a missing key is an empty set
+    pub fn apply(&self, sets: &[Option<&HashSet<Bytes>>]) -> HashSet<Bytes> {
+        match self {
+            SetOp::Inter => intersection(sets).cloned().collect(),
+            SetOp::Union => sets.iter().flatten().flat_map(|set| set.iter()).cloned().collect(),
+            SetOp::Diff => match sets.split_first() {
+                Some((Some(first), rest)) => first.iter()
+                    .filter(|member| !rest.iter().flatten().any(|set| set.contains(*member)))
+                    .cloned()
+                    .collect(),
+                _ => HashSet::new(),
+            },
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SetOp::Inter => "SINTER",
+            SetOp::Union => "SUNION",
+            SetOp::Diff => "SDIFF",
+        }
+    }
+}
+
+// members of every set, nothing at all as soon as one is missing
+pub(super) fn intersection<'a>(sets: &[Option<&'a HashSet<Bytes>>]) -> impl Iterator<Item = &'a Bytes> {
+    let mut sets: Vec<&HashSet<Bytes>> = sets.iter().copied().collect::<Option<_>>().unwrap_or_default();
+    // checking the smallest set against the others is the cheapest
+    sets.sort_unstable_by_key(|set| set.len());
+
+    let smallest = if sets.is_empty() { None } else { Some(sets.remove(0)) };
+    smallest.into_iter()
+        .flatten()
+        .filter(move |member| sets.iter().all(|set| set.contains(*member)))
+}
+
+impl Combine {
+    pub fn new(op: SetOp, keys: Vec<Bytes>, destination: Option<Bytes>) -> Combine {
+        Combine { op, keys, destination }
+    }
+
+    pub fn parse_args(op: SetOp, store: bool, parser: &mut Parser) -> Result<Combine> {
+        let destination = if store { Some(parser.next_bytes()?) } else { None };
+        let keys = super::parse_rest(parser)?;
+
+        Ok(Combine::new(op, keys, destination))
+    }
+
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let result = self.op.apply(&super::load(keyspace, &self.keys)?);
+
+            let destination = match &self.destination {
+                Some(destination) => destination,
+                None => return Ok(super::set_reply(&result)),
+            };
+
+            // the destination is replaced whatever it held, an empty result
+            // leaves it deleted
+            let len = result.len();
+            keyspace.insert(destination.clone(), Value::Set(result));
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+
+    pub fn is_store(&self) -> bool {
+        self.destination.is_some()
+    }
+}
+
+impl ClientCmd for Combine {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        match &self.destination {
+            Some(destination) => {
+                frame.add(Frame::Bulk(format!("{}STORE", self.op.name()).into()));
+                frame.add(Frame::Bulk(destination.clone()));
+            }
+            None => frame.add(Frame::Bulk(self.op.name().into())),
+        }
+        for key in &self.keys {
+            frame.add(Frame::Bulk(key.clone()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::CommandError,
+    db::Keyspace,
+    frame::Frame,
+    parser::{Parser, ParserError},
+};
+
+pub use combine::{Combine, SetOp};
+pub use sadd::Sadd;
+pub use scard::Scard;
+pub use sintercard::Sintercard;
+pub use sismember::Sismember;
+pub use smembers::Smembers;
+pub use smismember::Smismember;
+pub use smove::Smove;
+pub use spop::Spop;
+pub use srandmember::Srandmember;
+pub use srem::Srem;
+pub use sscan::Sscan;
+
+mod combine;
+mod sadd;
+mod scard;
+mod sintercard;
+mod sismember;
+mod smembers;
+mod smismember;
+mod smove;
+mod spop;
+mod srandmember;
+mod srem;
+mod sscan;
+
+// one or more arguments up to the end of the command
+fn parse_rest(parser: &mut Parser) -> Result<Vec<Bytes>> {
+    let mut args = vec![parser.next_bytes()?];
+
+    loop {
+        match parser.next_bytes() {
+            Ok(arg) => args.push(arg),
+            Err(ParserError::EndOfStream) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(args)
+}
+
+// the sets at `keys`, None for a missing key; every key is checked to
+// hold a set, even once the result can't change anymore
+fn load<'a>(keyspace: &'a mut Keyspace, keys: &[Bytes]) -> Result<Vec<Option<&'a HashSet<Bytes>>>, CommandError> {
+    for key in keys {
+        if let Some(value) = keyspace.get(key) {
+            value.as_set()?;
+        }
+    }
+
+    let keyspace = &*keyspace;
+    Ok(keys.iter().map(|key| keyspace.peek(key).and_then(|value| value.as_set().ok())).collect())
+}
+
+fn set_reply<'a>(members: impl IntoIterator<Item = &'a Bytes>) -> Frame {
+    Frame::Set(members.into_iter().cloned().map(Frame::Bulk).collect())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sadd {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl Named for Sadd {
+    const NAME: &'static str = "SADD";
+}
+
+impl Sadd {
+    pub fn new(key: Bytes, members: Vec<Bytes>) -> Sadd {
+        Sadd { key, members }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Sadd> {
+        let key = parser.next_bytes()?;
+        let members = super::parse_rest(parser)?;
+
+        Ok(Sadd::new(key, members))
+    }
+
+    // number of members that weren't in the set yet
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let set = keyspace.get_or_insert(&self.key, || Value::Set(HashSet::new())).as_set_mut()?;
+            let added = self.members.iter().filter(|member| set.insert((*member).clone())).count();
+            if added == 0 {
+                keyspace.unchanged();
+            }
+
+            Ok(Frame::Integer(added as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Sadd {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Sadd::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for member in &self.members {
+            frame.add(Frame::Bulk(member.clone()));
+        }
+
+        frame
+    }
+}
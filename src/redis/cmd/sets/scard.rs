@@ -0,0 +1,54 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scard {
+    key: Bytes,
+}
+
+impl Named for Scard {
+    const NAME: &'static str = "SCARD";
+}
+
+impl Scard {
+    pub fn new(key: Bytes) -> Scard {
+        Scard { key }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Scard> {
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Scard::new(key))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let len = match keyspace.get(&self.key) {
+                Some(value) => value.as_set()?.len(),
+                None => 0,
+            };
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Scard {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Scard::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+
+        frame
+    }
+}
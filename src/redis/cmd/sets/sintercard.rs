@@ -0,0 +1,87 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::Named,
+};
+
+use super::combine::intersection;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sintercard {
+    keys: Vec<Bytes>,
+    // 0 counts the whole intersection
+    limit: u64,
+}
+
+impl Named for Sintercard {
+    const NAME: &'static str = "SINTERCARD";
+}
+
+impl Sintercard {
+    pub fn new(keys: Vec<Bytes>, limit: u64) -> Sintercard {
+        Sintercard { keys, limit }
+    }
+
+    // SINTERCARD numkeys key [key ...] [LIMIT limit]
+    pub fn parse_args(parser: &mut Parser) -> Result<Sintercard> {
+        let numkeys = match parser.next_int() {
+            Ok(numkeys) if numkeys > 0 => numkeys,
+            _ => return Err(CommandError::Other("numkeys should be greater than 0".to_string()).into()),
+        };
+
+        let mut keys = vec![];
+        for _ in 0..numkeys {
+            keys.push(parser.next_bytes().map_err(|_| {
+                CommandError::Other("Number of keys can't be greater than number of args".to_string())
+            })?);
+        }
+
+        let limit = match parser.next_string() {
+            Ok(option) if option.eq_ignore_ascii_case("limit") => {
+                parser.next_int().map_err(|_| CommandError::Other("LIMIT can't be negative".to_string()))?
+            }
+            Ok(_) => return Err(CommandError::Syntax.into()),
+            Err(ParserError::EndOfStream) => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if parser.finish().is_err() {
+            return Err(CommandError::Syntax.into());
+        }
+
+        Ok(Sintercard::new(keys, limit))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let sets = super::load(keyspace, &self.keys)?;
+            // counting stops at the limit
+            let limit = if self.limit > 0 { self.limit as usize } else { usize::MAX };
+            let len = intersection(&sets).take(limit).count();
+
+            Ok(Frame::Integer(len as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Sintercard {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Sintercard::NAME.into()));
+        frame.add(Frame::Bulk(self.keys.len().to_string().into()));
+        for key in &self.keys {
+            frame.add(Frame::Bulk(key.clone()));
+        }
+        if self.limit > 0 {
+            frame.add(Frame::Bulk("LIMIT".into()));
+            frame.add(Frame::Bulk(self.limit.to_string().into()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sismember {
+    key: Bytes,
+    member: Bytes,
+}
+
+impl Named for Sismember {
+    const NAME: &'static str = "SISMEMBER";
+}
+
+impl Sismember {
+    pub fn new(key: Bytes, member: Bytes) -> Sismember {
+        Sismember { key, member }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Sismember> {
+        let key = parser.next_bytes()?;
+        let member = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Sismember::new(key, member))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let exists = match keyspace.get(&self.key) {
+                Some(value) => value.as_set()?.contains(&self.member),
+                None => false,
+            };
+
+            Ok(Frame::Integer(exists as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Sismember {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Sismember::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        frame.add(Frame::Bulk(self.member.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Smembers {
+    key: Bytes,
+}
+
+impl Named for Smembers {
+    const NAME: &'static str = "SMEMBERS";
+}
+
+impl Smembers {
+    pub fn new(key: Bytes) -> Smembers {
+        Smembers { key }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Smembers> {
+        let key = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Smembers::new(key))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let frame = match keyspace.get(&self.key) {
+                Some(value) => super::set_reply(value.as_set()?),
+                None => Frame::Set(vec![]),
+            };
+
+            Ok(frame)
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Smembers {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Smembers::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Smismember {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl Named for Smismember {
+    const NAME: &'static str = "SMISMEMBER";
+}
+
+impl Smismember {
+    pub fn new(key: Bytes, members: Vec<Bytes>) -> Smismember {
+        Smismember { key, members }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Smismember> {
+        let key = parser.next_bytes()?;
+        let members = super::parse_rest(parser)?;
+
+        Ok(Smismember::new(key, members))
+    }
+
+    // 1 or 0 for each member, in the order they were given
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let set = match keyspace.get(&self.key) {
+                Some(value) => Some(value.as_set()?),
+                None => None,
+            };
+
+            Ok(Frame::Array(self.members.iter().map(|member| {
+                let exists = set.is_some_and(|set| set.contains(member));
+                Frame::Integer(exists as i64)
+            }).collect()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Smismember {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Smismember::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for member in &self.members {
+            frame.add(Frame::Bulk(member.clone()));
+        }
+
+        frame
+    }
+}
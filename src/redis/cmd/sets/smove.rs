@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::{Db, Value},
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Smove {
+    source: Bytes,
+    destination: Bytes,
+    member: Bytes,
+}
+
+impl Named for Smove {
+    const NAME: &'static str = "SMOVE";
+}
+
+impl Smove {
+    pub fn new(source: Bytes, destination: Bytes, member: Bytes) -> Smove {
+        Smove { source, destination, member }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Smove> {
+        let source = parser.next_bytes()?;
+        let destination = parser.next_bytes()?;
+        let member = parser.next_bytes()?;
+        parser.finish()?;
+
+        Ok(Smove::new(source, destination, member))
+    }
+
+    // 1 when the member was moved, 0 when the source doesn't have it
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            // both keys must hold sets whether or not anything moves
+            if let Some(value) = keyspace.get(&self.destination) {
+                value.as_set()?;
+            }
+            let source = match keyspace.get_mut(&self.source) {
+                Some(value) => value.as_set_mut()?,
+                None => return Ok(Frame::Integer(0)),
+            };
+
+            if self.source == self.destination {
+                let found = source.contains(&self.member);
+                keyspace.unchanged();
+                return Ok(Frame::Integer(found as i64));
+            }
+            if !source.remove(&self.member) {
+                keyspace.unchanged();
+                return Ok(Frame::Integer(0));
+            }
+
+            keyspace.get_or_insert(&self.destination, || Value::Set(HashSet::new()))
+                .as_set_mut()?
+                .insert(self.member.clone());
+
+            Ok(Frame::Integer(1))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Smove {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Smove::NAME.into()));
+        frame.add(Frame::Bulk(self.source.clone()));
+        frame.add(Frame::Bulk(self.destination.clone()));
+        frame.add(Frame::Bulk(self.member.clone()));
+
+        frame
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::{sample_iter, Named},
+};
+
+use super::Srem;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spop {
+    key: Bytes,
+    // with a count the reply is a set, even of one member
+    count: Option<u64>,
+}
+
+impl Named for Spop {
+    const NAME: &'static str = "SPOP";
+}
+
+impl Spop {
+    pub fn new(key: Bytes, count: Option<u64>) -> Spop {
+        Spop { key, count }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Spop> {
+        let key = parser.next_bytes()?;
+
+        let count = match parser.next_int() {
+            Ok(count) => Some(count),
+            Err(ParserError::EndOfStream) => None,
+            Err(_) => return Err(CommandError::Other(
+                "value is out of range, must be positive".to_string()
+            ).into()),
+        };
+        parser.finish()?;
+
+        Ok(Spop::new(key, count))
+    }
+
+    // the reply along with the SREM of the popped members, which is what
+    // replicas get since they would pick other members
+    pub fn apply(&self, db: &mut Db) -> (Frame, Option<Srem>) {
+        db.with_keyspace(|keyspace| {
+            let set = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_set_mut()?,
+                None if self.count.is_some() => return Ok((Frame::Set(vec![]), None)),
+                None => return Ok((Frame::Null, None)),
+            };
+
+            let count = self.count.unwrap_or(1) as usize;
+            let popped: Vec<Bytes> = if count >= set.len() {
+                std::mem::take(set).into_iter().collect()
+            } else {
+                let popped: Vec<Bytes> = sample_iter(set.iter(), count).into_iter().cloned().collect();
+                for member in &popped {
+                    set.remove(member);
+                }
+                popped
+            };
+            if popped.is_empty() {
+                keyspace.unchanged();
+            }
+
+            let frame = match self.count {
+                Some(_) => super::set_reply(&popped),
+                None => popped.first().cloned().map(Frame::Bulk).unwrap_or(Frame::Null),
+            };
+            let srem = (!popped.is_empty()).then(|| Srem::new(self.key.clone(), popped));
+
+            Ok((frame, srem))
+        }).unwrap_or_else(|e: CommandError| (e.to_frame(), None))
+    }
+}
+
+impl ClientCmd for Spop {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Spop::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        if let Some(count) = self.count {
+            frame.add(Frame::Bulk(count.to_string().into()));
+        }
+
+        frame
+    }
+}
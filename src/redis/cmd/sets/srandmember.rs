@@ -0,0 +1,86 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::{Parser, ParserError},
+    utils::{pick_iter, random_index, sample_iter, Named, MAX_PICKS},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Srandmember {
+    key: Bytes,
+    // with a count the reply is an array; distinct members when positive,
+    // possibly repeated ones when negative
+    count: Option<i64>,
+}
+
+impl Named for Srandmember {
+    const NAME: &'static str = "SRANDMEMBER";
+}
+
+impl Srandmember {
+    pub fn new(key: Bytes, count: Option<i64>) -> Srandmember {
+        Srandmember { key, count }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Srandmember> {
+        let key = parser.next_bytes()?;
+
+        let count = match parser.next_signed_int() {
+            Ok(count) => Some(count),
+            Err(ParserError::EndOfStream) => None,
+            Err(e) => return Err(e.into()),
+        };
+        if parser.finish().is_err() {
+            return Err(CommandError::Syntax.into());
+        }
+        if count.is_some_and(|count| count < 0 && count.unsigned_abs() > MAX_PICKS) {
+            return Err(CommandError::Other("value is out of range".to_string()).into());
+        }
+
+        Ok(Srandmember::new(key, count))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let set = match keyspace.get(&self.key) {
+                Some(value) => value.as_set()?,
+                None if self.count.is_some() => return Ok(Frame::array()),
+                None => return Ok(Frame::Null),
+            };
+
+            let count = match self.count {
+                None => {
+                    let member = set.iter().nth(random_index(set.len())).cloned();
+                    return Ok(member.map_or(Frame::Null, Frame::Bulk));
+                }
+                Some(count) => count,
+            };
+
+            let picked: Vec<&Bytes> = if count >= 0 {
+                sample_iter(set.iter(), count as usize)
+            } else {
+                pick_iter(set.iter(), set.len(), count.unsigned_abs() as usize)
+            };
+
+            Ok(Frame::Array(picked.into_iter().cloned().map(Frame::Bulk).collect()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Srandmember {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Srandmember::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        if let Some(count) = self.count {
+            frame.add(Frame::Bulk(count.to_string().into()));
+        }
+
+        frame
+    }
+}
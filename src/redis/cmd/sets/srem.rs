@@ -0,0 +1,64 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Srem {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl Named for Srem {
+    const NAME: &'static str = "SREM";
+}
+
+impl Srem {
+    pub fn new(key: Bytes, members: Vec<Bytes>) -> Srem {
+        Srem { key, members }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Srem> {
+        let key = parser.next_bytes()?;
+        let members = super::parse_rest(parser)?;
+
+        Ok(Srem::new(key, members))
+    }
+
+    // number of members removed, the key goes with the last one
+    pub fn apply(&self, db: &mut Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let set = match keyspace.get_mut(&self.key) {
+                Some(value) => value.as_set_mut()?,
+                None => return Ok(Frame::Integer(0)),
+            };
+
+            let removed = self.members.iter().filter(|member| set.remove(*member)).count();
+            if removed == 0 {
+                keyspace.unchanged();
+            }
+
+            Ok(Frame::Integer(removed as i64))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Srem {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Srem::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        for member in &self.members {
+            frame.add(Frame::Bulk(member.clone()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::redis::{
+    cmd::{scan::{self, ScanArgs}, ClientCmd, CommandError},
+    db::Db,
+    frame::Frame,
+    parser::Parser,
+    utils::Named,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sscan {
+    key: Bytes,
+    args: ScanArgs,
+}
+
+impl Named for Sscan {
+    const NAME: &'static str = "SSCAN";
+}
+
+impl Sscan {
+    pub fn new(key: Bytes, args: ScanArgs) -> Sscan {
+        Sscan { key, args }
+    }
+
+    pub fn parse_args(parser: &mut Parser) -> Result<Sscan> {
+        let key = parser.next_bytes()?;
+        let args = ScanArgs::parse(parser, false)?;
+
+        Ok(Sscan::new(key, args))
+    }
+
+    pub fn apply(&self, db: &Db) -> Frame {
+        db.with_keyspace(|keyspace| {
+            let set = match keyspace.get(&self.key) {
+                Some(value) => value.as_set()?,
+                None => return Ok(scan::reply(0, vec![])),
+            };
+
+            let (cursor, members) = self.args.scan(set.iter());
+
+            Ok(scan::reply(cursor, members.into_iter().cloned().map(Frame::Bulk).collect()))
+        }).unwrap_or_else(|e: CommandError| e.to_frame())
+    }
+}
+
+impl ClientCmd for Sscan {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+
+        frame.add(Frame::Bulk(Sscan::NAME.into()));
+        frame.add(Frame::Bulk(self.key.clone()));
+        self.args.add_to(&mut frame);
+
+        frame
+    }
+}
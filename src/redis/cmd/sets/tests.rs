@@ -0,0 +1,227 @@
+use bytes::Bytes;
+
+use crate::redis::cmd::tests::{bulks, items, prepare_conn, send, sorted, start_server};
+use crate::redis::cmd::{ClientCmd, Command, Psync};
+use crate::redis::frame::Frame;
+
+use super::Srem;
+
+fn members(frame: &[&str]) -> Vec<Frame> {
+    items(bulks(frame))
+}
+
+#[tokio::test]
+async fn test_add_rem_members() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(send(&mut conn, &["SADD", "tags", "a", "b", "a"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["SADD", "tags", "b", "c"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SCARD", "tags"]).await, Frame::Integer(3));
+    assert_eq!(send(&mut conn, &["SCARD", "none"]).await, Frame::Integer(0));
+    assert_eq!(sorted(send(&mut conn, &["SMEMBERS", "tags"]).await), members(&["a", "b", "c"]));
+    assert_eq!(send(&mut conn, &["SMEMBERS", "none"]).await, Frame::Array(vec![]));
+
+    assert_eq!(send(&mut conn, &["SISMEMBER", "tags", "b"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SISMEMBER", "tags", "z"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["SISMEMBER", "none", "b"]).await, Frame::Integer(0));
+    assert_eq!(
+        send(&mut conn, &["SMISMEMBER", "tags", "z", "a"]).await,
+        Frame::Array(vec![Frame::Integer(0), Frame::Integer(1)]),
+    );
+
+    assert_eq!(send(&mut conn, &["SREM", "tags", "a", "z"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SREM", "tags", "b", "c"]).await, Frame::Integer(2));
+    // the last member takes the key with it
+    assert_eq!(send(&mut conn, &["LPUSH", "tags", "x"]).await, Frame::Integer(1));
+}
+
+#[tokio::test]
+async fn test_pop_randmember() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["SADD", "s", "a", "b", "c"]).await;
+
+    let random = send(&mut conn, &["SRANDMEMBER", "s"]).await;
+    assert!(members(&["a", "b", "c"]).contains(&random));
+    assert_eq!(sorted(send(&mut conn, &["SRANDMEMBER", "s", "5"]).await), members(&["a", "b", "c"]));
+    assert_eq!(items(send(&mut conn, &["SRANDMEMBER", "s", "-5"]).await).len(), 5);
+    for count in ["-1000000000000", &i64::MIN.to_string()] {
+        assert_eq!(
+            send(&mut conn, &["SRANDMEMBER", "s", count]).await,
+            Frame::Error("ERR value is out of range".to_string()),
+        );
+    }
+    assert_eq!(send(&mut conn, &["SRANDMEMBER", "none"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["SRANDMEMBER", "none", "2"]).await, Frame::Array(vec![]));
+
+    let popped = send(&mut conn, &["SPOP", "s"]).await;
+    assert!(members(&["a", "b", "c"]).contains(&popped));
+    assert_eq!(send(&mut conn, &["SCARD", "s"]).await, Frame::Integer(2));
+    assert_eq!(items(send(&mut conn, &["SPOP", "s", "5"]).await).len(), 2);
+    assert_eq!(send(&mut conn, &["SCARD", "s"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["SPOP", "s"]).await, Frame::Null);
+    assert_eq!(send(&mut conn, &["SPOP", "s", "1"]).await, Frame::Array(vec![]));
+    assert_eq!(
+        send(&mut conn, &["SPOP", "s", "-1"]).await,
+        Frame::Error("ERR value is out of range, must be positive".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_move() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["SADD", "from", "a", "b"]).await;
+
+    assert_eq!(send(&mut conn, &["SMOVE", "from", "to", "a"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SMOVE", "from", "to", "z"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["SMOVE", "from", "from", "b"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SMEMBERS", "to"]).await, bulks(&["a"]));
+
+    assert_eq!(send(&mut conn, &["SMOVE", "from", "to", "b"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SCARD", "from"]).await, Frame::Integer(0));
+    assert_eq!(sorted(send(&mut conn, &["SMEMBERS", "to"]).await), members(&["a", "b"]));
+}
+
+#[tokio::test]
+async fn test_algebra() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    send(&mut conn, &["SADD", "s1", "a", "b", "c", "d"]).await;
+    send(&mut conn, &["SADD", "s2", "c", "d", "e"]).await;
+    send(&mut conn, &["SADD", "s3", "d", "e", "f"]).await;
+
+    assert_eq!(sorted(send(&mut conn, &["SINTER", "s1", "s2"]).await), members(&["c", "d"]));
+    assert_eq!(sorted(send(&mut conn, &["SINTER", "s1", "s2", "s3"]).await), members(&["d"]));
+    assert_eq!(send(&mut conn, &["SINTER", "s1", "none"]).await, Frame::Array(vec![]));
+    assert_eq!(
+        sorted(send(&mut conn, &["SUNION", "s1", "s3", "none"]).await),
+        members(&["a", "b", "c", "d", "e", "f"]),
+    );
+    assert_eq!(sorted(send(&mut conn, &["SDIFF", "s1", "s2", "s3"]).await), members(&["a", "b"]));
+    assert_eq!(send(&mut conn, &["SDIFF", "none", "s1"]).await, Frame::Array(vec![]));
+
+    assert_eq!(send(&mut conn, &["SINTERSTORE", "dst", "s1", "s2"]).await, Frame::Integer(2));
+    assert_eq!(sorted(send(&mut conn, &["SMEMBERS", "dst"]).await), members(&["c", "d"]));
+    assert_eq!(send(&mut conn, &["SUNIONSTORE", "dst", "s2", "s3"]).await, Frame::Integer(4));
+    assert_eq!(send(&mut conn, &["SDIFFSTORE", "dst", "s2", "s1"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SMEMBERS", "dst"]).await, bulks(&["e"]));
+
+    // a destination of another type is replaced, an empty result deletes it
+    send(&mut conn, &["SET", "string", "x"]).await;
+    assert_eq!(send(&mut conn, &["SINTERSTORE", "string", "s1", "s3"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SMEMBERS", "string"]).await, bulks(&["d"]));
+    assert_eq!(send(&mut conn, &["SINTERSTORE", "string", "s1", "none"]).await, Frame::Integer(0));
+    assert_eq!(send(&mut conn, &["GET", "string"]).await, Frame::Null);
+
+    assert_eq!(send(&mut conn, &["SINTERCARD", "2", "s1", "s2"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["SINTERCARD", "2", "s1", "s2", "LIMIT", "1"]).await, Frame::Integer(1));
+    assert_eq!(send(&mut conn, &["SINTERCARD", "2", "s1", "s2", "LIMIT", "0"]).await, Frame::Integer(2));
+    assert_eq!(send(&mut conn, &["SINTERCARD", "2", "none", "s1"]).await, Frame::Integer(0));
+    assert_eq!(sorted(send(&mut conn, &["SDIFF", "s2", "none", "s3"]).await), members(&["c"]));
+    assert_eq!(
+        send(&mut conn, &["SINTERCARD", "0", "s1"]).await,
+        Frame::Error("ERR numkeys should be greater than 0".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["SINTERCARD", "3", "s1", "s2"]).await,
+        Frame::Error("ERR Number of keys can't be greater than number of args".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["SINTERCARD", "2", "s1", "s2", "LIMIT", "-1"]).await,
+        Frame::Error("ERR LIMIT can't be negative".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_scan() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    let all: Vec<String> = (0..30).map(|i| format!("m{}", i)).collect();
+    let mut sadd = vec!["SADD", "s"];
+    sadd.extend(all.iter().map(|member| member.as_str()));
+    send(&mut conn, &sadd).await;
+
+    let mut seen = vec![];
+    let mut cursor = "0".to_string();
+    loop {
+        let reply = items(send(&mut conn, &["SSCAN", "s", &cursor, "COUNT", "7"]).await);
+        cursor = match &reply[0] {
+            Frame::Bulk(cursor) => String::from_utf8(cursor.to_vec()).unwrap(),
+            frame => panic!("unexpected frame {:?}", frame),
+        };
+        seen.extend(items(reply[1].clone()));
+
+        if cursor == "0" {
+            break;
+        }
+    }
+    seen.sort_by_key(|item| format!("{:?}", item));
+    let mut expected = items(bulks(&all.iter().map(|member| member.as_str()).collect::<Vec<_>>()));
+    expected.sort_by_key(|item| format!("{:?}", item));
+    assert_eq!(seen, expected);
+
+    let reply = items(send(&mut conn, &["SSCAN", "s", "0", "MATCH", "m1?", "COUNT", "100"]).await);
+    assert_eq!(items(reply[1].clone()).len(), 10);
+    assert_eq!(
+        send(&mut conn, &["SSCAN", "s", "0", "NOVALUES"]).await,
+        Frame::Error("ERR syntax error".to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_wrong_type() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+    let wrongtype = Frame::Error("WRONGTYPE Operation against a key holding the wrong kind of value".to_string());
+
+    send(&mut conn, &["SADD", "s", "a"]).await;
+    send(&mut conn, &["SET", "string", "x"]).await;
+
+    assert_eq!(send(&mut conn, &["GET", "s"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["HSET", "s", "a", "1"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SADD", "string", "a"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SMEMBERS", "string"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SPOP", "string"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SMOVE", "s", "string", "a"]).await, wrongtype);
+    // every key is checked, even after an empty one settled the result
+    assert_eq!(send(&mut conn, &["SINTER", "none", "string"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SSCAN", "string", "0"]).await, wrongtype);
+    assert_eq!(send(&mut conn, &["SCARD", "s"]).await, Frame::Integer(1));
+}
+
+// replicas remove the members the master picked
+#[tokio::test]
+async fn test_spop_propagated_as_srem() {
+    let addr = start_server().await;
+    let mut client = prepare_conn(addr).await;
+
+    let mut replica = prepare_conn(addr).await;
+    replica.write_frame(&Psync::default().to_frame()).await.unwrap();
+    replica.read_frame().await.unwrap().unwrap();
+    replica.read_rdb().await.unwrap().unwrap();
+
+    send(&mut client, &["SADD", "s", "a", "b", "c"]).await;
+    match Command::from_frame(&replica.read_frame().await.unwrap().unwrap()).unwrap() {
+        Command::Sadd(_) => {}
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    let popped = items(send(&mut client, &["SPOP", "s", "2"]).await)
+        .into_iter()
+        .map(|member| match member {
+            Frame::Bulk(member) => member,
+            frame => panic!("unexpected frame {:?}", frame),
+        })
+        .collect::<Vec<Bytes>>();
+
+    assert_eq!(
+        replica.read_frame().await.unwrap().unwrap(),
+        Srem::new(Bytes::from_static(b"s"), popped).to_frame(),
+    );
+}
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 
 use bytes::Bytes;
+use tokio::io::AsyncReadExt;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::time::{Duration, sleep};
 
@@ -8,11 +9,13 @@ use crate::redis::{
     Config,
     connection::Connection,
     db::Db,
+    frame::Protocol,
     Role,
     ServerInfo,
     tests::make_frame,
 };
 use crate::redis::cmd::ClientCmd;
+use crate::redis::cmd::set::Expiry;
 use crate::Server;
 
 use super::*;
@@ -22,9 +25,11 @@ fn config() -> Config {
 }
 
 pub(super) async fn start_server() -> SocketAddr {
-    // redis server fixture
+    start_server_with(config()).await
+}
 
-    let cfg = config();
+// redis server fixture
+pub(super) async fn start_server_with(cfg: Config) -> SocketAddr {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
@@ -43,6 +48,31 @@ pub(super) async fn prepare_conn(addr: SocketAddr) -> Connection {
     Connection::new(stream)
 }
 
+// array of bulk strings, the way clients send commands
+pub(super) fn bulks(items: &[&str]) -> Frame {
+    Frame::Array(items.iter().map(|item| Frame::Bulk(item.to_string().into())).collect())
+}
+
+pub(super) async fn send(conn: &mut Connection, words: &[&str]) -> Frame {
+    conn.write_frame(&bulks(words)).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap()
+}
+
+pub(super) fn items(frame: Frame) -> Vec<Frame> {
+    match frame {
+        Frame::Array(items) => items,
+        frame => panic!("not an array: {:?}", frame),
+    }
+}
+
+// array items in a stable order, for replies in hash order
+pub(super) fn sorted(frame: Frame) -> Vec<Frame> {
+    let mut items = items(frame);
+    items.sort_by_key(|item| format!("{:?}", item));
+
+    items
+}
+
 // ECHO
 #[test]
 fn test_cmd_from_frame_echo() {
@@ -88,7 +118,7 @@ fn test_cmd_from_frame_set() {
 
     let expected = Command::Set(
         Set::new(
-            "hey".to_string(),
+            Bytes::from_static(b"hey"),
             Bytes::from_static(b"you"),
             None,
         )
@@ -100,6 +130,59 @@ fn test_cmd_from_frame_set() {
     )
 }
 
+#[test]
+fn test_cmd_from_frame_set_expiry() {
+    let input = b"*5\r\n$3\r\nSET\r\n$3\r\nhey\r\n$3\r\nyou\r\n$4\r\nPXAT\r\n$13\r\n1700000000000\r\n";
+    let cmd = Command::from_frame(&make_frame(input)).unwrap();
+
+    let at = std::time::UNIX_EPOCH + Duration::from_millis(1_700_000_000_000);
+    let expected = Command::Set(
+        Set::new(Bytes::from_static(b"hey"), Bytes::from_static(b"you"), Some(Expiry::At(at)))
+    );
+
+    assert_eq!(cmd, expected);
+
+    let input = b"*5\r\n$3\r\nSET\r\n$3\r\nhey\r\n$3\r\nyou\r\n$2\r\nEX\r\n$2\r\n10\r\n";
+    let cmd = Command::from_frame(&make_frame(input)).unwrap();
+
+    let expected = Command::Set(
+        Set::new(Bytes::from_static(b"hey"), Bytes::from_static(b"you"), Some(Expiry::In(Duration::from_secs(10))))
+    );
+
+    assert_eq!(cmd, expected);
+}
+
+#[test]
+fn test_cmd_write_flag() {
+    let set = make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nhey\r\n$3\r\nyou\r\n");
+    let get = make_frame(b"*2\r\n$3\r\nGET\r\n$3\r\nhey\r\n");
+
+    assert!(Command::from_frame(&set).unwrap().is_write());
+    assert!(!Command::from_frame(&get).unwrap().is_write());
+}
+
+#[test]
+fn test_cmd_rewrite_relative_expiry() {
+    let input = b"*5\r\n$3\r\nSET\r\n$3\r\nhey\r\n$3\r\nyou\r\n$2\r\nPX\r\n$4\r\n1000\r\n";
+    let frame = make_frame(input);
+
+    let now = std::time::UNIX_EPOCH.elapsed().unwrap().as_millis() as u64;
+    let (_, propagated) = Command::from_frame(&frame).unwrap().rewrite(frame);
+
+    // replicas get the absolute deadline
+    let items = match propagated {
+        Frame::Array(items) => items,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+    assert_eq!(items[3], Frame::Bulk(Bytes::from_static(b"PXAT")));
+
+    let at: u64 = match &items[4] {
+        Frame::Bulk(millis) => std::str::from_utf8(millis).unwrap().parse().unwrap(),
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+    assert!(at >= now + 1000 && at < now + 2000);
+}
+
 #[test]
 fn test_cmd_from_frame_get() {
     let input = b"*2\r\n$3\r\nGET\r\n$3\r\nhey\r\n";
@@ -108,7 +191,7 @@ fn test_cmd_from_frame_get() {
     let cmd = Command::from_frame(&frame).unwrap();
 
     let expected = Command::Get(
-        Get::new("hey".to_string())
+        Get::new(Bytes::from_static(b"hey"))
     );
 
     assert_eq!(
@@ -123,7 +206,7 @@ async fn test_cmd_set_get() {
     let mut conn = prepare_conn(addr).await;
 
     let set = Set::new(
-        "hey".to_string(),
+        Bytes::from_static(b"hey"),
         Bytes::from_static(b"you"),
         None,
     );
@@ -137,7 +220,7 @@ async fn test_cmd_set_get() {
         expected,
     );
 
-    let get = Get::new("hey".to_string());
+    let get = Get::new(Bytes::from_static(b"hey"));
     conn.write_frame(&get.to_frame()).await.unwrap();
 
     let response_frame = conn.read_frame().await.unwrap().unwrap();
@@ -165,7 +248,7 @@ async fn test_cmd_set_with_ttl() {
 
     conn.read_frame().await.unwrap().unwrap();
 
-    let get = Get::new("grape".to_string());
+    let get = Get::new(Bytes::from_static(b"grape"));
     conn.write_frame(&get.to_frame()).await.unwrap();
 
     let before_expire = conn.read_frame().await.unwrap().unwrap();
@@ -195,8 +278,313 @@ async fn test_cmd_info() {
     let response = conn.read_frame().await.unwrap().unwrap();
 
     let expected = Frame::Bulk(Bytes::from_static(
-        b"role:master\nmaster_replid:8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb\nmaster_repl_offset:0"
+        b"role:master\nconnected_slaves:0\nmaster_replid:8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb\n\
+        master_replid2:0000000000000000000000000000000000000000\n\
+        master_repl_offset:0\nsecond_repl_offset:-1\n\
+        repl_backlog_active:1\nrepl_backlog_size:1048576\n\
+        repl_backlog_first_byte_offset:1\nrepl_backlog_histlen:0\n\
+        expired_keys:0"
     ));
 
     assert_eq!(response, expected)
 }
+
+// PSYNC
+#[tokio::test]
+async fn test_cmd_psync_partial_resync() {
+    let addr = start_server().await;
+    let mut client = prepare_conn(addr).await;
+
+    let mut replica = prepare_conn(addr).await;
+    replica.write_frame(&Psync::default().to_frame()).await.unwrap();
+
+    let response = replica.read_frame().await.unwrap().unwrap();
+    let replid = match response {
+        Frame::Simple(resp) => {
+            let parts: Vec<&str> = resp.split_whitespace().collect();
+            assert_eq!(parts[0], "FULLRESYNC");
+            assert_eq!(parts[2], "0");
+            parts[1].to_string()
+        }
+        frame => panic!("unexpected response {:?}", frame),
+    };
+    replica.read_rdb().await.unwrap().unwrap();
+
+    let set = Set::new(Bytes::from_static(b"hey"), Bytes::from_static(b"you"), None);
+    client.write_frame(&set.to_frame()).await.unwrap();
+    client.read_frame().await.unwrap();
+
+    assert_eq!(replica.read_frame().await.unwrap().unwrap(), set.to_frame());
+
+    // a replica that has seen nothing since offset 0 gets the tail only
+    let mut late_replica = prepare_conn(addr).await;
+    let psync = Psync { replication_id: replid.clone(), offset: 1 };
+    late_replica.write_frame(&psync.to_frame()).await.unwrap();
+
+    assert_eq!(
+        late_replica.read_frame().await.unwrap().unwrap(),
+        Frame::Simple(format!("CONTINUE {}", replid)),
+    );
+    assert_eq!(late_replica.read_frame().await.unwrap().unwrap(), set.to_frame());
+
+    // unknown replication id falls back to full resync
+    let mut other_replica = prepare_conn(addr).await;
+    let psync = Psync { replication_id: "unknown".to_string(), offset: 1 };
+    other_replica.write_frame(&psync.to_frame()).await.unwrap();
+
+    let response = other_replica.read_frame().await.unwrap().unwrap();
+    let expected_offset = set.to_frame().byte_len();
+    assert_eq!(
+        response,
+        Frame::Simple(format!("FULLRESYNC {} {}", replid, expected_offset)),
+    );
+}
+
+// expiry on a master is replicated as DEL
+#[tokio::test]
+async fn test_expired_key_propagated_as_del() {
+    let addr = start_server().await;
+    let mut client = prepare_conn(addr).await;
+
+    let mut replica = prepare_conn(addr).await;
+    replica.write_frame(&Psync::default().to_frame()).await.unwrap();
+    replica.read_frame().await.unwrap().unwrap();
+    replica.read_rdb().await.unwrap().unwrap();
+
+    let input = b"*5\r\n$3\r\nSET\r\n$3\r\nhey\r\n$3\r\nyou\r\n$2\r\nPX\r\n$2\r\n50\r\n";
+    client.write_frame(&make_frame(input)).await.unwrap();
+    client.read_frame().await.unwrap();
+
+    match Command::from_frame(&replica.read_frame().await.unwrap().unwrap()).unwrap() {
+        Command::Set(_) => {}
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    assert_eq!(
+        replica.read_frame().await.unwrap().unwrap(),
+        Del::new(vec![Bytes::from_static(b"hey")]).to_frame(),
+    );
+}
+
+#[tokio::test]
+async fn test_cmd_binary_key() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    let key = Bytes::from_static(b"\xff\x00id\r\n\xfe");
+
+    let set = Set::new(key.clone(), Bytes::from_static(b"blob"), None);
+    conn.write_frame(&set.to_frame()).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Simple("OK".to_string()));
+
+    conn.write_frame(&Get::new(key.clone()).to_frame()).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Bulk(Bytes::from_static(b"blob")));
+
+    conn.write_frame(&Del::new(vec![key]).to_frame()).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Integer(1));
+}
+
+// what the client sent can't break an error reply into several
+#[tokio::test]
+async fn test_error_reply_sanitized() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    assert_eq!(
+        send(&mut conn, &["foo\r\n+INJECTED"]).await,
+        Frame::Error("ERR unknown command 'foo  +INJECTED', with args beginning with: ".to_string()),
+    );
+    assert_eq!(
+        send(&mut conn, &["HELLO", "3", "x\r\n+OK"]).await,
+        Frame::Error("ERR Syntax error in HELLO option 'x  +OK'".to_string()),
+    );
+    assert_eq!(send(&mut conn, &["PING"]).await, Frame::Simple("PONG".to_string()));
+}
+
+// errors
+#[test]
+fn test_cmd_from_frame_errors() {
+    let cases: Vec<(&[u8], &str)> = vec![
+        (
+            b"*2\r\n$3\r\nFOO\r\n$3\r\nbar\r\n",
+            "ERR unknown command 'FOO', with args beginning with: 'bar' ",
+        ),
+        (
+            b"*1\r\n$3\r\nGET\r\n",
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            b"*3\r\n$3\r\nGET\r\n$1\r\na\r\n$1\r\nb\r\n",
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            b"*3\r\n$4\r\nWAIT\r\n$1\r\nx\r\n$1\r\n0\r\n",
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            b"*4\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\nFOO\r\n",
+            "ERR syntax error",
+        ),
+        (
+            b"*2\r\n$6\r\nCONFIG\r\n$4\r\nNOPE\r\n",
+            "ERR unknown subcommand 'NOPE'. Try CONFIG HELP.",
+        ),
+    ];
+
+    for (input, expected) in cases {
+        let err = Command::from_frame(&make_frame(input)).unwrap_err();
+
+        assert_eq!(err.to_frame(), Frame::Error(expected.to_string()));
+    }
+}
+
+#[tokio::test]
+async fn test_set_invalid_expire() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+    let invalid = Frame::Error("ERR invalid expire time in 'set' command".to_string());
+
+    send(&mut conn, &["SET", "k", "v"]).await;
+
+    for (option, value) in [
+        ("EX", "0"),
+        ("PX", "0"),
+        ("EX", "-1"),
+        ("EX", "9223372036854775807"),
+        ("PX", "9223372036854775807"),
+        ("EXAT", "9223372036854775807"),
+        ("EXAT", "0"),
+    ] {
+        let reply = send(&mut conn, &["SET", "k", "other", option, value]).await;
+        assert_eq!(reply, invalid, "{} {}", option, value);
+    }
+    for option in ["EX", "EXAT"] {
+        assert_eq!(
+            send(&mut conn, &["SET", "k", "other", option, "18446744073709551615"]).await,
+            Frame::Error("ERR value is not an integer or out of range".to_string()),
+        );
+    }
+
+    // nothing was changed and the connection is still served
+    assert_eq!(send(&mut conn, &["GET", "k"]).await, Frame::Bulk(Bytes::from_static(b"v")));
+}
+
+#[tokio::test]
+async fn test_error_reply_keeps_connection_open() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    conn.write_frame(&make_frame(b"*1\r\n$3\r\nFOO\r\n")).await.unwrap();
+    assert_eq!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Error("ERR unknown command 'FOO', with args beginning with: ".to_string()),
+    );
+
+    conn.write_frame(&Ping::new(None).to_frame()).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Simple("PONG".to_string()));
+}
+
+// HELLO
+#[tokio::test]
+async fn test_cmd_hello_switches_protocol() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    conn.write_frame(&make_frame(b"*2\r\n$5\r\nHELLO\r\n$1\r\n4\r\n")).await.unwrap();
+    assert_eq!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Error("NOPROTO unsupported protocol version".to_string()),
+    );
+
+    // RESP2 clients get CONFIG GET flattened to an array
+    let config_get = make_frame(b"*3\r\n$6\r\nCONFIG\r\n$3\r\nGET\r\n$10\r\ndbfilename\r\n");
+    conn.write_frame(&config_get).await.unwrap();
+    assert_eq!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Array(vec![Frame::Bulk("dbfilename".into()), Frame::Bulk("dump.rdb".into())]),
+    );
+
+    let hello = Hello {
+        protover: Some(3),
+        auth: Some(("default".to_string(), "secret".to_string())),
+        setname: Some("conn1".to_string()),
+    };
+    conn.write_frame(&hello.to_frame()).await.unwrap();
+
+    let reply = match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Map(pairs) => pairs,
+        frame => panic!("expected a map, got {:?}", frame),
+    };
+    assert!(reply.contains(&(Frame::Bulk("proto".into()), Frame::Integer(3))));
+    assert!(reply.contains(&(Frame::Bulk("role".into()), Frame::Bulk("master".into()))));
+
+    conn.protocol = Protocol::Resp3;
+
+    conn.write_frame(&config_get).await.unwrap();
+    assert_eq!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Map(vec![(Frame::Bulk("dbfilename".into()), Frame::Bulk("dump.rdb".into()))]),
+    );
+
+    conn.write_frame(&make_frame(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Bulk("conn1".into()));
+
+    // nulls come back as RESP3 nulls
+    conn.write_frame(&Get::new(Bytes::from_static(b"missing")).to_frame()).await.unwrap();
+    conn.stream.read_buf(&mut conn.buffer).await.unwrap();
+    assert_eq!(&conn.buffer[..], b"_\r\n");
+}
+
+#[tokio::test]
+async fn test_cmd_hello_rejects_unknown_user() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    let hello = make_frame(b"*5\r\n$5\r\nHELLO\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$3\r\nbob\r\n$1\r\nx\r\n");
+    conn.write_frame(&hello).await.unwrap();
+    assert!(matches!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Error(msg) if msg.starts_with("WRONGPASS")
+    ));
+
+    // the connection stays on RESP2
+    conn.write_frame(&Get::new(Bytes::from_static(b"missing")).to_frame()).await.unwrap();
+    conn.stream.read_buf(&mut conn.buffer).await.unwrap();
+    assert_eq!(&conn.buffer[..], b"$-1\r\n");
+}
+
+// inline commands
+#[tokio::test]
+async fn test_inline_commands() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    conn.write_bytes(b"PING\r\n\r\nSET key 'hello world'\nGET key\r\n").await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Simple("PONG".to_string()));
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Simple("OK".to_string()));
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Bulk("hello world".into()));
+
+    // inline and RESP requests can be mixed on one connection
+    conn.write_bytes(b"\n*2\r\n$4\r\nECHO\r\n$2\r\nhi\r\necho \"a\\x00b\"\n").await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Bulk("hi".into()));
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Bulk("a\0b".into()));
+}
+
+// protocol limits
+#[tokio::test]
+async fn test_oversized_bulk_disconnects_client() {
+    let addr = start_server().await;
+    let mut conn = prepare_conn(addr).await;
+
+    conn.write_bytes(b"*2\r\n$4\r\nECHO\r\n$1073741824\r\n").await.unwrap();
+    assert_eq!(
+        conn.read_frame().await.unwrap().unwrap(),
+        Frame::Error("ERR protocol error; invalid bulk length".to_string()),
+    );
+    assert_eq!(conn.read_frame().await.unwrap(), None);
+
+    // other clients are still served
+    let mut conn = prepare_conn(addr).await;
+    conn.write_frame(&Ping::new(None).to_frame()).await.unwrap();
+    assert_eq!(conn.read_frame().await.unwrap().unwrap(), Frame::Simple("PONG".to_string()));
+}
@@ -1,18 +1,15 @@
-use std::sync::Arc;
-use std::time::Duration;
-
 use anyhow::Result;
-use tokio::sync::broadcast::Sender;
-use tokio::time::sleep;
+use tokio::time::{Duration, Instant};
 
 use crate::redis::{frame::Frame, parser::Parser, ServerInfo, utils::Named};
-use crate::redis::replica::{ReplicationMsg, Replinfo};
+use crate::redis::replica::ReplicationMsg;
 
 use super::ClientCmd;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Wait {
-    pub numreplicas: i8,
+    pub numreplicas: u64,
+    // milliseconds, 0 blocks forever
     pub timeout: u64,
 }
 
@@ -22,34 +19,39 @@ impl Named for Wait {
 
 impl Wait {
     pub fn parse_args(parser: &mut Parser) -> Result<Wait> {
-        let numreplicas = parser.next_string()?.parse::<i8>().unwrap();
-        let timeout = parser.next_string()?.parse::<u64>().unwrap();
+        let numreplicas = parser.next_int()?;
+        let timeout = parser.next_int()?;
+        parser.finish()?;
 
         Ok(Wait { numreplicas, timeout })
     }
 
-    pub async fn apply(&self, sender: &mut Arc<Sender<ReplicationMsg>>, server_info: &ServerInfo) -> Frame {
-        if !has_pending(&server_info.replinfo).await {
-            // if no previous commands were propagated
-            // just reply with number of connected replicas
-            let repl_count = server_info.replinfo.count.read().await;
-            Frame::Integer(*repl_count as u64)
-        } else {
-            sender.send(ReplicationMsg::Wait(self.timeout)).unwrap();
-            sleep(Duration::from_millis(self.timeout)).await;
+    pub async fn apply(&self, server_info: &ServerInfo) -> Frame {
+        let replinfo = &server_info.replinfo;
+        let numreplicas = self.numreplicas as usize;
 
-            let _ = server_info.replinfo.wait_lock.lock().await;
-            let ack = *server_info.replinfo.repl_completed.read().await;
+        // every write issued before WAIT is covered by this offset
+        let offset = replinfo.offset();
 
-            self.reset_repl_counter(server_info).await;
+        let acked = replinfo.replicas.count_acked(offset);
+        if acked >= numreplicas {
+            return Frame::Integer(acked as i64);
+        }
 
-            Frame::Integer(ack as u64)
+        {
+            // GETACK is a part of the replication stream
+            let _guard = replinfo.sync_lock.lock().await;
+            replinfo.propagate(ReplicationMsg::Getack);
         }
-    }
 
-    async fn reset_repl_counter(&self, server_info: &ServerInfo) {
-        let mut reset = server_info.replinfo.repl_completed.write().await;
-        *reset = 0;
+        let deadline = match self.timeout {
+            0 => None,
+            timeout => Some(Instant::now() + Duration::from_millis(timeout)),
+        };
+
+        let acked = replinfo.replicas.wait_acked(offset, numreplicas, deadline).await;
+
+        Frame::Integer(acked as i64)
     }
 }
 
@@ -70,7 +72,3 @@ impl ClientCmd for Wait {
         frame
     }
 }
-
-async fn has_pending(replinfo: &Replinfo) -> bool {
-    replinfo.has_pending().await
-}
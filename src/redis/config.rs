@@ -1,11 +1,89 @@
-use super::utils::Addr;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Default, Clone)]
+use super::utils::{Addr, parse_memory};
+
+#[derive(Clone)]
 pub struct Config {
     pub addr: Addr,
     pub master_addr: Option<Addr>,
     pub dir: String,
-    pub dbfilename: String
+    pub dbfilename: String,
+    pub save: Vec<SavePoint>,
+    pub repl_backlog_size: usize,
+    pub replica_output_limit: OutputBufferLimit,
+    pub proto_limits: ProtoLimits,
+    // `repl-timeout`, a replica drops the link to a master silent for longer
+    pub repl_timeout: Duration,
+    // `repl-ping-replica-period`, masters ping their replicas so an idle
+    // link isn't mistaken for a dead one
+    pub repl_ping_period: Duration,
+}
+
+// `client-output-buffer-limit replica <hard> <soft> <soft seconds>`, 0 disables a limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputBufferLimit {
+    pub hard: usize,
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+impl Default for OutputBufferLimit {
+    fn default() -> Self {
+        OutputBufferLimit {
+            hard: 256 * 1024 * 1024,
+            soft: 64 * 1024 * 1024,
+            soft_seconds: 60,
+        }
+    }
+}
+
+// what a client may send before it is disconnected with a protocol error
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoLimits {
+    // `proto-max-bulk-len`
+    pub max_bulk_len: usize,
+    // elements in one array, set, map or push
+    pub max_multibulk_len: usize,
+    // aggregates nested in each other
+    pub max_nesting: usize,
+    // `client-query-buffer-limit`, bytes received but not yet processed
+    pub query_buffer_limit: usize,
+}
+
+impl Default for ProtoLimits {
+    fn default() -> Self {
+        ProtoLimits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_nesting: 32,
+            query_buffer_limit: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+// snapshot after `seconds` if at least `changes` writes were made
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavePoint {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            addr: Addr::default(),
+            master_addr: None,
+            dir: String::from("."),
+            dbfilename: String::from("dump.rdb"),
+            save: vec![],
+            repl_backlog_size: 1024 * 1024,
+            replica_output_limit: OutputBufferLimit::default(),
+            proto_limits: ProtoLimits::default(),
+            repl_timeout: Duration::from_secs(60),
+            repl_ping_period: Duration::from_secs(10),
+        }
+    }
 }
 
 impl Config {
@@ -24,6 +102,33 @@ impl Config {
                 ),
                 "--dir" => cfg.dir = extract_arg(&args, i + 1)?,
                 "--dbfilename" => cfg.dbfilename = extract_arg(&args, i + 1)?,
+                "--save" => cfg.save = Config::parse_save_points(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--repl-backlog-size" => cfg.repl_backlog_size = Config::parse_memory_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--client-output-buffer-limit" => cfg.replica_output_limit = Config::parse_output_limit(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--proto-max-bulk-len" => cfg.proto_limits.max_bulk_len = Config::parse_memory_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--proto-max-multibulk-len" => cfg.proto_limits.max_multibulk_len = Config::parse_count_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--proto-max-nesting" => cfg.proto_limits.max_nesting = Config::parse_count_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--client-query-buffer-limit" => cfg.proto_limits.query_buffer_limit = Config::parse_memory_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--repl-timeout" => cfg.repl_timeout = Config::parse_seconds_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
+                "--repl-ping-replica-period" => cfg.repl_ping_period = Config::parse_seconds_arg(
+                    extract_arg(&args, i + 1)?
+                )?,
                 unknown => return Err(format!("Unknown param: {}", unknown))
             }
         }
@@ -31,6 +136,10 @@ impl Config {
         Ok(cfg)
     }
 
+    pub fn db_path(&self) -> PathBuf {
+        PathBuf::from(&self.dir).join(&self.dbfilename)
+    }
+
     fn parse_master_addr(addr_line: String) -> Result<Addr, String> {
         match addr_line.split_once(' ') {
             Some((host, port)) => Ok(Addr {
@@ -40,6 +149,54 @@ impl Config {
             None => Err("No master address provided".into())
         }
     }
+
+    fn parse_memory_arg(value: String) -> Result<usize, String> {
+        parse_memory(&value).ok_or(format!("Invalid memory value: {}", value))
+    }
+
+    fn parse_count_arg(value: String) -> Result<usize, String> {
+        value.parse().map_err(|_| format!("Invalid number: {}", value))
+    }
+
+    fn parse_seconds_arg(value: String) -> Result<Duration, String> {
+        match value.parse() {
+            Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+            _ => Err(format!("Invalid number of seconds: {}", value)),
+        }
+    }
+
+    // only the replica class is supported, `slave` is accepted as an alias
+    fn parse_output_limit(line: String) -> Result<OutputBufferLimit, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts[..] {
+            [class, hard, soft, soft_seconds] if class == "replica" || class == "slave" => {
+                Ok(OutputBufferLimit {
+                    hard: Config::parse_memory_arg(hard.to_string())?,
+                    soft: Config::parse_memory_arg(soft.to_string())?,
+                    soft_seconds: soft_seconds.parse()
+                        .map_err(|_| format!("Invalid output buffer limit: {}", line))?,
+                })
+            }
+            _ => Err(format!("Invalid output buffer limit: {}", line)),
+        }
+    }
+
+    // `--save "900 1 300 10"` means 900 sec and 1 change or 300 sec and 10 changes,
+    // an empty string disables snapshotting
+    fn parse_save_points(line: String) -> Result<Vec<SavePoint>, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        parts.chunks(2).map(|pair| {
+            match pair {
+                [seconds, changes] => match (seconds.parse(), changes.parse()) {
+                    (Ok(seconds), Ok(changes)) => Ok(SavePoint { seconds, changes }),
+                    _ => Err(format!("Invalid save parameters: {}", line)),
+                },
+                _ => Err(format!("Invalid save parameters: {}", line)),
+            }
+        }).collect()
+    }
 }
 
 fn extract_arg(args: &[String], i: usize) -> Result<String, String> {
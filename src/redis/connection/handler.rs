@@ -1,42 +1,71 @@
-use std::sync::Arc;
+use tokio::time::{self, Duration, Instant, Interval, MissedTickBehavior};
 
-use tokio::sync::broadcast::Sender;
-use tokio::time::{Duration, timeout};
-
-use crate::redis::cmd::{ClientCmd, Command};
+use crate::redis::cmd::{Block, ClientCmd, Command, CommandError, Psync};
+use crate::redis::cmd::psync::Resync;
 use crate::redis::cmd::replconf::Replconf;
-use crate::redis::connection::Connection;
+use crate::redis::connection::{ClientInfo, Connection};
 use crate::redis::db::Db;
+use crate::redis::frame::Frame;
 use crate::redis::replica::ReplicationMsg;
+use crate::redis::replicas::{ReplicaInfo, ReplicaState, ReplicaStream};
 use crate::redis::ServerInfo;
 
 pub struct Handler {
     pub(crate) connection: Connection,
     db: Db,
     pub(crate) server_info: ServerInfo,
-    sender: Arc<Sender<ReplicationMsg>>,
+    // filled by REPLCONF when the peer turns out to be a replica
+    replica: ReplicaInfo,
+    client: ClientInfo,
 }
 
+// how often a replica reports its offset to the master
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
 impl Handler {
     pub(crate) fn new(
-        connection: Connection,
+        mut connection: Connection,
         db: Db,
         server_info: ServerInfo,
-        sender: Arc<Sender<ReplicationMsg>>,
     ) -> Handler {
+        connection.set_limits(server_info.proto_limits.clone());
+
+        let replica = ReplicaInfo {
+            ip: connection.peer_ip(),
+            ..Default::default()
+        };
+
+        let client = ClientInfo::new(server_info.next_client_id());
+
         Handler {
             connection,
             db,
             server_info,
-            sender,
+            replica,
+            client,
         }
     }
 
     pub async fn handle_connection(&mut self) -> anyhow::Result<()> {
+        let mut ack_interval = time::interval_at(time::Instant::now() + ACK_INTERVAL, ACK_INTERVAL);
+        ack_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         loop {
-            self.check_wait_lock().await;
+            let opt_frame = if self.connection.is_repl_conn {
+                self.read_from_master(&mut ack_interval).await?
+            } else {
+                match self.connection.read_frame().await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        // the rest of the stream can't be trusted, the client
+                        // is told why before it gets disconnected
+                        let reply = Frame::Error(format!("ERR {}", e));
+                        let _ = self.connection.write_frame(&reply).await;
 
-            let opt_frame = self.connection.read_frame().await?;
+                        return Err(e.into());
+                    }
+                }
+            };
 
             let frame = match opt_frame {
                 Some(frame) => { frame }
@@ -44,99 +73,309 @@ impl Handler {
                 None => return Ok(()),
             };
 
-            let cmd = Command::from_frame(&frame)?;
+            if self.connection.is_repl_conn {
+                self.server_info.replinfo.touch_link();
+            }
+
+            let cmd = match Command::from_frame(&frame) {
+                Ok(cmd) => cmd,
+                Err(e) if self.connection.is_repl_conn => {
+                    eprintln!("Skipping invalid command from master: {}", e);
+
+                    // still part of the stream: the offset counts it, and
+                    // our own replicas get it as the rest of the stream
+                    let _guard = self.server_info.replinfo.sync_lock.lock().await;
+                    self.server_info.replinfo.propagate(ReplicationMsg::Propagate(frame));
+                    continue;
+                }
+                Err(e) => {
+                    self.connection.write_frame(&e.to_frame()).await?;
+                    continue;
+                }
+            };
+
+            if let Command::Psync(psync) = &cmd {
+                // replication connection is served until the replica goes away
+                return self.sync_replica(psync).await;
+            }
+
+            // a replica's dataset only follows its master
+            if cmd.is_write() && !self.connection.is_repl_conn && !self.server_info.is_master() {
+                self.connection.write_frame(&CommandError::ReadOnly.to_frame()).await?;
+                continue;
+            }
+
+            if let Command::Block(block) = &cmd {
+                self.run_blocking(block).await?;
+                continue;
+            }
+
+            // master replicates its writes, replica relays the whole stream
+            // received from its master to its own replicas
+            let propagate = self.connection.is_repl_conn
+                || (cmd.is_write() && self.server_info.is_master());
 
-            self.run_command(&cmd).await?;
+            let (cmd, frame) = if propagate && !self.connection.is_repl_conn {
+                cmd.rewrite(frame)
+            } else {
+                (cmd, frame)
+            };
+
+            let response = {
+                let sync_lock = self.server_info.replinfo.sync_lock.clone();
+                let _guard = if propagate {
+                    Some(sync_lock.lock().await)
+                } else {
+                    None
+                };
 
-            // TODO check list of commands which should change offset
-            self.increase_offset(frame.byte_len()).await;
+                let changes = self.db.changes();
+                let (response, settled) = self.run_command(&cmd).await;
 
-            if self.server_info.is_master() {
-                match cmd {
-                    // replicate write commands
-                    Command::Set(_) => {
-                        self.sender.send(ReplicationMsg::Propagate(frame))?;
-                        self.set_pending(true).await;
-                    },
+                if self.connection.is_repl_conn {
+                    self.server_info.replinfo.propagate(ReplicationMsg::Propagate(frame));
+                } else if propagate {
+                    // keys the write found expired are deleted first on replicas too
+                    self.db.propagate_expired();
 
-                    // after psync cmd master starts handle_propagationlistening for write commands to replicate
-                    Command::Psync(_) => { self.handle_replication().await? }
-                    _ => (),
+                    // writes that failed or changed nothing stay out of the stream
+                    if self.db.changes() != changes && !matches!(response, Frame::Error(_)) {
+                        for frame in settled.unwrap_or_else(|| vec![frame]) {
+                            self.server_info.replinfo.propagate(ReplicationMsg::Propagate(frame));
+                        }
+                    }
                 }
+
+                response
+            };
+
+            let should_reply = match &cmd {
+                // the only command to which replica replies
+                Command::Replconf(cmd) => cmd.ack_offset().is_none(),
+                _ => !self.connection.is_repl_conn,
             };
+            if should_reply {
+                self.connection.write_frame(&response).await?;
+            }
         }
     }
 
-    async fn handle_replication(&mut self) -> anyhow::Result<()> {
-        let mut receiver = self.sender.subscribe();
-        let getack = Replconf::getack();
+    // reads the replication stream, acking the processed offset every second;
+    // a master silent for `repl-timeout` is given up on so the link reconnects
+    async fn read_from_master(&mut self, ack_interval: &mut Interval) -> anyhow::Result<Option<Frame>> {
+        loop {
+            let deadline = self.server_info.replinfo.link_deadline();
 
-        while let Ok(msg) = receiver.recv().await {
-            match msg {
-                ReplicationMsg::Propagate(frame) => {
-                    self.connection.write_frame(&frame).await?;
-                },
-                ReplicationMsg::Wait(wait_timeout) => {
-                    self.connection.write_frame(&getack.to_frame()).await?;
-
-                    if (timeout(
-                        Duration::from_millis(wait_timeout),
-                        self.connection.read_frame(),
-                    ).await).is_ok() {
-                        self.ack_sync().await
-                    };
+            tokio::select! {
+                frame = self.connection.read_frame() => return Ok(frame?),
+                _ = time::sleep_until(deadline) => {
+                    anyhow::bail!("MASTER timeout: no data nor PING received");
+                }
+                _ = ack_interval.tick() => {
+                    let ack = Replconf::ack(self.server_info.replinfo.offset());
+                    self.connection.write_frame(&ack.to_frame()).await?;
                 }
             }
-        };
-        Ok(())
+        }
     }
 
-    async fn run_command(&mut self, command: &Command) -> anyhow::Result<()> {
-        let mut should_reply = !self.connection.is_repl_conn;
+    async fn sync_replica(&mut self, psync: &Psync) -> anyhow::Result<()> {
+        // resync payload and output buffer are taken together, so writes
+        // made after that point are buffered for this replica
+        let (resync, mut stream) = {
+            let _guard = self.server_info.replinfo.sync_lock.lock().await;
 
-        let response = match command {
-            Command::Ping(cmd) => { cmd.apply() }
-            Command::Echo(cmd) => { cmd.apply() }
-            Command::Set(cmd) => { cmd.apply(&mut self.db) }
-            Command::Get(cmd) => { cmd.apply(&mut self.db) }
-            Command::Info(cmd) => { cmd.apply(&self.server_info).await }
-            Command::Replconf(cmd) => {
-                // the only command to which replica replies
-                should_reply = true;
-                cmd.apply(&self.server_info).await
-            }
-            Command::Psync(cmd) => { cmd.apply(&mut self.server_info).await }
-            Command::Wait(cmd) => { cmd.apply(&mut self.sender, &self.server_info).await },
-            Command::Config(cmd) => { cmd.apply(&self.server_info) }
+            let resync = psync.resync(&self.server_info, &self.db).await;
+            (resync, self.server_info.replinfo.replicas.register(self.replica.clone()))
         };
 
-        if should_reply {
-            self.connection.write_frame(&response).await?;
-            if let Command::Psync(_) = command {
-                self.connection.write_rdb(&self.db.build_rdb_frame()).await?
-            }
-        }
+        let result = self.stream_to_replica(psync, resync, &mut stream).await;
 
-        Ok(())
+        self.server_info.replinfo.replicas.unregister(stream.id);
+
+        result
     }
 
-    async fn set_pending(&mut self, val: bool) {
-        let mut pending = self.server_info.replinfo.pending_commands.write().await;
+    async fn stream_to_replica(
+        &mut self,
+        psync: &Psync,
+        resync: Resync,
+        stream: &mut ReplicaStream,
+    ) -> anyhow::Result<()> {
+        let response = psync.apply(&self.server_info, &resync).await;
+        self.connection.write_frame(&response).await?;
 
-        *pending = val
-    }
+        match resync {
+            Resync::Full { rdb, .. } => self.connection.write_rdb(&rdb).await?,
+            Resync::Partial(tail) => self.connection.write_bytes(&tail).await?,
+        }
+
+        let replicas = self.server_info.replinfo.replicas.clone();
+        replicas.set_state(stream.id, ReplicaState::Online);
+
+        let getack = Replconf::getack();
+
+        loop {
+            tokio::select! {
+                msg = stream.recv() => {
+                    let msg = match msg? {
+                        Some(msg) => msg,
+                        None => return Ok(()),
+                    };
+                    stream.written(&msg);
+
+                    match msg {
+                        ReplicationMsg::Propagate(frame) => {
+                            self.connection.write_frame(&frame).await?;
+                        },
+                        ReplicationMsg::Getack => {
+                            self.connection.write_frame(&getack.to_frame()).await?;
+                        }
+                    }
+                }
+                frame = self.connection.read_frame() => {
+                    let frame = match frame? {
+                        Some(frame) => frame,
+                        // replica went away
+                        None => return Ok(()),
+                    };
 
-    async fn ack_sync(&self) {
-        let mut ack = self.server_info.replinfo.repl_completed.write().await;
-        *ack += 1;
+                    if let Ok(Command::Replconf(replconf)) = Command::from_frame(&frame) {
+                        if let Some(offset) = replconf.ack_offset() {
+                            replicas.ack(stream.id, offset);
+                        }
+                    }
+                }
+            }
+        }
     }
 
-    async fn check_wait_lock(&self) -> bool {
-        *self.server_info.replinfo.wait_lock.lock().await
+    // retries the pop each time the client's turn comes on one of its keys,
+    // writes are not held back while it waits, and only the non-blocking
+    // form of the pop that succeeded is propagated
+    async fn run_blocking(&mut self, block: &Block) -> anyhow::Result<()> {
+        // a timeout past what the clock can represent never runs out
+        let deadline = block.timeout().and_then(|timeout| Instant::now().checked_add(timeout));
+        let blocked = self.db.block(block.keys());
+
+        let propagate = self.server_info.is_master();
+        let sync_lock = self.server_info.replinfo.sync_lock.clone();
+
+        let response = loop {
+            {
+                let _guard = if propagate {
+                    Some(sync_lock.lock().await)
+                } else {
+                    None
+                };
+
+                let served = self.db.with_keyspace(|keyspace| block.serve(keyspace, &blocked));
+                if propagate {
+                    self.db.propagate_expired();
+                }
+
+                match served {
+                    Ok(Some((reply, frame))) => {
+                        if propagate {
+                            self.server_info.replinfo.propagate(ReplicationMsg::Propagate(frame));
+                        }
+                        break reply;
+                    }
+                    Ok(None) => {}
+                    Err(e) => break e.to_frame(),
+                }
+            }
+
+            tokio::select! {
+                _ = blocked.notified() => {}
+                _ = time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    break block.timed_out();
+                }
+                // nobody is left to take the elements, the next read sees the peer gone
+                closed = self.connection.closed() => return Ok(closed?),
+            }
+        };
+        drop(blocked);
+
+        self.connection.write_frame(&response).await?;
+
+        Ok(())
     }
 
-    async fn increase_offset(&mut self, increase: usize) {
-        let mut offset = self.server_info.replinfo.offset.lock().await;
-        *offset += increase as i64;
+    // the reply, along with the writes replicas get in place of the command
+    // when its outcome is random or computed
+    async fn run_command(&mut self, command: &Command) -> (Frame, Option<Vec<Frame>>) {
+        let response = match command {
+            Command::Ping(cmd) => { cmd.apply() }
+            Command::Echo(cmd) => { cmd.apply() }
+            Command::Set(cmd) => { cmd.apply(&mut self.db) }
+            Command::Get(cmd) => { cmd.apply(&mut self.db) }
+            Command::Del(cmd) => { cmd.apply(&mut self.db) }
+            Command::Info(cmd) => { cmd.apply(&self.db, &self.server_info).await }
+            Command::Replconf(cmd) => { cmd.apply(&self.server_info, &mut self.replica) }
+            // served by `sync_replica`
+            Command::Psync(_) => unreachable!(),
+            // served by `run_blocking`
+            Command::Block(_) => unreachable!(),
+            Command::Wait(cmd) => { cmd.apply(&self.server_info).await },
+            Command::Config(cmd) => { cmd.apply(&self.server_info) }
+            Command::Save(cmd) => { cmd.apply(&self.db, &self.server_info).await }
+            Command::Bgsave(cmd) => { cmd.apply(&self.db, &self.server_info) }
+            Command::Lastsave(cmd) => { cmd.apply(&self.server_info) }
+            Command::Replicaof(cmd) => { cmd.apply(&mut self.db, &self.server_info).await }
+            Command::Hello(cmd) => {
+                cmd.apply(&self.server_info, &mut self.client, &mut self.connection.protocol)
+            }
+            Command::Client(cmd) => { cmd.apply(&mut self.client) }
+            Command::Push(cmd) => { cmd.apply(&mut self.db) }
+            Command::Pop(cmd) => { cmd.apply(&mut self.db) }
+            Command::Lrange(cmd) => { cmd.apply(&self.db) }
+            Command::Llen(cmd) => { cmd.apply(&self.db) }
+            Command::Lindex(cmd) => { cmd.apply(&self.db) }
+            Command::Lset(cmd) => { cmd.apply(&mut self.db) }
+            Command::Linsert(cmd) => { cmd.apply(&mut self.db) }
+            Command::Lrem(cmd) => { cmd.apply(&mut self.db) }
+            Command::Ltrim(cmd) => { cmd.apply(&mut self.db) }
+            Command::Lpos(cmd) => { cmd.apply(&self.db) }
+            Command::Lmove(cmd) => { cmd.apply(&mut self.db) }
+            Command::Lmpop(cmd) => { cmd.apply(&mut self.db) }
+            Command::Hset(cmd) => { cmd.apply(&mut self.db) }
+            Command::Hsetnx(cmd) => { cmd.apply(&mut self.db) }
+            Command::Hget(cmd) => { cmd.apply(&self.db) }
+            Command::Hmget(cmd) => { cmd.apply(&self.db) }
+            Command::Hdel(cmd) => { cmd.apply(&mut self.db) }
+            Command::Hexists(cmd) => { cmd.apply(&self.db) }
+            Command::Hlen(cmd) => { cmd.apply(&self.db) }
+            Command::Hgetall(cmd) => { cmd.apply(&self.db) }
+            Command::Hincrby(cmd) => { cmd.apply(&mut self.db) }
+            Command::Hincrbyfloat(cmd) => {
+                let (response, writes) = cmd.apply(&mut self.db);
+                return (response, Some(writes));
+            }
+            Command::Hstrlen(cmd) => { cmd.apply(&self.db) }
+            Command::Hrandfield(cmd) => { cmd.apply(&self.db) }
+            Command::Hscan(cmd) => { cmd.apply(&self.db) }
+            Command::Hexpire(cmd) => { cmd.apply(&mut self.db) }
+            Command::Httl(cmd) => { cmd.apply(&self.db) }
+            Command::Hpersist(cmd) => { cmd.apply(&mut self.db) }
+            Command::Sadd(cmd) => { cmd.apply(&mut self.db) }
+            Command::Srem(cmd) => { cmd.apply(&mut self.db) }
+            Command::Sismember(cmd) => { cmd.apply(&self.db) }
+            Command::Smismember(cmd) => { cmd.apply(&self.db) }
+            Command::Smembers(cmd) => { cmd.apply(&self.db) }
+            Command::Scard(cmd) => { cmd.apply(&self.db) }
+            // members are popped at random, replicas remove the ones taken here
+            Command::Spop(cmd) => {
+                let (response, srem) = cmd.apply(&mut self.db);
+                return (response, Some(srem.iter().map(ClientCmd::to_frame).collect()));
+            }
+            Command::Srandmember(cmd) => { cmd.apply(&self.db) }
+            Command::Smove(cmd) => { cmd.apply(&mut self.db) }
+            Command::Sscan(cmd) => { cmd.apply(&self.db) }
+            Command::Combine(cmd) => { cmd.apply(&mut self.db) }
+            Command::Sintercard(cmd) => { cmd.apply(&self.db) }
+        };
+
+        (response, None)
     }
 }
@@ -12,9 +12,11 @@ use tokio::{
 
 pub(crate) use handler::Handler;
 
+use crate::redis::config::ProtoLimits;
 use crate::redis::utils::{add_cr, int_as_bytes};
 
-use super::frame::{Frame, FrameError};
+use super::cmd::CommandError;
+use super::frame::{Decoder, Frame, FrameError, Protocol};
 
 pub(crate) mod handler;
 #[derive(Debug)]
@@ -22,6 +24,36 @@ pub struct Connection {
     pub stream: BufStream<TcpStream>,
     pub buffer: BytesMut,
     pub(crate) is_repl_conn: bool,
+    // switched by HELLO, replies are encoded with it
+    pub(crate) protocol: Protocol,
+    // keeps a partly received frame between reads
+    decoder: Decoder,
+}
+
+// per connection state seen by CLIENT and HELLO
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(id: u64) -> ClientInfo {
+        ClientInfo { id, name: None }
+    }
+
+    pub fn set_name(&mut self, name: &str) -> Result<(), CommandError> {
+        if name.bytes().any(|b| b <= b' ' || b > b'~') {
+            return Err(CommandError::Other(
+                "Client names cannot contain spaces, newlines or special characters.".to_string()
+            ));
+        }
+
+        // an empty name clears it
+        self.name = if name.is_empty() { None } else { Some(name.to_string()) };
+
+        Ok(())
+    }
 }
 
 
@@ -31,6 +63,19 @@ impl Connection {
             stream: BufStream::new(stream),
             buffer: BytesMut::with_capacity(4096),
             is_repl_conn: false,
+            protocol: Protocol::default(),
+            decoder: Decoder::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: ProtoLimits) {
+        self.decoder.set_limits(limits);
+    }
+
+    pub fn peer_ip(&self) -> String {
+        match self.stream.get_ref().peer_addr() {
+            Ok(addr) => addr.ip().to_string(),
+            Err(_) => String::new(),
         }
     }
 
@@ -46,8 +91,25 @@ impl Connection {
         }
     }
 
+    // resolves once the peer goes away, anything it sends meanwhile is
+    // kept for the following `read_frame` up to the query buffer limit
+    pub async fn closed(&mut self) -> Result<(), FrameError> {
+        while !self.buf_empty().await? {
+            self.decoder.check_limit(&self.buffer)?;
+        }
+
+        Ok(())
+    }
+
     pub async fn write_frame(&mut self, frame: &Frame) -> Result<(), FrameError> {
-        self.stream.write_all(&frame.to_response()).await?;
+        self.stream.write_all(&frame.encode(self.protocol)).await?;
+        self.stream.flush().await?;
+
+        Ok(())
+    }
+
+    pub async fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), FrameError> {
+        self.stream.write_all(bytes).await?;
         self.stream.flush().await?;
 
         Ok(())
@@ -68,8 +130,8 @@ impl Connection {
 
     pub async fn read_rdb(&mut self) -> Result<Option<Bytes>, FrameError> {
         loop {
-            if let Ok(arr) = self.parse_rdb() {
-                return Ok(Some(arr));
+            if let Some(rdb) = self.parse_rdb()? {
+                return Ok(Some(rdb));
             };
 
             if self.buf_empty().await? {
@@ -78,37 +140,26 @@ impl Connection {
         }
     }
 
-    fn parse_rdb(&mut self) -> Result<Bytes, FrameError> {
+    fn parse_rdb(&mut self) -> Result<Option<Bytes>, FrameError> {
         let mut buf = Cursor::new(&self.buffer[..]);
 
-        let rdb = Frame::parse_rdb(&mut buf)?;
-        let len = buf.position() as usize;
-
-        self.buffer.advance(len);
-
-        Ok(rdb)
-    }
-
-    pub fn parse_frame(&mut self) -> Result<Option<Frame>, FrameError> {
-        let mut buf = Cursor::new(&self.buffer[..]);
-
-        match Frame::check(&mut buf) {
-            Ok(_) => {
+        match Frame::parse_rdb(&mut buf) {
+            Ok(rdb) => {
                 let len = buf.position() as usize;
 
-                buf.set_position(0);
-
-                let frame = Frame::parse(&mut buf)?;
-
                 self.buffer.advance(len);
 
-                Ok(Some(frame))
+                Ok(Some(rdb))
             }
             Err(FrameError::Incomplete) => Ok(None),
             Err(e) => Err(e)
         }
     }
 
+    pub fn parse_frame(&mut self) -> Result<Option<Frame>, FrameError> {
+        self.decoder.decode(&mut self.buffer)
+    }
+
     async fn buf_empty(&mut self) -> Result<bool, FrameError> {
         if 0 == self.stream.read_buf(&mut self.buffer).await? {
             if self.buffer.is_empty() || self.is_repl_conn {
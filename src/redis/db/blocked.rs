@@ -0,0 +1,96 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::sync::Notify;
+
+use super::{Db, Value};
+
+// Clients blocked on list keys, queued per key in the order they blocked.
+// Only the head of a queue is woken when its key gets elements, and it
+// wakes the next one when it leaves, so clients are served first come first served.
+#[derive(Debug, Default)]
+pub(super) struct BlockedKeys {
+    queues: HashMap<Bytes, VecDeque<Arc<Notify>>>,
+}
+
+// a client waiting for one of its keys, removed from the queues on drop
+#[derive(Debug)]
+pub struct Blocked {
+    db: Db,
+    keys: Vec<Bytes>,
+    waiter: Arc<Notify>,
+}
+
+impl BlockedKeys {
+    fn add(&mut self, key: &Bytes, waiter: &Arc<Notify>) {
+        let queue = self.queues.entry(key.clone()).or_default();
+
+        // a key given twice holds a single place in the queue
+        if !queue.iter().any(|queued| Arc::ptr_eq(queued, waiter)) {
+            queue.push_back(waiter.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &[u8], waiter: &Arc<Notify>) {
+        if let Some(queue) = self.queues.get_mut(key) {
+            queue.retain(|queued| !Arc::ptr_eq(queued, waiter));
+
+            if queue.is_empty() {
+                self.queues.remove(key);
+            }
+        }
+    }
+
+    pub(super) fn is_next(&self, key: &[u8], blocked: &Blocked) -> bool {
+        self.queues.get(key)
+            .and_then(|queue| queue.front())
+            .is_none_or(|head| Arc::ptr_eq(head, &blocked.waiter))
+    }
+
+    // called once `key` holds elements
+    pub(super) fn wake(&self, key: &[u8]) {
+        if let Some(head) = self.queues.get(key).and_then(|queue| queue.front()) {
+            head.notify_one();
+        }
+    }
+}
+
+impl Db {
+    // queues the client on `keys`, it is woken when its turn comes on one of them
+    pub fn block(&self, keys: Vec<Bytes>) -> Blocked {
+        let waiter = Arc::new(Notify::new());
+
+        let mut state = self.shared.state.lock().unwrap();
+        for key in &keys {
+            state.blocked.add(key, &waiter);
+        }
+
+        Blocked { db: self.clone(), keys, waiter }
+    }
+}
+
+impl Blocked {
+    // a wakeup only means the keys are worth another try, they may be taken meanwhile
+    pub async fn notified(&self) {
+        self.waiter.notified().await
+    }
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        let mut state = self.db.shared.state.lock().unwrap();
+        let state = &mut *state;
+
+        for key in &self.keys {
+            state.blocked.remove(key, &self.waiter);
+
+            // elements left behind go to the next client in line
+            if let Some(Value::List(list)) = state.entries.get(key).map(|entry| &entry.value) {
+                if !list.is_empty() {
+                    state.blocked.wake(key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::ops::Index;
+
+use bytes::Bytes;
+use tokio::time::Instant;
+
+// Fields of a hash along with the deadlines of the fields given a TTL.
+// Deadlines are also scheduled in `State.expirations` through the keyspace,
+// this only keeps the value each field currently has. A replica keeps fields
+// past their deadline until its master deletes them, reads skip such fields
+// while the length still counts them, as in redis.
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: HashMap<Bytes, Bytes>,
+    expires: HashMap<Bytes, Instant>,
+    // no deadline comes before it, it may be stale once the earliest one
+    // is removed, until the next look for expired fields
+    earliest: Option<Instant>,
+}
+
+impl Hash {
+    pub fn new() -> Hash {
+        Hash::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Bytes> {
+        self.fields.get(field).filter(|_| self.is_live(field, Instant::now()))
+    }
+
+    pub fn contains_key(&self, field: &[u8]) -> bool {
+        self.get(field).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &Bytes)> {
+        let now = Instant::now();
+        self.fields.iter().filter(move |(field, _)| self.is_live(field, now))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Bytes> {
+        self.iter().map(|(field, _)| field)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Bytes> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    // every stored field, those past their deadline included
+    pub fn iter_all(&self) -> impl Iterator<Item = (&Bytes, &Bytes)> {
+        self.fields.iter()
+    }
+
+    fn is_live(&self, field: &[u8], now: Instant) -> bool {
+        self.expires.get(field).is_none_or(|at| *at > now)
+    }
+
+    // a new value for the field, the TTL it had is dropped as with HSET
+    pub fn insert(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        self.expires.remove(&field);
+        self.fields.insert(field, value)
+    }
+
+    // a new value for the field that keeps its TTL, as with HINCRBY
+    pub fn update(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        self.fields.insert(field, value)
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> Option<Bytes> {
+        self.expires.remove(field);
+        self.fields.remove(field)
+    }
+
+    pub fn expires_at(&self, field: &[u8]) -> Option<Instant> {
+        self.expires.get(field).copied()
+    }
+
+    pub fn has_expires(&self) -> bool {
+        !self.expires.is_empty()
+    }
+
+    // true when the field had a TTL
+    pub fn persist(&mut self, field: &[u8]) -> bool {
+        self.expires.remove(field).is_some()
+    }
+
+    // only recorded here, `Keyspace::expire_field` also schedules the deadline
+    pub fn set_expire(&mut self, field: Bytes, at: Instant) {
+        self.expires.insert(field, at);
+        self.earliest = Some(self.earliest.map_or(at, |earliest| earliest.min(at)));
+    }
+
+    pub fn expires(&self) -> impl Iterator<Item = (&Bytes, Instant)> {
+        self.expires.iter().map(|(field, at)| (field, *at))
+    }
+
+    // fields whose deadline passed, with the deadlines they had
+    pub(super) fn remove_expired(&mut self, now: Instant) -> Vec<(Bytes, Instant)> {
+        if self.earliest.is_none_or(|earliest| earliest > now) {
+            return vec![];
+        }
+
+        let expired: Vec<(Bytes, Instant)> = self.expires.iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(field, at)| (field.clone(), *at))
+            .collect();
+
+        for (field, _) in &expired {
+            self.remove(field);
+        }
+        self.earliest = self.expires.values().min().copied();
+
+        expired
+    }
+}
+
+// the same fields with the same deadlines
+impl PartialEq for Hash {
+    fn eq(&self, other: &Hash) -> bool {
+        self.fields == other.fields && self.expires == other.expires
+    }
+}
+
+impl Index<&Bytes> for Hash {
+    type Output = Bytes;
+
+    fn index(&self, field: &Bytes) -> &Bytes {
+        &self.fields[field]
+    }
+}
+
+impl FromIterator<(Bytes, Bytes)> for Hash {
+    fn from_iter<I: IntoIterator<Item = (Bytes, Bytes)>>(pairs: I) -> Hash {
+        Hash { fields: pairs.into_iter().collect(), expires: HashMap::new(), earliest: None }
+    }
+}
@@ -0,0 +1,116 @@
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use super::{Blocked, Entry, Expiring, Shared, State, Value};
+
+// Access to several keys under one lock, so a command touching more than
+// one of them is atomic. Reads go through the same lazy expiry as `Db::get`,
+// and aggregates a command leaves empty are removed when it is done.
+pub struct Keyspace<'a> {
+    pub(super) shared: &'a Shared,
+    pub(super) state: &'a mut State,
+    // keys handed out for writing
+    pub(super) touched: Vec<Bytes>,
+}
+
+impl Keyspace<'_> {
+    pub fn get(&mut self, key: &[u8]) -> Option<&Value> {
+        self.shared.lookup(self.state, key).map(|entry| &entry.value)
+    }
+
+    // a read leaving expired keys for the next lookup to delete, so several
+    // values can be held at once; they are reported missing all the same
+    pub fn peek(&self, key: &[u8]) -> Option<&Value> {
+        self.state.entries.get(key)
+            .filter(|entry| !entry.is_expired(Instant::now()))
+            .map(|entry| &entry.value)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut Value> {
+        self.shared.lookup(self.state, key)?;
+        self.touched.push(Bytes::copy_from_slice(key));
+
+        self.state.entries.get_mut(key).map(|entry| &mut entry.value)
+    }
+
+    // the value at `key`, created with `default` when the key is missing
+    pub fn get_or_insert(&mut self, key: &[u8], default: impl FnOnce() -> Value) -> &mut Value {
+        if self.get(key).is_none() {
+            self.insert(Bytes::copy_from_slice(key), default());
+        }
+        self.touched.push(Bytes::copy_from_slice(key));
+
+        &mut self.state.entries.get_mut(key).expect("inserted above").value
+    }
+
+    // replaces whatever was stored at `key` along with its TTL
+    pub fn insert(&mut self, key: Bytes, value: Value) {
+        if let Value::Hash(hash) = &value {
+            for (field, at) in hash.expires() {
+                self.schedule(at, Expiring::Field(key.clone(), field.clone()));
+            }
+        }
+
+        let old = self.state.entries.insert(key.clone(), Entry { value, expires_at: None });
+
+        if let Some(at) = old.and_then(|entry| entry.expires_at) {
+            self.state.expirations.remove(&(at, Expiring::Key(key.clone())));
+        }
+        self.touched.push(key);
+    }
+
+    // gives an existing hash field a deadline, replacing the one it had
+    pub fn expire_field(&mut self, key: &[u8], field: &Bytes, at: Instant) {
+        let Some(Entry { value: Value::Hash(hash), .. }) = self.state.entries.get_mut(key) else {
+            return;
+        };
+        if !hash.contains_key(field) {
+            return;
+        }
+
+        hash.set_expire(field.clone(), at);
+        self.schedule(at, Expiring::Field(Bytes::copy_from_slice(key), field.clone()));
+        self.touched.push(Bytes::copy_from_slice(key));
+    }
+
+    fn schedule(&mut self, at: Instant, target: Expiring) {
+        if self.state.schedule(at, target) {
+            self.shared.notify_expire.notify_one();
+        }
+    }
+
+    // the command found nothing to write after all, the keys it took
+    // for writing are neither cleaned up nor counted as a change
+    pub fn unchanged(&mut self) {
+        self.touched.clear();
+    }
+
+    // whether `blocked` comes first among the clients waiting for `key`
+    pub fn is_next(&self, key: &[u8], blocked: &Blocked) -> bool {
+        self.state.blocked.is_next(key, blocked)
+    }
+
+    pub(super) fn finish(self) {
+        if self.touched.is_empty() {
+            return;
+        }
+
+        for key in &self.touched {
+            let expires_at = match self.state.entries.get(key) {
+                Some(entry) if entry.value.is_empty() => entry.expires_at,
+                Some(Entry { value: Value::List(_), .. }) => {
+                    self.state.blocked.wake(key);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            self.state.entries.remove(key);
+            if let Some(at) = expires_at {
+                self.state.expirations.remove(&(at, Expiring::Key(key.clone())));
+            }
+        }
+
+        self.state.changed(1);
+    }
+}
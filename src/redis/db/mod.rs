@@ -1,13 +1,25 @@
 use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use base64::prelude::*;
-use base64::prelude::BASE64_STANDARD;
 use bytes::Bytes;
-use tokio::sync::Notify;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
 use tokio::time::{Duration, Instant, sleep_until};
 
-const EMPTY_RDB: &str = "UkVESVMwMDEx+glyZWRpcy12ZXIFNy4yLjD6CnJlZGlzLWJpdHPAQPoFY3RpbWXCbQi8ZfoIdXNlZC1tZW3CsMQQAPoIYW9mLWJhc2XAAP/wbjv+wP9aog==";
+use super::rdb::{Rdb, RDB_VERSION, RdbEntry};
+
+pub use blocked::Blocked;
+pub use hash::Hash;
+pub use keyspace::Keyspace;
+pub use value::{Value, WrongType};
+
+use blocked::BlockedKeys;
+
+mod blocked;
+mod hash;
+mod keyspace;
+mod value;
 
 #[derive(Debug, Clone)]
 pub struct Db {
@@ -19,20 +31,53 @@ struct Shared {
     state: Mutex<State>,
 
     notify_expire: Notify,
+    expire_hook: Mutex<Option<ExpireHook>>,
+}
+
+// lets a master replicate the keys removed by active expiry
+pub(crate) struct ExpireHook {
+    // taken before expiring, orders deletions with the rest of the replication stream
+    pub lock: Arc<AsyncMutex<()>>,
+    pub expired: Box<ExpiredFn>,
 }
 
+// key, and the field when only a hash field expired
+type ExpiredFn = dyn Fn(&[u8], Option<&[u8]>) + Send + Sync;
+
 #[derive(Debug)]
 struct State {
-    entries: HashMap<String, Entry>,
+    entries: HashMap<Bytes, Entry>,
 
     shutdown: bool,
-    // track TTLs
-    expirations: BTreeSet<(Instant, String)>,
+    // track TTLs, of keys and of hash fields
+    expirations: BTreeSet<(Instant, Expiring)>,
+    // replicas leave expiry to their master and only hide expired keys
+    active_expire: bool,
+    // number of changes since the last successful save
+    dirty: u64,
+    // every change ever made, unlike `dirty` never goes back
+    changes: u64,
+    // keys deleted because their TTL passed
+    expired_keys: u64,
+    // deleted by a read while the hook lock was taken, replicated by
+    // whoever holds it before it propagates anything of its own
+    unpropagated: Vec<(Bytes, Option<Bytes>)>,
+    // clients waiting for list keys to get elements
+    blocked: BlockedKeys,
+}
+
+// what a deadline in `State.expirations` is for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Expiring {
+    Key(Bytes),
+    // stale once the field was removed or given another deadline, such
+    // entries are not looked for when that happens and get skipped instead
+    Field(Bytes, Bytes),
 }
 
 #[derive(Debug)]
 struct Entry {
-    data: Bytes,
+    value: Value,
 
     expires_at: Option<Instant>,
 }
@@ -44,8 +89,15 @@ impl Db {
                 entries: HashMap::new(),
                 shutdown: false,
                 expirations: BTreeSet::new(),
+                active_expire: true,
+                dirty: 0,
+                changes: 0,
+                expired_keys: 0,
+                unpropagated: vec![],
+                blocked: BlockedKeys::default(),
             }),
             notify_expire: Notify::new(),
+            expire_hook: Mutex::new(None),
         });
 
         tokio::spawn(remove_expired_tasks(shared.clone()));
@@ -53,35 +105,121 @@ impl Db {
         Db { shared }
     }
 
-    pub fn get(&self, key: &str) -> Option<Bytes> {
-        let state = self.shared.state.lock().unwrap();
+    // string stored at `key`
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match self.shared.lookup(&mut state, key) {
+            Some(entry) => entry.value.as_string().cloned().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // replaces any value at `key` with a string
+    pub fn set(&mut self, key: Bytes, data: Bytes, expire: Option<Duration>) {
+        // a deadline the clock can't represent is never reached
+        let expires_at = expire.and_then(|duration| Instant::now().checked_add(duration));
 
-        state.entries.get(key).map(|entry| entry.data.clone())
+        self.insert(key, Value::String(data), expires_at);
+        self.shared.state.lock().unwrap().changed(1);
     }
 
-    pub fn set(&mut self, key: String, data: Bytes, expire: Option<Duration>) {
+    // runs `f` with the keyspace locked, writes made through it count as one change
+    pub fn with_keyspace<R>(&self, f: impl FnOnce(&mut Keyspace) -> R) -> R {
         let mut state = self.shared.state.lock().unwrap();
-        let mut notify = false;
 
-        let expires_at = expire.map(|duration| {
-            let expire = Instant::now() + duration;
-            notify = state.expirations.first().map(|first_expire|
-            first_expire.0 > expire
-            ).unwrap_or(true);
+        let mut keyspace = Keyspace {
+            shared: &self.shared,
+            state: &mut state,
+            touched: vec![],
+        };
 
-            expire
-        });
+        let result = f(&mut keyspace);
+        keyspace.finish();
+
+        result
+    }
+
+    // false for an expired key, which a replica still drops as its master asks
+    pub fn delete(&mut self, key: &[u8]) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let found = self.shared.lookup(&mut state, key).is_some();
+
+        if let Some(entry) = state.entries.remove(key) {
+            if let Some(expire) = entry.expires_at {
+                state.expirations.remove(&(expire, Expiring::Key(Bytes::copy_from_slice(key))));
+            }
+            state.changed(1);
+        }
+
+        found
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.shared.state.lock().unwrap().expired_keys
+    }
+
+    pub fn set_active_expire(&self, active: bool) {
+        self.shared.state.lock().unwrap().active_expire = active;
+        self.shared.notify_expire.notify_one();
+    }
 
-        let old_entry = state.entries.insert(key.clone(), Entry { data, expires_at });
+    pub(crate) fn set_expire_hook(&self, hook: ExpireHook) {
+        *self.shared.expire_hook.lock().unwrap() = Some(hook);
+    }
+
+    // replicates the deletions reads made while the caller held the hook
+    // lock, a write it is about to propagate may have found the key expired
+    pub(crate) fn propagate_expired(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        let hook = self.shared.expire_hook.lock().unwrap();
+
+        self.shared.propagate_expired(&mut state, hook.as_ref());
+    }
+
+    // fill the keyspace from a parsed RDB file, skipping keys that are already expired
+    pub fn load_rdb(&mut self, rdb: Rdb) {
+        let now = SystemTime::now();
+
+        for entry in rdb.entries {
+            let expires_at = match entry.expires_at {
+                None => None,
+                Some(at) => match at.duration_since(now) {
+                    Ok(ttl) => Some(Instant::now() + ttl),
+                    Err(_) => continue,
+                },
+            };
+
+            self.insert(entry.key, entry.value, expires_at);
+        }
+    }
+
+    fn insert(&mut self, key: Bytes, value: Value, expires_at: Option<Instant>) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let mut notify = expires_at.map(|expire| {
+            state.expirations.first().map(|first_expire|
+                first_expire.0 > expire
+            ).unwrap_or(true)
+        }).unwrap_or(false);
+
+        if let Value::Hash(hash) = &value {
+            for (field, at) in hash.expires() {
+                notify |= state.schedule(at, Expiring::Field(key.clone(), field.clone()));
+            }
+        }
+
+        let old_entry = state.entries.insert(key.clone(), Entry { value, expires_at });
 
         if let Some(old_val) = old_entry {
             if let Some(expire) = old_val.expires_at {
-                state.expirations.remove(&(expire, key.clone()));
+                state.expirations.remove(&(expire, Expiring::Key(key.clone())));
             }
         }
 
         if let Some(expire) = expires_at {
-            state.expirations.insert((expire, key));
+            state.expirations.insert((expire, Expiring::Key(key)));
         };
 
         drop(state);
@@ -91,13 +229,73 @@ impl Db {
         }
     }
 
+    // point-in-time copy of the keyspace along with the dirty counter it covers
+    pub fn snapshot(&self) -> (Rdb, u64) {
+        let state = self.shared.state.lock().unwrap();
+
+        let now = Instant::now();
+        let wall_now = SystemTime::now();
+
+        let entries = state.entries.iter().filter(|(_, entry)| !entry.is_expired(now)).map(|(key, entry)| {
+            RdbEntry {
+                key: key.clone(),
+                value: entry.value.clone(),
+                expires_at: entry.expires_at.map(|at| wall_now + at.saturating_duration_since(now)),
+            }
+        }).collect();
+
+        let ctime = wall_now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        let rdb = Rdb {
+            version: RDB_VERSION,
+            aux: vec![
+                (String::from("redis-ver"), Bytes::from_static(b"7.2.0")),
+                (String::from("redis-bits"), Bytes::from_static(b"64")),
+                (String::from("ctime"), ctime.to_string().into()),
+            ],
+            entries,
+        };
+
+        (rdb, state.dirty)
+    }
+
+    pub fn dirty(&self) -> u64 {
+        self.shared.state.lock().unwrap().dirty
+    }
+
+    // grows with every write, a command that leaves it as is changed nothing
+    pub fn changes(&self) -> u64 {
+        self.shared.state.lock().unwrap().changes
+    }
+
+    // forget the changes covered by a successful save
+    pub fn saved(&self, dirty: u64) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.dirty = state.dirty.saturating_sub(dirty);
+    }
+
     pub fn build_rdb_frame(&self) -> Vec<u8> {
-        BASE64_STANDARD.decode(EMPTY_RDB).unwrap()
+        self.snapshot().0.to_bytes()
+    }
+
+    pub fn flush(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let flushed = state.entries.len() as u64;
+        state.changed(flushed);
+        state.entries.clear();
+        state.expirations.clear();
     }
 }
 
 impl Drop for Db {
     fn drop(&mut self) {
+        // the other reference is held by the background expiration task,
+        // so only the last `Db` handle shuts it down
+        if Arc::strong_count(&self.shared) > 2 {
+            return;
+        }
+
         let mut state = self.shared.state.lock().unwrap();
         state.shutdown = true;
 
@@ -106,24 +304,160 @@ impl Drop for Db {
     }
 }
 
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+impl State {
+    fn changed(&mut self, count: u64) {
+        self.dirty += count;
+        self.changes += count;
+    }
+
+    // true when it is the earliest deadline, the expiry task has to be woken for it
+    fn schedule(&mut self, at: Instant, target: Expiring) -> bool {
+        let earliest = self.expirations.first().is_none_or(|first| first.0 > at);
+        self.expirations.insert((at, target));
+
+        earliest
+    }
+
+    // removes the hash fields of `key` whose deadline passed, the key goes
+    // with the last one; the fields are returned to be replicated
+    fn expire_fields(&mut self, key: &[u8], now: Instant) -> Vec<Bytes> {
+        let Some(Entry { value: Value::Hash(hash), expires_at }) = self.entries.get_mut(key) else {
+            return vec![];
+        };
+        let key_expires_at = *expires_at;
+
+        let key = Bytes::copy_from_slice(key);
+        let mut removed = vec![];
+        for (field, at) in hash.remove_expired(now) {
+            self.expirations.remove(&(at, Expiring::Field(key.clone(), field.clone())));
+            removed.push(field);
+        }
+
+        if hash.is_empty() {
+            self.entries.remove(&key);
+            if let Some(at) = key_expires_at {
+                self.expirations.remove(&(at, Expiring::Key(key)));
+            }
+            self.expired_keys += 1;
+        }
+
+        removed
+    }
+}
+
+impl fmt::Debug for ExpireHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExpireHook")
+    }
+}
+
 impl Shared {
+    // every read goes through here: an expired key is reported missing,
+    // and deleted unless the master is in charge of expiring it
+    fn lookup<'a>(&self, state: &'a mut State, key: &[u8]) -> Option<&'a Entry> {
+        let expires_at = state.entries.get(key)?.expires_at;
+
+        if let Some(at) = expires_at.filter(|at| *at <= Instant::now()) {
+            if !state.active_expire {
+                return None;
+            }
+
+            self.expired(state, key, None);
+
+            state.entries.remove(key);
+            state.expirations.remove(&(at, Expiring::Key(Bytes::copy_from_slice(key))));
+            state.expired_keys += 1;
+
+            return None;
+        }
+
+        // replicas keep expired fields until the master deletes them, `Hash` hides them
+        let has_field_expires = matches!(
+            state.entries.get(key),
+            Some(Entry { value: Value::Hash(hash), .. }) if hash.has_expires()
+        );
+        if has_field_expires && state.active_expire {
+            for field in state.expire_fields(key, Instant::now()) {
+                self.expired(state, key, Some(&field));
+            }
+        }
+
+        state.entries.get(key)
+    }
+
+    // a deletion made by a read is replicated right away unless the hook lock
+    // is taken: its holder may be a write that found the key expired, or a
+    // replica between its snapshot and joining the stream
+    fn expired(&self, state: &mut State, key: &[u8], field: Option<&[u8]>) {
+        let hook = self.expire_hook.lock().unwrap();
+        let Some(hook) = hook.as_ref() else {
+            return;
+        };
+
+        if let Ok(_guard) = hook.lock.try_lock() {
+            (hook.expired)(key, field);
+            return;
+        }
+
+        state.unpropagated.push((Bytes::copy_from_slice(key), field.map(Bytes::copy_from_slice)));
+        // the expiry task takes over once the lock is free again
+        self.notify_expire.notify_one();
+    }
+
+    // the hook lock has to be held
+    fn propagate_expired(&self, state: &mut State, hook: Option<&ExpireHook>) {
+        let unpropagated = std::mem::take(&mut state.unpropagated);
+
+        if let Some(hook) = hook {
+            for (key, field) in unpropagated {
+                (hook.expired)(&key, field.as_deref());
+            }
+        }
+    }
+
     fn remove_expired(&self) -> Option<Instant> {
         let mut state = self.state.lock().unwrap();
         let state = &mut *state;
 
-        if state.shutdown {
+        if state.shutdown || !state.active_expire {
             return None;
         }
 
+        let hook = self.expire_hook.lock().unwrap();
+        self.propagate_expired(state, hook.as_ref());
+
         let now = Instant::now();
 
-        while let Some(&(expire, ref key)) = state.expirations.iter().next() {
+        while let Some((expire, target)) = state.expirations.first().cloned() {
             if expire > now {
                 return Some(expire);
             }
 
-            state.entries.remove(key);
-            state.expirations.remove(&(expire, key.clone()));
+            match target {
+                Expiring::Key(key) => {
+                    if let Some(hook) = hook.as_ref() {
+                        (hook.expired)(&key, None);
+                    }
+
+                    state.entries.remove(&key);
+                    state.expirations.remove(&(expire, Expiring::Key(key)));
+                    state.expired_keys += 1;
+                }
+                Expiring::Field(key, field) => {
+                    state.expirations.remove(&(expire, Expiring::Field(key.clone(), field)));
+                    for field in state.expire_fields(&key, now) {
+                        if let Some(hook) = hook.as_ref() {
+                            (hook.expired)(&key, Some(&field));
+                        }
+                    }
+                }
+            }
         }
 
         None
@@ -132,11 +466,23 @@ impl Shared {
     fn is_up(&self) -> bool {
         !self.state.lock().unwrap().shutdown
     }
+
+    fn hook_lock(&self) -> Option<Arc<AsyncMutex<()>>> {
+        self.expire_hook.lock().unwrap().as_ref().map(|hook| hook.lock.clone())
+    }
 }
 
 async fn remove_expired_tasks(shared: Arc<Shared>) {
     while shared.is_up() {
-        if let Some(expire) = shared.remove_expired() {
+        let next_expire = match shared.hook_lock() {
+            Some(lock) => {
+                let _guard = lock.lock().await;
+                shared.remove_expired()
+            }
+            None => shared.remove_expired(),
+        };
+
+        if let Some(expire) = next_expire {
             tokio::select! {
                 _ = sleep_until(expire) => {},
                 _ = shared.notify_expire.notified() => {}
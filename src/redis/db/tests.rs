@@ -10,7 +10,7 @@ async fn test_db_set_get() {
     let mut db = Db::new();
 
     let input = (
-        String::from("key"),
+        Bytes::from_static(b"key"),
         Bytes::from_static(b"data"),
     );
         
@@ -22,7 +22,7 @@ async fn test_db_set_get() {
     );
 
     assert_eq!(
-        Some(input.1), 
+        Ok(Some(input.1)),
         db.get(&input.0),
     );
 }
@@ -32,7 +32,7 @@ async fn test_set_with_ttl () {
     let mut db = Db::new();
 
     let input = (
-        String::from("key"),
+        Bytes::from_static(b"key"),
         Bytes::from_static(b"data"),
         Duration::from_millis(100),
     );
@@ -40,19 +40,155 @@ async fn test_set_with_ttl () {
     db.set(
         input.0.clone(), 
         input.1.clone(),
-        Some(input.2.clone()),
+        Some(input.2),
     );
 
-    let state = db.shared.state.lock().unwrap();
-    assert!(state.expirations.first().is_some());
-    drop(state);
-    assert!(db.get(&input.0.clone()).is_some());
+    {
+        let state = db.shared.state.lock().unwrap();
+        assert!(state.expirations.first().is_some());
+    }
+    assert!(db.get(&input.0.clone()).unwrap().is_some());
 
     sleep(Duration::from_millis(200)).await;
 
-    assert!(db.get(&input.0.clone()).is_none());
+    assert!(db.get(&input.0.clone()).unwrap().is_none());
     let state = db.shared.state.lock().unwrap();
     assert!(state.expirations.first().is_none());
     drop(state);
 
 }
+
+#[tokio::test]
+async fn test_replica_keeps_expired_keys_hidden() {
+    let mut db = Db::new();
+    db.set_active_expire(false);
+
+    db.set(Bytes::from_static(b"key"), Bytes::from_static(b"data"), Some(Duration::from_millis(50)));
+
+    sleep(Duration::from_millis(100)).await;
+
+    // logically gone, physically kept until the master says otherwise
+    assert!(db.get(b"key").unwrap().is_none());
+    assert!(db.shared.state.lock().unwrap().entries.contains_key(&b"key"[..]));
+
+    // reported as missing, but gone once the master deletes it
+    assert!(!db.delete(b"key"));
+    assert!(!db.shared.state.lock().unwrap().entries.contains_key(&b"key"[..]));
+}
+
+#[tokio::test]
+async fn test_lazy_expire_on_read() {
+    let mut db = Db::new();
+
+    db.set(Bytes::from_static(b"key"), Bytes::from_static(b"data"), Some(Duration::from_secs(60)));
+
+    // pretend the TTL passed without the background task noticing
+    {
+        let mut state = db.shared.state.lock().unwrap();
+        let past = Instant::now() - Duration::from_millis(1);
+        let old = state.entries.get_mut(&b"key"[..]).unwrap().expires_at.replace(past).unwrap();
+        state.expirations.remove(&(old, Expiring::Key(Bytes::from_static(b"key"))));
+        state.expirations.insert((past, Expiring::Key(Bytes::from_static(b"key"))));
+    }
+
+    assert!(db.get(b"key").unwrap().is_none());
+    assert!(!db.shared.state.lock().unwrap().entries.contains_key(&b"key"[..]));
+    assert!(db.shared.state.lock().unwrap().expirations.is_empty());
+    assert_eq!(db.expired_keys(), 1);
+}
+
+// the DEL is left to whoever holds the hook lock, so a read can't slip it in
+// ahead of the write holding it or while a replica joins the stream
+#[tokio::test]
+async fn test_lazy_expire_waits_for_hook_lock() {
+    let mut db = Db::new();
+
+    let expired = Arc::new(Mutex::new(vec![]));
+    let lock = Arc::new(AsyncMutex::new(()));
+    db.set_expire_hook(ExpireHook {
+        lock: lock.clone(),
+        expired: Box::new({
+            let expired = expired.clone();
+            move |key, _| expired.lock().unwrap().push(Bytes::copy_from_slice(key))
+        }),
+    });
+
+    db.set(Bytes::from_static(b"key"), Bytes::from_static(b"data"), Some(Duration::from_millis(10)));
+
+    let guard = lock.lock().await;
+    sleep(Duration::from_millis(20)).await;
+
+    assert!(db.get(b"key").unwrap().is_none());
+    assert!(expired.lock().unwrap().is_empty());
+
+    db.propagate_expired();
+    assert_eq!(*expired.lock().unwrap(), vec![Bytes::from_static(b"key")]);
+    drop(guard);
+}
+
+#[tokio::test]
+async fn test_expired_keys_not_deleted() {
+    let mut db = Db::new();
+
+    db.set(Bytes::from_static(b"key"), Bytes::from_static(b"data"), Some(Duration::from_millis(10)));
+    db.with_keyspace(|keyspace| {
+        let hash = [(Bytes::from_static(b"field"), Bytes::from_static(b"value"))].into_iter().collect();
+        keyspace.insert(Bytes::from_static(b"hash"), Value::Hash(hash));
+        keyspace.expire_field(b"hash", &Bytes::from_static(b"field"), Instant::now() + Duration::from_millis(10));
+    });
+
+    sleep(Duration::from_millis(20)).await;
+
+    // DEL finds neither, the hash went with its last field
+    assert!(!db.delete(b"key"));
+    assert!(!db.delete(b"hash"));
+    assert_eq!(db.expired_keys(), 2);
+}
+
+#[tokio::test]
+async fn test_replica_keeps_expired_fields_hidden() {
+    let db = Db::new();
+    db.set_active_expire(false);
+
+    db.with_keyspace(|keyspace| {
+        let hash = [
+            (Bytes::from_static(b"gone"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"kept"), Bytes::from_static(b"2")),
+        ].into_iter().collect();
+        keyspace.insert(Bytes::from_static(b"hash"), Value::Hash(hash));
+        keyspace.expire_field(b"hash", &Bytes::from_static(b"gone"), Instant::now() + Duration::from_millis(10));
+    });
+
+    sleep(Duration::from_millis(20)).await;
+
+    db.with_keyspace(|keyspace| {
+        let hash = keyspace.get(b"hash").unwrap().as_hash().unwrap();
+
+        assert!(hash.get(b"gone").is_none());
+        assert_eq!(hash.keys().collect::<Vec<_>>(), vec![&Bytes::from_static(b"kept")]);
+        // stored until the master deletes it
+        assert_eq!(hash.len(), 2);
+    });
+}
+
+#[test]
+fn test_hash_remove_expired_after_earliest_removed() {
+    let now = Instant::now();
+    let mut hash: Hash = [
+        (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+        (Bytes::from_static(b"b"), Bytes::from_static(b"2")),
+    ].into_iter().collect();
+
+    hash.set_expire(Bytes::from_static(b"a"), now + Duration::from_millis(10));
+    hash.set_expire(Bytes::from_static(b"b"), now + Duration::from_millis(30));
+    assert!(hash.remove_expired(now).is_empty());
+
+    // the earliest deadline went away, the next one is still found
+    hash.persist(b"a");
+    assert!(hash.remove_expired(now + Duration::from_millis(20)).is_empty());
+    assert_eq!(
+        hash.remove_expired(now + Duration::from_millis(40)),
+        vec![(Bytes::from_static(b"b"), now + Duration::from_millis(30))],
+    );
+    assert!(!hash.has_expires());
+}
@@ -0,0 +1,78 @@
+use std::collections::{HashSet, VecDeque};
+
+use bytes::Bytes;
+
+use super::Hash;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Bytes),
+    List(VecDeque<Bytes>),
+    Hash(Hash),
+    Set(HashSet<Bytes>),
+}
+
+// a command was run against a key holding another type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WrongType;
+
+impl Value {
+    // aggregates left empty are removed from the keyspace
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+        }
+    }
+
+    pub fn as_string(&self) -> Result<&Bytes, WrongType> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&VecDeque<Bytes>, WrongType> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut VecDeque<Bytes>, WrongType> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_hash(&self) -> Result<&Hash, WrongType> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Result<&mut Hash, WrongType> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_set(&self) -> Result<&HashSet<Bytes>, WrongType> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(WrongType),
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Result<&mut HashSet<Bytes>, WrongType> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(WrongType),
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::redis::config::ProtoLimits;
+
+use super::{inline, invalid_len, parse_double, Frame, FrameError};
+
+// longest inline command or frame header, as in redis
+const MAX_LINE: usize = 64 * 1024;
+
+// Resumable RESP parser. Every line and bulk payload is taken off the
+// buffer as soon as it is complete, and aggregates still waiting for
+// elements are kept on a stack, so bytes already seen are never parsed
+// again when the rest of a frame arrives with the next read.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    // aggregates waiting for elements, innermost last
+    stack: Vec<Aggregate>,
+    // header read, waiting for the payload
+    bulk: Option<PendingBulk>,
+    // buffer prefix already searched for the end of the current line
+    scanned: usize,
+    // bytes of the current frame already taken off the buffer
+    pending: usize,
+    limits: ProtoLimits,
+}
+
+#[derive(Debug)]
+struct Aggregate {
+    kind: AggregateKind,
+    remaining: usize,
+    items: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AggregateKind {
+    Array,
+    Set,
+    Push,
+    Map,
+    // the attribute pairs followed by the frame they describe
+    Attributes,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingBulk {
+    verbatim: bool,
+    len: usize,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    pub fn set_limits(&mut self, limits: ProtoLimits) {
+        self.limits = limits;
+    }
+
+    // whether `buf` along with the partly decoded frame still fits the query buffer
+    pub fn check_limit(&self, buf: &BytesMut) -> Result<(), FrameError> {
+        if self.limits.query_buffer_limit > 0 && buf.len() + self.pending > self.limits.query_buffer_limit {
+            return Err("protocol error; client query buffer limit reached".into());
+        }
+
+        Ok(())
+    }
+
+    // Ok(None) until a whole frame is in, bytes consumed so far stay consumed
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Frame>, FrameError> {
+        self.check_limit(buf)?;
+
+        loop {
+            let frame = match self.bulk {
+                Some(bulk) => match self.take_bulk(buf, bulk)? {
+                    Some(frame) => frame,
+                    None => return Ok(None),
+                },
+                None if self.stack.is_empty() && buf.first().is_some_and(|&b| Frame::is_inline(b)) => {
+                    match self.take_inline(buf)? {
+                        // blank lines are skipped, as telnet users tend to send them
+                        Some(frame) if frame == Frame::array() => continue,
+                        Some(frame) => frame,
+                        None => return Ok(None),
+                    }
+                }
+                None => {
+                    let line = match self.take_line(buf)? {
+                        Some(line) => line,
+                        None => return Ok(None),
+                    };
+
+                    match self.start_frame(&line)? {
+                        Some(frame) => frame,
+                        // an aggregate or a bulk payload follows
+                        None => continue,
+                    }
+                }
+            };
+
+            if let Some(frame) = self.complete(frame) {
+                self.pending = 0;
+
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    // a frame header, or the whole frame for the single line types
+    fn start_frame(&mut self, line: &[u8]) -> Result<Option<Frame>, FrameError> {
+        let (&type_byte, rest) = match line.split_first() {
+            Some(split) => split,
+            None => return Err("protocol error; empty frame".into()),
+        };
+
+        let frame = match type_byte {
+            b'+' => Frame::Simple(String::from_utf8(rest.to_vec())?),
+            b'-' => Frame::Error(String::from_utf8(rest.to_vec())?),
+            b':' => Frame::Integer(parse_int(rest)?),
+            b'_' => Frame::Null,
+            b',' => Frame::Double(parse_double(std::str::from_utf8(rest).map_err(|_| "protocol error; invalid double")?)?),
+            b'#' => match rest {
+                b"t" => Frame::Boolean(true),
+                b"f" => Frame::Boolean(false),
+                _ => return Err("protocol error; invalid boolean".into()),
+            },
+            b'(' => Frame::BigNumber(String::from_utf8(rest.to_vec())?),
+            b'$' => match parse_len(rest)? {
+                Some(len) if len > self.limits.max_bulk_len => {
+                    return Err("protocol error; invalid bulk length".into());
+                }
+                Some(len) => {
+                    self.bulk = Some(PendingBulk { verbatim: false, len });
+                    return Ok(None);
+                }
+                None => Frame::Null,
+            },
+            b'=' => {
+                let len = parse_count(rest)?;
+                if len > self.limits.max_bulk_len {
+                    return Err("protocol error; invalid bulk length".into());
+                }
+
+                self.bulk = Some(PendingBulk { verbatim: true, len });
+                return Ok(None);
+            }
+            b'*' => match parse_len(rest)? {
+                Some(len) => return self.start_aggregate(AggregateKind::Array, len),
+                None => Frame::NullArray,
+            },
+            b'~' => return self.start_aggregate(AggregateKind::Set, parse_count(rest)?),
+            b'>' => return self.start_aggregate(AggregateKind::Push, parse_count(rest)?),
+            b'%' => return self.start_aggregate(AggregateKind::Map, parse_count(rest)?),
+            b'|' => return self.start_aggregate(AggregateKind::Attributes, parse_count(rest)?),
+            unknown => {
+                return Err(
+                    FrameError::Other(
+                        format!("protocol error; invalid frame type byte `{}`", unknown)
+                    )
+                );
+            }
+        };
+
+        Ok(Some(frame))
+    }
+
+    // `len` is the declared length, pairs for maps and attributes
+    fn start_aggregate(&mut self, kind: AggregateKind, len: usize) -> Result<Option<Frame>, FrameError> {
+        if len > self.limits.max_multibulk_len {
+            return Err("protocol error; invalid multibulk length".into());
+        }
+        if self.stack.len() >= self.limits.max_nesting {
+            return Err("protocol error; too deeply nested frame".into());
+        }
+
+        let len = match kind {
+            AggregateKind::Map => len * 2,
+            AggregateKind::Attributes => len * 2 + 1,
+            _ => len,
+        };
+
+        let aggregate = Aggregate {
+            kind,
+            remaining: len,
+            // the declared length is not trusted for the allocation
+            items: Vec::with_capacity(len.min(1024)),
+        };
+
+        if len == 0 {
+            return Ok(Some(aggregate.finish()));
+        }
+
+        self.stack.push(aggregate);
+
+        Ok(None)
+    }
+
+    // adds a finished frame to its parent, returns it once nothing is pending
+    fn complete(&mut self, mut frame: Frame) -> Option<Frame> {
+        loop {
+            let parent = match self.stack.last_mut() {
+                Some(parent) => parent,
+                None => return Some(frame),
+            };
+
+            parent.items.push(frame);
+            parent.remaining -= 1;
+
+            if parent.remaining > 0 {
+                return None;
+            }
+
+            frame = self.stack.pop()?.finish();
+        }
+    }
+
+    fn take_bulk(&mut self, buf: &mut BytesMut, bulk: PendingBulk) -> Result<Option<Frame>, FrameError> {
+        if buf.len() < bulk.len + 2 {
+            return Ok(None);
+        }
+
+        // bulk payload is binary safe: exactly `len` bytes followed by \r\n
+        if &buf[bulk.len..bulk.len + 2] != b"\r\n" {
+            return Err("protocol error; bulk string not terminated by CRLF".into());
+        }
+
+        let data = buf.split_to(bulk.len).freeze();
+        buf.advance(2);
+        self.bulk = None;
+        self.pending += bulk.len + 2;
+
+        if !bulk.verbatim {
+            return Ok(Some(Frame::Bulk(data)));
+        }
+
+        if data.len() < 4 || data[3] != b':' {
+            return Err("protocol error; invalid verbatim string".into());
+        }
+
+        Ok(Some(Frame::Verbatim(
+            String::from_utf8(data[..3].to_vec())?,
+            data.slice(4..),
+        )))
+    }
+
+    // line without the CRLF, the search resumes where the last one stopped
+    fn take_line(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, FrameError> {
+        match find_crlf(&buf[..], self.scanned) {
+            Some(end) if end > MAX_LINE => Err("protocol error; too big frame header".into()),
+            Some(end) => {
+                self.scanned = 0;
+
+                let line = buf.split_to(end).freeze();
+                buf.advance(2);
+                self.pending += end + 2;
+
+                Ok(Some(line))
+            }
+            None if buf.len() > MAX_LINE => Err("protocol error; too big frame header".into()),
+            None => {
+                // a trailing \r may still be followed by \n
+                self.scanned = buf.len().saturating_sub(1);
+
+                Ok(None)
+            }
+        }
+    }
+
+    // inline commands end with `\n`, optionally preceded by `\r`, and come
+    // back as the array of bulks a RESP client would send
+    fn take_inline(&mut self, buf: &mut BytesMut) -> Result<Option<Frame>, FrameError> {
+        let end = match find_byte(&buf[..], self.scanned, b'\n') {
+            Some(end) if end > MAX_LINE => return Err("protocol error; too big inline request".into()),
+            Some(end) => end,
+            None if buf.len() > MAX_LINE => return Err("protocol error; too big inline request".into()),
+            None => {
+                self.scanned = buf.len();
+                return Ok(None);
+            }
+        };
+        self.scanned = 0;
+
+        let line = buf.split_to(end + 1);
+        let line = line[..end].strip_suffix(b"\r").unwrap_or(&line[..end]);
+
+        Ok(Some(Frame::Array(inline::split_args(line)?.into_iter().map(Frame::Bulk).collect())))
+    }
+}
+
+impl Aggregate {
+    fn finish(self) -> Frame {
+        match self.kind {
+            AggregateKind::Array => Frame::Array(self.items),
+            AggregateKind::Set => Frame::Set(self.items),
+            AggregateKind::Push => Frame::Push(self.items),
+            AggregateKind::Map => Frame::Map(pairs(self.items)),
+            AggregateKind::Attributes => {
+                let mut items = self.items;
+                let frame = items.pop().unwrap_or(Frame::Null);
+
+                Frame::Attributes(pairs(items), Box::new(frame))
+            }
+        }
+    }
+}
+
+fn pairs(items: Vec<Frame>) -> Vec<(Frame, Frame)> {
+    let mut items = items.into_iter();
+    let mut result = Vec::with_capacity(items.len() / 2);
+
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        result.push((key, value));
+    }
+
+    result
+}
+
+fn parse_int(digits: &[u8]) -> Result<i64, FrameError> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| FrameError::Other("Can't parse integer".to_string()))
+}
+
+// bulk and array length, None for the `-1` null
+fn parse_len(digits: &[u8]) -> Result<Option<usize>, FrameError> {
+    match parse_int(digits)? {
+        -1 => Ok(None),
+        len => Ok(Some(len.try_into().map_err(|_| invalid_len(len))?)),
+    }
+}
+
+// length of a type that has no null form
+fn parse_count(digits: &[u8]) -> Result<usize, FrameError> {
+    let len = parse_int(digits)?;
+
+    len.try_into().map_err(|_| invalid_len(len))
+}
+
+// position of the first \r\n at or after `from`
+pub(super) fn find_crlf(buf: &[u8], from: usize) -> Option<usize> {
+    let mut from = from;
+
+    loop {
+        let lf = find_byte(buf, from, b'\n')?;
+
+        if lf > from && buf[lf - 1] == b'\r' {
+            return Some(lf - 1);
+        }
+
+        from = lf + 1;
+    }
+}
+
+// word at a time search, eight bytes per step instead of one
+pub(super) fn find_byte(buf: &[u8], from: usize, needle: u8) -> Option<usize> {
+    const LO: u64 = 0x0101_0101_0101_0101;
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    let pattern = LO * needle as u64;
+    let mut i = from;
+
+    while i + 8 <= buf.len() {
+        let mut word = [0; 8];
+        word.copy_from_slice(&buf[i..i + 8]);
+
+        let x = u64::from_le_bytes(word) ^ pattern;
+        let found = x.wrapping_sub(LO) & !x & HI;
+
+        if found != 0 {
+            return Some(i + (found.trailing_zeros() / 8) as usize);
+        }
+
+        i += 8;
+    }
+
+    buf[i.min(buf.len())..].iter().position(|&b| b == needle).map(|pos| i + pos)
+}
@@ -0,0 +1,96 @@
+use bytes::Bytes;
+
+use super::FrameError;
+
+const UNBALANCED_QUOTES: &str = "protocol error; unbalanced quotes in request";
+
+// splits an inline command line into arguments the way redis-cli does:
+// double quotes understand `\n \r \t \b \a \xHH` escapes, single quotes
+// only `\'`, and a closing quote must be followed by a space
+pub(crate) fn split_args(line: &[u8]) -> Result<Vec<Bytes>, FrameError> {
+    let mut args = vec![];
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = vec![];
+        let mut in_double = false;
+        let mut in_single = false;
+
+        loop {
+            if in_double {
+                match line.get(i) {
+                    None => return Err(UNBALANCED_QUOTES.into()),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'x') && hex_byte(line, i + 2).is_some() => {
+                        arg.push(hex_byte(line, i + 2).unwrap());
+                        i += 3;
+                    }
+                    Some(b'\\') if i + 1 < line.len() => {
+                        i += 1;
+                        arg.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            c => c,
+                        });
+                    }
+                    Some(b'"') => {
+                        closing_quote(line, i)?;
+                        in_double = false;
+                    }
+                    Some(&c) => arg.push(c),
+                }
+            } else if in_single {
+                match line.get(i) {
+                    None => return Err(UNBALANCED_QUOTES.into()),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    Some(b'\'') => {
+                        closing_quote(line, i)?;
+                        in_single = false;
+                    }
+                    Some(&c) => arg.push(c),
+                }
+            } else {
+                match line.get(i) {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double = true,
+                    Some(b'\'') => in_single = true,
+                    Some(&c) => arg.push(c),
+                }
+            }
+
+            i += 1;
+        }
+
+        args.push(Bytes::from(arg));
+    }
+}
+
+fn hex_byte(line: &[u8], at: usize) -> Option<u8> {
+    let digits = line.get(at..at + 2)?;
+    if !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+fn closing_quote(line: &[u8], at: usize) -> Result<(), FrameError> {
+    match line.get(at + 1) {
+        None => Ok(()),
+        Some(c) if c.is_ascii_whitespace() => Ok(()),
+        Some(_) => Err(UNBALANCED_QUOTES.into()),
+    }
+}
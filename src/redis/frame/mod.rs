@@ -11,13 +11,41 @@ use thiserror::Error;
 
 use crate::redis::utils;
 
+pub use decoder::Decoder;
+
+mod decoder;
+mod inline;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Frame {
     Simple(String),
+    // message includes the error prefix, e.g. `ERR` or `WRONGTYPE`
+    Error(String),
     Bulk(Bytes),
-    Integer(u64),
+    Integer(i64),
     Null,
+    // `*-1`, what RESP2 replies for a missing array
+    NullArray,
     Array(Vec<Frame>),
+
+    // RESP3, flattened when written to a RESP2 client
+    Map(Vec<(Frame, Frame)>),
+    Set(Vec<Frame>),
+    Double(f64),
+    Boolean(bool),
+    BigNumber(String),
+    // three chars format, e.g. `txt` or `mkd`
+    Verbatim(String, Bytes),
+    Push(Vec<Frame>),
+    // attributes sent ahead of the frame they describe
+    Attributes(Vec<(Frame, Frame)>, Box<Frame>),
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
 }
 
 #[derive(Error, Debug)]
@@ -27,58 +55,25 @@ pub enum FrameError {
 }
 
 impl Frame {
-    pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), FrameError> {
-        match get_u8(src)? {
-            // simple
-            b'+' => {
-                get_line(src)?;
-            }
-            // bulk
-            b'$' => {
-                if peek(src)? == b'-' {
-                    skip(src, 4)?
-                } else {
-                    let len = get_int(src)?;
-
-                    // check for valid string of len `len` + \r\n
-                    skip(src, len + 2)?
-                }
-            }
-            //integer
-            b':' => {
-                get_int(src)?;
-            }
-            // array
-            b'*' => {
-                let len = get_int(src)?;
-                for _ in 0..len {
-                    Frame::check(src)?;
-                }
-            }
-            unknown => {
-                return Err(
-                    FrameError::Other(
-                        format!("protocol error; invalid frame type byte `{}`", unknown)
-                    )
-                );
-            }
-        }
-
-        Ok(())
+    // anything not starting with a type byte is an inline command
+    pub fn is_inline(first: u8) -> bool {
+        !matches!(
+            first,
+            b'+' | b'-' | b'$' | b':' | b'*' | b'_' | b',' | b'#' | b'(' | b'=' | b'~' | b'>' | b'%' | b'|'
+        )
     }
+
     pub fn parse_rdb(src: &mut Cursor<&[u8]>) -> Result<Bytes, FrameError> {
         match get_u8(src)? {
             b'$' => {
-                let content_len = get_int(src)?;
+                let content_len = get_count(src)?;
                 let start = src.position() as usize;
-                if src.get_ref().len() - 1 < start + content_len {
-                    return Err(FrameError::Other("Corrupted RDB".into()));
+                if src.remaining() < content_len {
+                    return Err(FrameError::Incomplete);
                 }
                 src.set_position((start + content_len) as u64);
 
-                let resp = Ok(src.get_ref()[start..start + content_len].to_vec().into());
-
-                resp
+                Ok(src.get_ref()[start..start + content_len].to_vec().into())
             }
             _ => {
                 Err(FrameError::Other("Wrong RDB file format".into()))
@@ -86,76 +81,39 @@ impl Frame {
         }
     }
 
-    pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, FrameError> {
-        match get_u8(src)? {
-            // simple
-            b'+' => {
-                let line = get_line(src)?.to_vec();
-
-                let string = String::from_utf8(line)?;
-
-                Ok(Frame::Simple(string))
-            }
-            // bulk
-            b'$' => {
-                if peek(src)? == b'-' {
-                    let line = get_line(src)?;
-
-                    if line != b"-1" {
-                        return Err(
-                            FrameError::Other("Wrong format".to_string())
-                        );
-                    }
-
-                    Ok(Frame::Null)
-                } else {
-                    let len = get_int(src)?;
-
-                    if src.remaining() < len + 2 {
-                        return Err(FrameError::Incomplete);
-                    }
-
-                    let line = get_line(src)?.to_vec();
-
-                    Ok(Frame::Bulk(line.into()))
-                }
-            }
-            // integer
-            b':' => {
-                let int = get_int(src)?;
-
-                Ok(Frame::Integer(int.try_into()?))
-            }
-            // array
-            b'*' => {
-                let len = get_int(src)?;
-
-                let mut result = Vec::with_capacity(len);
-
-                for _ in 0..len {
-                    result.push(Frame::parse(src)?);
-                }
+    // RESP2 encoding, RESP3 types come out flattened
+    pub fn to_response(&self) -> Vec<u8> {
+        self.encode(Protocol::Resp2)
+    }
 
-                Ok(Frame::Array(result))
-            }
-            // unknown
-            any => {
-                eprintln!("Unknown frame type: {}", String::from_utf8(vec![any]).unwrap());
-                unimplemented!()
+    pub fn encode(&self, protocol: Protocol) -> Vec<u8> {
+        if protocol == Protocol::Resp2 {
+            if let Some(flat) = self.to_resp2() {
+                return flat.encode(protocol);
             }
         }
-    }
 
-    pub fn to_response(&self) -> Vec<u8> {
         match self {
             Frame::Simple(val) => {
                 format!("+{}\r\n", val)
                     .as_bytes()
                     .to_vec()
             }
-            Frame::Null => {
-                b"$-1\r\n".to_vec()
+            Frame::Error(val) => {
+                // errors may quote what the client sent, which must not
+                // end the line early, as in redis' addReplyErrorLength
+                format!("-{}\r\n", val.replace(['\r', '\n'], " "))
+                    .as_bytes()
+                    .to_vec()
             }
+            Frame::Null => match protocol {
+                Protocol::Resp2 => b"$-1\r\n".to_vec(),
+                Protocol::Resp3 => b"_\r\n".to_vec(),
+            },
+            Frame::NullArray => match protocol {
+                Protocol::Resp2 => b"*-1\r\n".to_vec(),
+                Protocol::Resp3 => b"_\r\n".to_vec(),
+            },
             Frame::Integer(val) => {
                 format!(":{}\r\n", val).as_bytes().to_vec()
             }
@@ -171,22 +129,54 @@ impl Frame {
 
                 buff
             }
-            Frame::Array(arr) => {
-                let mut buff: Vec<u8> = Vec::new();
-                buff.push(b'*');
-
-                buff.extend(utils::int_as_bytes(&arr.len()));
+            Frame::Array(arr) => encode_items(b'*', arr, protocol),
+            Frame::Set(items) => encode_items(b'~', items, protocol),
+            Frame::Push(items) => encode_items(b'>', items, protocol),
+            Frame::Map(pairs) => encode_pairs(b'%', pairs, protocol),
+            Frame::Attributes(attrs, frame) => {
+                let mut buff = encode_pairs(b'|', attrs, protocol);
+                buff.extend(frame.encode(protocol));
 
+                buff
+            }
+            Frame::Double(val) => {
+                format!(",{}\r\n", format_double(*val)).into_bytes()
+            }
+            Frame::Boolean(val) => {
+                format!("#{}\r\n", if *val { 't' } else { 'f' }).into_bytes()
+            }
+            Frame::BigNumber(val) => {
+                format!("({}\r\n", val).into_bytes()
+            }
+            Frame::Verbatim(format, text) => {
+                let mut buff = format!("={}\r\n{}:", text.len() + 4, format).into_bytes();
+                buff.extend(text);
                 utils::add_cr(&mut buff);
-                for frame in arr {
-                    buff.extend(frame.to_response());
-                }
 
                 buff
             }
         }
     }
 
+    // RESP2 equivalent of a RESP3 only frame
+    fn to_resp2(&self) -> Option<Frame> {
+        let flat = match self {
+            Frame::Map(pairs) => Frame::Array(
+                pairs.iter().flat_map(|(key, value)| [key.clone(), value.clone()]).collect()
+            ),
+            Frame::Set(items) | Frame::Push(items) => Frame::Array(items.clone()),
+            Frame::Double(val) => Frame::Bulk(format_double(*val).into()),
+            Frame::Boolean(val) => Frame::Integer(*val as i64),
+            Frame::BigNumber(val) => Frame::Bulk(val.clone().into()),
+            Frame::Verbatim(_, text) => Frame::Bulk(text.clone()),
+            // RESP2 has no attributes, the frame goes alone
+            Frame::Attributes(_, frame) => *frame.clone(),
+            _ => return None,
+        };
+
+        Some(flat)
+    }
+
     pub fn array() -> Frame {
         Frame::Array(vec![])
     }
@@ -198,17 +188,14 @@ impl Frame {
         }
     }
 
-    pub fn extend(&mut self, frames: Vec<Frame>) {
-        match self {
-            Frame::Array(arr) => arr.extend(frames),
-            _ => panic!("Must be an array frame")
-        }
-    }
-
+    // length of the RESP2 encoding
     pub fn byte_len(&self) -> usize {
         match self {
-            Frame::Simple(s) => s.len() + 3, // len of str + 1 for encoding byte + 2 for\r\n
-            Frame::Integer(n) => utils::count_digits(&(*n as usize)) + 3,
+            Frame::Simple(s) | Frame::Error(s) => s.len() + 3, // len of str + 1 for encoding byte + 2 for\r\n
+            Frame::Integer(n) => {
+                let sign = if *n < 0 { 1 } else { 0 };
+                sign + utils::count_digits(&(n.unsigned_abs() as usize)) + 3
+            }
             Frame::Array(arr) => {
                 let mut len = utils::count_digits(&arr.len()) + 3;
                 for frame in arr {
@@ -217,29 +204,13 @@ impl Frame {
                 len
             }
             Frame::Bulk(s) => utils::count_digits(&s.len()) + s.len() + 5,
-            Frame::Null => 5
+            Frame::Null | Frame::NullArray => 5,
+            frame => frame.to_response().len(),
         }
     }
 }
 
 
-fn peek(src: &mut Cursor<&[u8]>) -> Result<u8, FrameError> {
-    if !src.has_remaining() {
-        return Err(FrameError::Incomplete);
-    }
-
-    Ok(src.chunk()[0])
-}
-
-fn skip(src: &mut Cursor<&[u8]>, n: usize) -> Result<(), FrameError> {
-    if src.remaining() < n {
-        return Err(FrameError::Incomplete);
-    }
-
-    src.advance(n);
-    Ok(())
-}
-
 fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, FrameError> {
     if !src.has_remaining() {
         return Err(FrameError::Incomplete);
@@ -248,7 +219,7 @@ fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, FrameError> {
     Ok(src.get_u8())
 }
 
-fn get_int(src: &mut Cursor<&[u8]>) -> Result<usize, FrameError> {
+fn get_int(src: &mut Cursor<&[u8]>) -> Result<i64, FrameError> {
     if !src.has_remaining() {
         return Err(FrameError::Incomplete);
     }
@@ -262,18 +233,67 @@ fn get_int(src: &mut Cursor<&[u8]>) -> Result<usize, FrameError> {
         })
 }
 
+// length of a type that has no null form
+fn get_count(src: &mut Cursor<&[u8]>) -> Result<usize, FrameError> {
+    let len = get_int(src)?;
+
+    len.try_into().map_err(|_| invalid_len(len))
+}
+
+fn invalid_len(len: i64) -> FrameError {
+    FrameError::Other(format!("protocol error; invalid length `{}`", len))
+}
+
+fn encode_items(prefix: u8, items: &[Frame], protocol: Protocol) -> Vec<u8> {
+    let mut buff = vec![prefix];
+    buff.extend(utils::int_as_bytes(&items.len()));
+    utils::add_cr(&mut buff);
+
+    for frame in items {
+        buff.extend(frame.encode(protocol));
+    }
+
+    buff
+}
+
+fn encode_pairs(prefix: u8, pairs: &[(Frame, Frame)], protocol: Protocol) -> Vec<u8> {
+    let mut buff = vec![prefix];
+    buff.extend(utils::int_as_bytes(&pairs.len()));
+    utils::add_cr(&mut buff);
+
+    for (key, value) in pairs {
+        buff.extend(key.encode(protocol));
+        buff.extend(value.encode(protocol));
+    }
+
+    buff
+}
+
+fn format_double(val: f64) -> String {
+    if val.is_nan() {
+        return "nan".to_string();
+    }
+
+    // `inf` and `-inf` as Display writes them
+    val.to_string()
+}
+
+fn parse_double(line: &str) -> Result<f64, FrameError> {
+    match line {
+        "inf" | "+inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        "nan" => Ok(f64::NAN),
+        _ => line.parse().map_err(|_| "protocol error; invalid double".into()),
+    }
+}
+
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], FrameError> {
     let start = src.position() as usize;
-    let end = src.get_ref().len() - 1;
+    let end = decoder::find_crlf(src.get_ref(), start).ok_or(FrameError::Incomplete)?;
 
-    for i in start..end {
-        if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
-            src.set_position((i + 2) as u64);
+    src.set_position((end + 2) as u64);
 
-            return Ok(&src.get_ref()[start..i]);
-        }
-    }
-    Err(FrameError::Incomplete)
+    Ok(&src.get_ref()[start..end])
 }
 
 impl From<String> for FrameError {
@@ -1,7 +1,14 @@
+use bytes::BytesMut;
+
+use crate::redis::config::ProtoLimits;
 use crate::redis::tests::make_frame;
 
 use super::*;
 
+fn decode(input: &[u8]) -> Result<Option<Frame>, FrameError> {
+    Decoder::new().decode(&mut BytesMut::from(input))
+}
+
 #[test]
 fn test_parse_simple() {
     let frame = make_frame(b"+OK\r\n");
@@ -113,3 +120,438 @@ fn test_byte_len() {
     )
 }
 
+
+#[test]
+fn test_parse_bulk_with_crlf_inside() {
+    let frame = make_frame(b"$8\r\nab\r\ncd\r\n\r\n");
+
+    let expected = Frame::Bulk(
+        Bytes::from_static(b"ab\r\ncd\r\n")
+    );
+
+    assert_eq!(expected, frame);
+}
+
+#[test]
+fn test_parse_bulk_with_lone_cr_and_lf() {
+    let frame = make_frame(b"$4\r\n\ra\nb\r\n");
+
+    let expected = Frame::Bulk(
+        Bytes::from_static(b"\ra\nb")
+    );
+
+    assert_eq!(expected, frame);
+}
+
+#[test]
+fn test_parse_array_of_binary_bulks() {
+    let frame = make_frame(b"*3\r\n$0\r\n\r\n$2\r\n\r\n\r\n$1\r\nx\r\n");
+
+    let expected = Frame::Array(vec![
+        Frame::Bulk(Bytes::from_static(b"")),
+        Frame::Bulk(Bytes::from_static(b"\r\n")),
+        Frame::Bulk(Bytes::from_static(b"x")),
+    ]);
+
+    assert_eq!(expected, frame);
+}
+
+#[test]
+fn test_to_response_binary_bulk() {
+    let input = b"$8\r\nab\r\ncd\r\n\r\n";
+    let frame = make_frame(input);
+
+    assert_eq!(frame.to_response(), input)
+}
+
+#[test]
+fn test_parse_bulk_bad_terminator() {
+    let input: &[u8] = b"$3\r\nabcde\r\n";
+
+    assert!(matches!(
+        decode(input),
+        Err(FrameError::Other(_))
+    ));
+    // caught as soon as the two bytes after the data arrive
+    assert!(matches!(
+        decode(b"$3\r\nabcde"),
+        Err(FrameError::Other(_))
+    ));
+}
+
+#[test]
+fn test_parse_bulk_incomplete() {
+    let input: &[u8] = b"$5\r\nhel";
+
+    assert!(matches!(
+        decode(input),
+        Ok(None)
+    ));
+
+    // payload is there but the terminator is not
+    let input: &[u8] = b"$5\r\nhello\r";
+
+    assert!(matches!(
+        decode(input),
+        Ok(None)
+    ));
+}
+
+#[test]
+fn test_parse_error() {
+    let input = b"-ERR unknown command 'foo'\r\n";
+    let frame = make_frame(input);
+
+    let expected = Frame::Error(
+        String::from("ERR unknown command 'foo'")
+    );
+
+    assert_eq!(expected, frame);
+    assert_eq!(frame.to_response(), input);
+    assert_eq!(frame.byte_len(), input.len());
+}
+
+#[test]
+fn test_parse_unknown_type_byte() {
+    // at the top level it would be taken for an inline command
+    let input: &[u8] = b"*1\r\n!oops\r\n";
+
+    assert!(matches!(
+        decode(input),
+        Err(FrameError::Other(_))
+    ));
+}
+
+#[test]
+fn test_resp3_round_trip() {
+    let inputs: [&[u8]; 9] = [
+        b"%2\r\n+a\r\n:1\r\n$1\r\nb\r\n#t\r\n",
+        b"~2\r\n+a\r\n:2\r\n",
+        b",3.25\r\n",
+        b"#f\r\n",
+        b"(3492890328409238509324850943850943825024385\r\n",
+        b"=15\r\ntxt:Some string\r\n",
+        b"_\r\n",
+        b">2\r\n$7\r\nmessage\r\n$2\r\nhi\r\n",
+        b"|1\r\n+ttl\r\n:100\r\n$3\r\nval\r\n",
+    ];
+
+    for input in inputs {
+        let frame = make_frame(input);
+
+        assert_eq!(frame.encode(Protocol::Resp3), input);
+    }
+}
+
+#[test]
+fn test_parse_resp3_types() {
+    assert_eq!(
+        make_frame(b"%1\r\n+key\r\n,-1.5\r\n"),
+        Frame::Map(vec![(Frame::Simple("key".to_string()), Frame::Double(-1.5))]),
+    );
+    assert_eq!(
+        make_frame(b"=8\r\nmkd:# hi\r\n"),
+        Frame::Verbatim("mkd".to_string(), Bytes::from_static(b"# hi")),
+    );
+    assert_eq!(
+        make_frame(b"|1\r\n+a\r\n+b\r\n:7\r\n"),
+        Frame::Attributes(
+            vec![(Frame::Simple("a".to_string()), Frame::Simple("b".to_string()))],
+            Box::new(Frame::Integer(7)),
+        ),
+    );
+    assert_eq!(make_frame(b",inf\r\n"), Frame::Double(f64::INFINITY));
+    assert_eq!(make_frame(b",-inf\r\n"), Frame::Double(f64::NEG_INFINITY));
+    assert!(matches!(make_frame(b",nan\r\n"), Frame::Double(val) if val.is_nan()));
+}
+
+#[test]
+fn test_parse_resp3_invalid() {
+    let inputs: [&[u8]; 3] = [b"#x\r\n", b",abc\r\n", b"=3\r\ntxt\r\n"];
+
+    for input in inputs {
+        assert!(matches!(
+            decode(input),
+            Err(FrameError::Other(_))
+        ));
+    }
+
+    // the frame after the attributes is still missing
+    let input: &[u8] = b"|1\r\n+a\r\n+b\r\n";
+
+    assert!(matches!(
+        decode(input),
+        Ok(None)
+    ));
+}
+
+#[test]
+fn test_resp3_flattened_for_resp2() {
+    let frame = Frame::Map(vec![
+        (Frame::Bulk("a".into()), Frame::Double(1.5)),
+        (Frame::Bulk("b".into()), Frame::Boolean(true)),
+    ]);
+
+    assert_eq!(frame.to_response(), b"*4\r\n$1\r\na\r\n$3\r\n1.5\r\n$1\r\nb\r\n:1\r\n");
+    assert_eq!(frame.byte_len(), frame.to_response().len());
+
+    assert_eq!(Frame::Set(vec![Frame::Integer(1)]).to_response(), b"*1\r\n:1\r\n");
+    assert_eq!(Frame::Null.to_response(), b"$-1\r\n");
+    assert_eq!(Frame::Null.encode(Protocol::Resp3), b"_\r\n");
+    assert_eq!(
+        Frame::Verbatim("txt".to_string(), Bytes::from_static(b"hi")).to_response(),
+        b"$2\r\nhi\r\n",
+    );
+    assert_eq!(
+        Frame::Attributes(vec![], Box::new(Frame::Boolean(false))).to_response(),
+        b":0\r\n",
+    );
+}
+
+#[test]
+fn test_parse_inline() {
+    let mut buf = BytesMut::from(&b"SET key \"hello world\"\r\n\r\nGET key\nPI"[..]);
+    let mut decoder = Decoder::new();
+
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap().unwrap(),
+        Frame::Array(vec![
+            Frame::Bulk("SET".into()),
+            Frame::Bulk("key".into()),
+            Frame::Bulk("hello world".into()),
+        ]),
+    );
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap().unwrap(),
+        Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk("key".into())]),
+    );
+
+    assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+
+    buf.extend_from_slice(b"NG\r\n");
+    assert_eq!(
+        decoder.decode(&mut buf).unwrap(),
+        Some(Frame::Array(vec![Frame::Bulk("PING".into())])),
+    );
+    assert!(Frame::is_inline(b'P'));
+    assert!(!Frame::is_inline(b'*'));
+}
+
+#[test]
+fn test_parse_inline_quoting() {
+    let parse = |line: &[u8]| decode(line).map(Option::unwrap);
+
+    assert_eq!(
+        parse(b"ECHO \"a\\tb\\x41\\\"\" 'it\\'s' \"\"\n").unwrap(),
+        Frame::Array(vec![
+            Frame::Bulk("ECHO".into()),
+            Frame::Bulk("a\tbA\"".into()),
+            Frame::Bulk("it's".into()),
+            Frame::Bulk("".into()),
+        ]),
+    );
+    // no escapes inside single quotes besides \'
+    assert_eq!(
+        parse(b"'a\\nb'\n").unwrap(),
+        Frame::Array(vec![Frame::Bulk("a\\nb".into())]),
+    );
+    assert_eq!(decode(b"  \r\n").unwrap(), None);
+
+    for line in [&b"ECHO \"abc\n"[..], b"ECHO 'abc\n", b"ECHO \"a\"b\n"] {
+        assert!(matches!(parse(line), Err(FrameError::Other(_))));
+    }
+}
+
+#[test]
+fn test_signed_integer_round_trip() {
+    for (input, val) in [
+        (&b":-1\r\n"[..], -1),
+        (b":-2\r\n", -2),
+        (b":0\r\n", 0),
+        (b":9223372036854775807\r\n", i64::MAX),
+        (b":-9223372036854775808\r\n", i64::MIN),
+    ] {
+        let frame = make_frame(input);
+
+        assert_eq!(frame, Frame::Integer(val));
+        assert_eq!(frame.to_response(), input);
+        assert_eq!(frame.byte_len(), input.len());
+    }
+}
+
+#[test]
+fn test_null_array_round_trip() {
+    let input = b"*-1\r\n";
+    let frame = make_frame(input);
+
+    assert_eq!(frame, Frame::NullArray);
+    assert_eq!(frame.to_response(), input);
+    assert_eq!(frame.byte_len(), input.len());
+    assert_eq!(frame.encode(Protocol::Resp3), b"_\r\n");
+
+    // nested in an array, as EXEC and BLPOP replies can be
+    let input = b"*2\r\n*-1\r\n$-1\r\n";
+    let frame = make_frame(input);
+
+    assert_eq!(frame, Frame::Array(vec![Frame::NullArray, Frame::Null]));
+    assert_eq!(frame.to_response(), input);
+}
+
+#[test]
+fn test_parse_invalid_negative_length() {
+    let inputs: [&[u8]; 4] = [b"$-2\r\n", b"*-5\r\n", b"~-1\r\n", b"%-1\r\n"];
+
+    for input in inputs {
+        assert!(matches!(
+            decode(input),
+            Err(FrameError::Other(_))
+        ));
+    }
+}
+
+#[test]
+fn test_decoder_resumes_byte_by_byte() {
+    let input: &[u8] = b"*3\r\n$6\r\nhe\r\nlo\r\n%1\r\n+k\r\n*2\r\n:-7\r\n_\r\n=6\r\ntxt:ok\r\n+next\r\n";
+    let expected = Frame::Array(vec![
+        Frame::Bulk("he\r\nlo".into()),
+        Frame::Map(vec![(
+            Frame::Simple("k".to_string()),
+            Frame::Array(vec![Frame::Integer(-7), Frame::Null]),
+        )]),
+        Frame::Verbatim("txt".to_string(), "ok".into()),
+    ]);
+
+    let mut decoder = Decoder::new();
+    let mut buf = BytesMut::new();
+    let mut frames = vec![];
+
+    for &byte in input {
+        buf.extend_from_slice(&[byte]);
+
+        if let Some(frame) = decoder.decode(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+    }
+
+    assert_eq!(frames, vec![expected, Frame::Simple("next".to_string())]);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn test_find_crlf() {
+    let naive = |buf: &[u8], from: usize| {
+        (from..buf.len().saturating_sub(1)).find(|&i| &buf[i..i + 2] == b"\r\n")
+    };
+
+    let mut buf = vec![b'a'; 40];
+    for (i, j) in [(0, 1), (7, 8), (8, 9), (15, 16), (38, 39)] {
+        let mut buf = buf.clone();
+        buf[i] = b'\r';
+        buf[j] = b'\n';
+
+        for from in 0..buf.len() {
+            assert_eq!(decoder::find_crlf(&buf, from), naive(&buf, from), "crlf at {} from {}", i, from);
+        }
+    }
+
+    // lone \n and \r are not line ends
+    buf[3] = b'\n';
+    buf[9] = b'\r';
+    buf[20] = b'\r';
+    buf[21] = b'\n';
+    assert_eq!(decoder::find_crlf(&buf, 0), Some(20));
+    // 0x8a and 0x0b around the needle must not be taken for it
+    assert_eq!(decoder::find_byte(&[0x8a, 0x0b, 0x09, 0x0a], 0, b'\n'), Some(3));
+}
+
+// cargo test --release -- --ignored --nocapture test_decoder_throughput
+#[test]
+#[ignore]
+fn test_decoder_throughput() {
+    let set = b"*3\r\n$3\r\nSET\r\n$10\r\nkey:000001\r\n$64\r\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\r\n";
+    let pipeline: Vec<u8> = set.iter().copied().cycle().take(set.len() * 100_000).collect();
+    let big_bulk = [
+        &b"*2\r\n$4\r\nECHO\r\n$16777216\r\n"[..],
+        &vec![b'x'; 16 * 1024 * 1024],
+        b"\r\n",
+    ].concat();
+
+    for (name, input) in [("pipeline of 100k SETs", &pipeline), ("16MB bulk", &big_bulk)] {
+        let start = std::time::Instant::now();
+
+        // fed in 16KB reads, as the socket hands them over
+        let mut decoder = Decoder::new();
+        let mut buf = BytesMut::new();
+        let mut frames = 0;
+        for chunk in input.chunks(16 * 1024) {
+            buf.extend_from_slice(chunk);
+
+            while decoder.decode(&mut buf).unwrap().is_some() {
+                frames += 1;
+            }
+        }
+
+        let elapsed = start.elapsed();
+        println!(
+            "{}: {} frames, {:.1} MB/s",
+            name,
+            frames,
+            input.len() as f64 / elapsed.as_secs_f64() / 1024.0 / 1024.0,
+        );
+    }
+}
+
+#[test]
+fn test_decoder_limits() {
+    let limits = ProtoLimits {
+        max_bulk_len: 8,
+        max_multibulk_len: 2,
+        max_nesting: 2,
+        query_buffer_limit: 64,
+    };
+    let decode_limited = |input: &[u8]| {
+        let mut decoder = Decoder::new();
+        decoder.set_limits(limits.clone());
+
+        decoder.decode(&mut BytesMut::from(input))
+    };
+
+    assert!(decode_limited(b"$8\r\n12345678\r\n").is_ok());
+    assert!(decode_limited(b"*2\r\n*1\r\n:1\r\n:2\r\n").is_ok());
+
+    let rejected: [&[u8]; 6] = [
+        // the declared length is enough, the payload is never waited for
+        b"$9\r\n",
+        b"=9\r\n",
+        b"*3\r\n",
+        b"%3\r\n",
+        b"*1\r\n*1\r\n*1\r\n",
+        &[b'a'; 65],
+    ];
+    for input in rejected {
+        assert!(matches!(decode_limited(input), Err(FrameError::Other(_))), "{:?}", input);
+    }
+
+    // lines without an end in sight
+    let mut header = b"*".to_vec();
+    header.extend(vec![b'1'; 70 * 1024]);
+    assert!(matches!(decode(&header), Err(FrameError::Other(_))));
+    assert!(matches!(decode(&vec![b'a'; 70 * 1024]), Err(FrameError::Other(_))));
+}
+
+#[test]
+fn test_decoder_query_buffer_counts_consumed_elements() {
+    let mut decoder = Decoder::new();
+    decoder.set_limits(ProtoLimits {
+        query_buffer_limit: 32,
+        ..Default::default()
+    });
+
+    // elements already taken off the buffer still count toward the limit
+    let mut buf = BytesMut::from(&b"*4\r\n$8\r\n12345678\r\n$8\r\n12345678\r\n"[..]);
+    assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+    assert!(buf.is_empty());
+
+    buf.extend_from_slice(b"$8\r\n12345");
+    assert!(matches!(decoder.decode(&mut buf), Err(FrameError::Other(_))));
+}
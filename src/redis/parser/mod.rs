@@ -19,12 +19,14 @@ pub(crate) struct Parser<'a> {
 #[derive(Error, Debug, PartialEq)]
 pub enum ParserError {
     EndOfStream,
+    // arguments left after the command took what it needs
+    TrailingArgs,
 
     Other(String),
 }
 
 impl Parser<'_> {
-    pub fn new(frame: &Frame) -> Result<Parser, ParserError> {
+    pub fn new(frame: &Frame) -> Result<Parser<'_>, ParserError> {
         let frame_array = match frame {
             Frame::Array(array) => array.iter(),
             _ => return Err(
@@ -73,15 +75,23 @@ impl Parser<'_> {
         }
     }
 
+    // every argument has to be consumed
+    pub fn finish(&mut self) -> Result<(), ParserError> {
+        match self.next() {
+            Err(ParserError::EndOfStream) => Ok(()),
+            _ => Err(ParserError::TrailingArgs),
+        }
+    }
+
     pub fn next_int(&mut self) -> Result<u64, ParserError> {
         match self.next()? {
-            Frame::Integer(val) => Ok(val.to_owned()),
+            Frame::Integer(val) => Ok((*val).try_into().map_err(|_| ParserError::from(NOT_AN_INTEGER))?),
             Frame::Simple(val) => Ok(val.parse::<u64>()?),
             Frame::Bulk(val) => {
                 Ok(
-                    String::from_utf8(
-                        val.to_vec()
-                    ).unwrap().parse()?
+                    str::from_utf8(&val[..])
+                        .map_err(|_| ParserError::from(NOT_AN_INTEGER))?
+                        .parse()?
                 )
             }
             frame => {
@@ -93,6 +103,24 @@ impl Parser<'_> {
             }
         }
     }
+
+    // list indexes and counts, negative ones count from the tail
+    pub fn next_signed_int(&mut self) -> Result<i64, ParserError> {
+        let parsed = match self.next()? {
+            Frame::Integer(val) => return Ok(*val),
+            Frame::Simple(val) => val.parse().ok(),
+            Frame::Bulk(val) => str::from_utf8(&val[..]).ok().and_then(|val| val.parse().ok()),
+            frame => {
+                return Err(
+                    ParserError::Other(
+                        format!("protocol error; expected simple/bulk frame, got {:?}", frame)
+                    )
+                );
+            }
+        };
+
+        parsed.ok_or_else(|| NOT_AN_INTEGER.into())
+    }
 }
 
 impl From<String> for ParserError {
@@ -107,9 +135,11 @@ impl From<&str> for ParserError {
     }
 }
 
+pub(crate) const NOT_AN_INTEGER: &str = "value is not an integer or out of range";
+
 impl From<ParseIntError> for ParserError {
     fn from(_: ParseIntError) -> ParserError {
-        ParserError::Other("Unable to parse int".into())
+        NOT_AN_INTEGER.into()
     }
 }
 
@@ -117,6 +147,7 @@ impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParserError::EndOfStream => "protocol error; unexpected end of stream".fmt(f),
+            ParserError::TrailingArgs => "protocol error; unexpected arguments".fmt(f),
             ParserError::Other(err) => err.fmt(f),
         }
     }
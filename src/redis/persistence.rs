@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+use std::time::SystemTime;
+
+use tokio::task;
+use tokio::time::{self, Duration};
+
+use super::{
+    config::{Config, SavePoint},
+    db::Db,
+    rdb::RdbError,
+};
+
+#[derive(Clone)]
+pub(crate) struct Persistence {
+    pub path: PathBuf,
+    pub save_points: Vec<SavePoint>,
+
+    lastsave: Arc<Mutex<SystemTime>>,
+    // set while SAVE or BGSAVE writes the dump, one at a time
+    save_in_progress: Arc<AtomicBool>,
+}
+
+impl Persistence {
+    pub fn new(cfg: &Config) -> Persistence {
+        Persistence {
+            path: cfg.db_path(),
+            save_points: cfg.save.clone(),
+            lastsave: Arc::new(Mutex::new(SystemTime::now())),
+            save_in_progress: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // save the caller waits for, used by SAVE; file I/O runs on a blocking
+    // thread so the worker keeps serving the other clients
+    pub async fn save(&self, db: &Db) -> Result<(), RdbError> {
+        if self.save_in_progress.swap(true, Ordering::SeqCst) {
+            return Err("Background save already in progress".into());
+        }
+
+        let (rdb, dirty) = db.snapshot();
+        let path = self.path.clone();
+
+        let result = task::spawn_blocking(move || rdb.save(&path)).await
+            .unwrap_or_else(|e| Err(e.to_string().into()));
+        if result.is_ok() {
+            self.saved(db, dirty);
+        }
+
+        self.save_in_progress.store(false, Ordering::SeqCst);
+
+        result
+    }
+
+    // snapshot is taken right away, writing happens on a blocking thread;
+    // returns false if another save is still running
+    pub fn bgsave(&self, db: &Db) -> bool {
+        if self.save_in_progress.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        let (rdb, dirty) = db.snapshot();
+        let persistence = self.clone();
+        let db = db.clone();
+
+        task::spawn_blocking(move || {
+            match rdb.save(&persistence.path) {
+                Ok(_) => persistence.saved(&db, dirty),
+                Err(e) => eprintln!("Background saving error: {}", e),
+            }
+
+            persistence.save_in_progress.store(false, Ordering::SeqCst);
+        });
+
+        true
+    }
+
+    pub fn lastsave(&self) -> SystemTime {
+        *self.lastsave.lock().unwrap()
+    }
+
+    fn saved(&self, db: &Db, dirty: u64) {
+        db.saved(dirty);
+        *self.lastsave.lock().unwrap() = SystemTime::now();
+    }
+
+    fn should_save(&self, db: &Db) -> bool {
+        let dirty = db.dirty();
+        let elapsed = self.lastsave().elapsed().unwrap_or_default().as_secs();
+
+        self.save_points.iter().any(|point| {
+            dirty >= point.changes && elapsed >= point.seconds
+        })
+    }
+
+    // checks save points once a second and triggers BGSAVE when one is reached
+    pub async fn watch_save_points(self, db: Db) {
+        if self.save_points.is_empty() {
+            return;
+        }
+
+        let mut interval = time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+
+            if self.should_save(&db) {
+                self.bgsave(&db);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::redis::rdb::Rdb;
+
+    use super::*;
+
+    fn persistence(name: &str, save: Vec<SavePoint>) -> Persistence {
+        let cfg = Config {
+            dir: std::env::temp_dir().to_string_lossy().to_string(),
+            dbfilename: format!("{}-{}.rdb", name, std::process::id()),
+            save,
+            ..Default::default()
+        };
+
+        Persistence::new(&cfg)
+    }
+
+    #[tokio::test]
+    async fn test_save_roundtrip() {
+        let persistence = persistence("save-roundtrip", vec![]);
+        let mut db = Db::new();
+
+        db.set(Bytes::from_static(b"foo"), Bytes::from_static(b"bar"), None);
+        db.set(Bytes::from_static(b"ttl"), Bytes::from_static(b"baz"), Some(Duration::from_secs(60)));
+
+        persistence.save(&db).await.unwrap();
+        assert_eq!(db.dirty(), 0);
+
+        let mut restored = Db::new();
+        restored.load_rdb(Rdb::load(&persistence.path).unwrap().unwrap());
+
+        assert_eq!(restored.get(b"foo"), Ok(Some(Bytes::from_static(b"bar"))));
+        assert_eq!(restored.get(b"ttl"), Ok(Some(Bytes::from_static(b"baz"))));
+
+        std::fs::remove_file(&persistence.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_refused_during_bgsave() {
+        let persistence = persistence("save-during-bgsave", vec![]);
+        let db = Db::new();
+
+        persistence.save_in_progress.store(true, Ordering::SeqCst);
+        assert_eq!(
+            persistence.save(&db).await.unwrap_err().to_string(),
+            "Background save already in progress",
+        );
+        assert!(!persistence.bgsave(&db));
+        assert!(!persistence.path.exists());
+
+        persistence.save_in_progress.store(false, Ordering::SeqCst);
+        persistence.save(&db).await.unwrap();
+        assert!(persistence.bgsave(&db));
+
+        while persistence.save_in_progress.load(Ordering::SeqCst) {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        std::fs::remove_file(&persistence.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_point_triggers_bgsave() {
+        let persistence = persistence("save-point", vec![SavePoint { seconds: 0, changes: 1 }]);
+        let mut db = Db::new();
+
+        tokio::spawn(persistence.clone().watch_save_points(db.clone()));
+
+        db.set(Bytes::from_static(b"foo"), Bytes::from_static(b"bar"), None);
+
+        time::sleep(Duration::from_millis(1500)).await;
+
+        assert!(persistence.path.exists());
+        assert_eq!(db.dirty(), 0);
+
+        std::fs::remove_file(&persistence.path).unwrap();
+    }
+}
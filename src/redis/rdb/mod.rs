@@ -0,0 +1,544 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    fs,
+    io::{self, Cursor, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{Buf, Bytes};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::redis::db::{Hash, Value};
+
+use crc64::crc64;
+
+mod crc64;
+
+const MAGIC: &[u8] = b"REDIS";
+pub const RDB_VERSION: u16 = 11;
+// first version readers accept `TYPE_HASH_METADATA` in, as written by redis 7.4
+const RDB_VERSION_HASH_METADATA: u16 = 12;
+
+// opcodes
+const OP_IDLE: u8 = 0xF8;
+const OP_FREQ: u8 = 0xF9;
+const OP_AUX: u8 = 0xFA;
+const OP_RESIZEDB: u8 = 0xFB;
+const OP_EXPIRETIME_MS: u8 = 0xFC;
+const OP_EXPIRETIME: u8 = 0xFD;
+const OP_SELECTDB: u8 = 0xFE;
+const OP_EOF: u8 = 0xFF;
+
+// temp files written so far, see `Rdb::save`
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+// value types
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_HASH: u8 = 4;
+// hash with field TTLs, as written by redis 7.4
+const TYPE_HASH_METADATA: u8 = 24;
+
+// special string encodings, see `read_length`
+const ENC_INT8: u8 = 0;
+const ENC_INT16: u8 = 1;
+const ENC_INT32: u8 = 2;
+const ENC_LZF: u8 = 3;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Rdb {
+    pub version: u16,
+    pub aux: Vec<(String, Bytes)>,
+    pub entries: Vec<RdbEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RdbEntry {
+    pub key: Bytes,
+    pub value: Value,
+    pub expires_at: Option<SystemTime>,
+}
+
+#[derive(Error, Debug)]
+pub enum RdbError {
+    Incomplete,
+    Other(String),
+}
+
+enum Length {
+    Len(usize),
+    Encoded(u8),
+}
+
+impl Rdb {
+    pub fn load(path: &Path) -> Result<Option<Rdb>, RdbError> {
+        match fs::read(path) {
+            Ok(content) => Ok(Some(Rdb::parse(&content)?)),
+            // missing file means empty dataset
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(content: &[u8]) -> Result<Rdb, RdbError> {
+        let mut src = Cursor::new(content);
+        let mut rdb = Rdb {
+            version: read_header(&mut src)?,
+            ..Default::default()
+        };
+
+        let mut expires_at = None;
+
+        loop {
+            match get_u8(&mut src)? {
+                OP_AUX => {
+                    let key = String::from_utf8(read_string(&mut src)?.to_vec())?;
+                    let value = read_string(&mut src)?;
+
+                    rdb.aux.push((key, value));
+                }
+                // all databases are merged into a single keyspace
+                OP_SELECTDB => {
+                    read_len(&mut src)?;
+                }
+                OP_RESIZEDB => {
+                    read_len(&mut src)?;
+                    read_len(&mut src)?;
+                }
+                OP_EXPIRETIME_MS => {
+                    let millis = get_u64_le(&mut src)?;
+                    expires_at = Some(UNIX_EPOCH + Duration::from_millis(millis));
+                }
+                OP_EXPIRETIME => {
+                    let secs = get_u32_le(&mut src)?;
+                    expires_at = Some(UNIX_EPOCH + Duration::from_secs(secs as u64));
+                }
+                OP_FREQ => {
+                    get_u8(&mut src)?;
+                }
+                OP_IDLE => {
+                    read_len(&mut src)?;
+                }
+                OP_EOF => {
+                    // 8 bytes checksum, absent in files written before version 5
+                    if rdb.version >= 5 {
+                        let end = src.position() as usize;
+                        let checksum = get_u64_le(&mut src)?;
+
+                        // zero means the checksum was disabled by the writer
+                        if checksum != 0 && checksum != crc64(0, &content[..end]) {
+                            return Err("RDB checksum mismatch".into());
+                        }
+                    }
+                    break;
+                }
+                TYPE_STRING => {
+                    let key = read_string(&mut src)?;
+                    let value = Value::String(read_string(&mut src)?);
+
+                    rdb.entries.push(RdbEntry { key, value, expires_at: expires_at.take() });
+                }
+                TYPE_LIST => {
+                    let key = read_string(&mut src)?;
+                    let len = read_len(&mut src)?;
+
+                    let mut list = VecDeque::with_capacity(len.min(src.remaining()));
+                    for _ in 0..len {
+                        list.push_back(read_string(&mut src)?);
+                    }
+
+                    rdb.entries.push(RdbEntry { key, value: Value::List(list), expires_at: expires_at.take() });
+                }
+                TYPE_SET => {
+                    let key = read_string(&mut src)?;
+                    let len = read_len(&mut src)?;
+
+                    let mut set = HashSet::with_capacity(len.min(src.remaining()));
+                    for _ in 0..len {
+                        set.insert(read_string(&mut src)?);
+                    }
+
+                    rdb.entries.push(RdbEntry { key, value: Value::Set(set), expires_at: expires_at.take() });
+                }
+                TYPE_HASH => {
+                    let key = read_string(&mut src)?;
+                    let len = read_len(&mut src)?;
+
+                    let mut hash = Hash::new();
+                    for _ in 0..len {
+                        let field = read_string(&mut src)?;
+                        hash.insert(field, read_string(&mut src)?);
+                    }
+
+                    rdb.entries.push(RdbEntry { key, value: Value::Hash(hash), expires_at: expires_at.take() });
+                }
+                TYPE_HASH_METADATA => {
+                    let key = read_string(&mut src)?;
+                    let key_expires_at = expires_at.take();
+                    // field TTLs are stored relative to the earliest one
+                    let min_expire = get_u64_le(&mut src)?;
+                    let len = read_len(&mut src)?;
+
+                    let mut hash = Hash::new();
+                    for _ in 0..len {
+                        let ttl = read_len(&mut src)? as u64;
+                        let field = read_string(&mut src)?;
+                        let value = read_string(&mut src)?;
+
+                        if ttl == 0 {
+                            hash.insert(field, value);
+                            continue;
+                        }
+
+                        let millis = min_expire.saturating_add(ttl - 1);
+                        let at = UNIX_EPOCH + Duration::from_millis(millis);
+                        // fields that expired while the file was at rest are left out
+                        if let Ok(left) = at.duration_since(SystemTime::now()) {
+                            hash.insert(field.clone(), value);
+                            hash.set_expire(field, Instant::now() + left);
+                        }
+                    }
+
+                    // nothing left of it once every field expired
+                    if !hash.is_empty() {
+                        rdb.entries.push(RdbEntry { key, value: Value::Hash(hash), expires_at: key_expires_at });
+                    }
+                }
+                unknown => {
+                    return Err(
+                        format!("unsupported RDB value type or opcode `{:#04x}`", unknown).into()
+                    );
+                }
+            }
+        }
+
+        Ok(rdb)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), RdbError> {
+        // write to a temp file first so a crash never leaves a truncated dump behind,
+        // each save gets its own so two of them never write the same file
+        let seq = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+        let tmp_path = path.with_file_name(format!("temp-{}-{}.rdb", std::process::id(), seq));
+
+        let written = fs::File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(&self.to_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, path));
+
+        // a failed save leaves nothing behind, not even a partial temp file
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        Ok(written?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // field TTLs can't be written in an older format, the file is
+        // stamped with the version that introduced them instead
+        let field_ttls = self.entries.iter().any(|entry| {
+            matches!(&entry.value, Value::Hash(hash) if hash.has_expires())
+        });
+        let version = if field_ttls {
+            self.version.max(RDB_VERSION_HASH_METADATA)
+        } else {
+            self.version
+        };
+
+        let mut buff = MAGIC.to_vec();
+        buff.extend(format!("{:04}", version).as_bytes());
+
+        for (key, value) in &self.aux {
+            buff.push(OP_AUX);
+            write_string(&mut buff, key.as_bytes());
+            write_string(&mut buff, value);
+        }
+
+        buff.push(OP_SELECTDB);
+        write_length(&mut buff, 0);
+
+        let expires = self.entries.iter().filter(|entry| entry.expires_at.is_some()).count();
+        buff.push(OP_RESIZEDB);
+        write_length(&mut buff, self.entries.len());
+        write_length(&mut buff, expires);
+
+        for entry in &self.entries {
+            if let Some(at) = entry.expires_at {
+                let millis = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+
+                buff.push(OP_EXPIRETIME_MS);
+                buff.extend(millis.to_le_bytes());
+            }
+
+            write_value(&mut buff, &entry.key, &entry.value);
+        }
+
+        buff.push(OP_EOF);
+        let checksum = crc64(0, &buff);
+        buff.extend(checksum.to_le_bytes());
+
+        buff
+    }
+}
+
+// type byte, key and the value in its plain (not packed) encoding
+fn write_value(buff: &mut Vec<u8>, key: &[u8], value: &Value) {
+    match value {
+        Value::String(data) => {
+            buff.push(TYPE_STRING);
+            write_string(buff, key);
+            write_string(buff, data);
+        }
+        Value::List(list) => {
+            buff.push(TYPE_LIST);
+            write_string(buff, key);
+            write_length(buff, list.len());
+            for item in list {
+                write_string(buff, item);
+            }
+        }
+        Value::Set(set) => {
+            buff.push(TYPE_SET);
+            write_string(buff, key);
+            write_length(buff, set.len());
+            for member in set {
+                write_string(buff, member);
+            }
+        }
+        Value::Hash(hash) if hash.has_expires() => {
+            let now = Instant::now();
+            let wall_now = SystemTime::now();
+            let unix_millis = |at: Instant| {
+                (wall_now + at.saturating_duration_since(now))
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64
+            };
+
+            let min_expire = hash.expires().map(|(_, at)| unix_millis(at)).min().unwrap_or_default();
+
+            buff.push(TYPE_HASH_METADATA);
+            write_string(buff, key);
+            buff.extend(min_expire.to_le_bytes());
+            write_length(buff, hash.len());
+            for (field, value) in hash.iter_all() {
+                let ttl = hash.expires_at(field).map_or(0, |at| unix_millis(at) - min_expire + 1);
+
+                write_length(buff, ttl as usize);
+                write_string(buff, field);
+                write_string(buff, value);
+            }
+        }
+        Value::Hash(hash) => {
+            buff.push(TYPE_HASH);
+            write_string(buff, key);
+            write_length(buff, hash.len());
+            for (field, value) in hash.iter_all() {
+                write_string(buff, field);
+                write_string(buff, value);
+            }
+        }
+    }
+}
+
+fn write_length(buff: &mut Vec<u8>, len: usize) {
+    if len < 1 << 6 {
+        buff.push(len as u8);
+    } else if len < 1 << 14 {
+        buff.push(0x40 | (len >> 8) as u8);
+        buff.push(len as u8);
+    } else if len <= u32::MAX as usize {
+        buff.push(0x80);
+        buff.extend((len as u32).to_be_bytes());
+    } else {
+        buff.push(0x81);
+        buff.extend((len as u64).to_be_bytes());
+    }
+}
+
+fn write_string(buff: &mut Vec<u8>, string: &[u8]) {
+    write_length(buff, string.len());
+    buff.extend(string);
+}
+
+fn read_header(src: &mut Cursor<&[u8]>) -> Result<u16, RdbError> {
+    if take(src, MAGIC.len())? != MAGIC {
+        return Err("Wrong RDB file format".into());
+    }
+
+    let version = String::from_utf8(take(src, 4)?.to_vec())?;
+
+    version.parse().map_err(|_| {
+        format!("invalid RDB version `{}`", version).into()
+    })
+}
+
+fn read_length(src: &mut Cursor<&[u8]>) -> Result<Length, RdbError> {
+    let first = get_u8(src)?;
+
+    // two most significant bits define how the length is encoded
+    let length = match first >> 6 {
+        0b00 => Length::Len((first & 0x3F) as usize),
+        0b01 => {
+            let next = get_u8(src)?;
+            Length::Len((((first & 0x3F) as usize) << 8) | next as usize)
+        }
+        0b10 => match first {
+            0x80 => Length::Len(u32::from_be_bytes(take(src, 4)?.try_into().unwrap()) as usize),
+            0x81 => Length::Len(u64::from_be_bytes(take(src, 8)?.try_into().unwrap()) as usize),
+            _ => return Err(format!("invalid length encoding `{:#04x}`", first).into()),
+        },
+        _ => Length::Encoded(first & 0x3F),
+    };
+
+    Ok(length)
+}
+
+fn read_len(src: &mut Cursor<&[u8]>) -> Result<usize, RdbError> {
+    match read_length(src)? {
+        Length::Len(len) => Ok(len),
+        Length::Encoded(_) => Err("expected length, got encoded string".into()),
+    }
+}
+
+fn read_string(src: &mut Cursor<&[u8]>) -> Result<Bytes, RdbError> {
+    let string = match read_length(src)? {
+        Length::Len(len) => Bytes::copy_from_slice(take(src, len)?),
+        Length::Encoded(ENC_INT8) => {
+            (get_u8(src)? as i8).to_string().into()
+        }
+        Length::Encoded(ENC_INT16) => {
+            i16::from_le_bytes(take(src, 2)?.try_into().unwrap()).to_string().into()
+        }
+        Length::Encoded(ENC_INT32) => {
+            i32::from_le_bytes(take(src, 4)?.try_into().unwrap()).to_string().into()
+        }
+        Length::Encoded(ENC_LZF) => {
+            let compressed_len = read_len(src)?;
+            let len = read_len(src)?;
+
+            lzf_decompress(take(src, compressed_len)?, len)?.into()
+        }
+        Length::Encoded(unknown) => {
+            return Err(format!("unknown string encoding `{}`", unknown).into());
+        }
+    };
+
+    Ok(string)
+}
+
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>, RdbError> {
+    // `len` comes from the file, it is only trusted once the output matches it
+    let mut output = Vec::with_capacity(len.min(input.len()));
+    let mut i = 0;
+
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+
+        if ctrl < 32 {
+            // literal run of ctrl + 1 bytes
+            let end = i + ctrl + 1;
+            if end > input.len() {
+                return Err("corrupted LZF string".into());
+            }
+            output.extend_from_slice(&input[i..end]);
+            i = end;
+        } else {
+            // back reference
+            let mut run = ctrl >> 5;
+            if run == 7 {
+                run += *input.get(i).ok_or("corrupted LZF string")? as usize;
+                i += 1;
+            }
+            let offset = ((ctrl & 0x1F) << 8) + *input.get(i).ok_or("corrupted LZF string")? as usize + 1;
+            i += 1;
+
+            if offset > output.len() {
+                return Err("corrupted LZF string".into());
+            }
+
+            let start = output.len() - offset;
+            for k in 0..run + 2 {
+                output.push(output[start + k]);
+            }
+        }
+    }
+
+    if output.len() != len {
+        return Err("corrupted LZF string".into());
+    }
+
+    Ok(output)
+}
+
+fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, RdbError> {
+    if !src.has_remaining() {
+        return Err(RdbError::Incomplete);
+    }
+
+    Ok(src.get_u8())
+}
+
+fn get_u32_le(src: &mut Cursor<&[u8]>) -> Result<u32, RdbError> {
+    Ok(u32::from_le_bytes(take(src, 4)?.try_into().unwrap()))
+}
+
+fn get_u64_le(src: &mut Cursor<&[u8]>) -> Result<u64, RdbError> {
+    Ok(u64::from_le_bytes(take(src, 8)?.try_into().unwrap()))
+}
+
+fn take<'a>(src: &mut Cursor<&'a [u8]>, n: usize) -> Result<&'a [u8], RdbError> {
+    if src.remaining() < n {
+        return Err(RdbError::Incomplete);
+    }
+
+    let start = src.position() as usize;
+    src.advance(n);
+
+    Ok(&src.get_ref()[start..start + n])
+}
+
+impl From<String> for RdbError {
+    fn from(src: String) -> RdbError {
+        RdbError::Other(src)
+    }
+}
+
+impl From<&str> for RdbError {
+    fn from(src: &str) -> RdbError {
+        src.to_string().into()
+    }
+}
+
+impl From<std::string::FromUtf8Error> for RdbError {
+    fn from(_src: std::string::FromUtf8Error) -> RdbError {
+        "invalid RDB string".into()
+    }
+}
+
+impl From<io::Error> for RdbError {
+    fn from(value: io::Error) -> RdbError {
+        RdbError::Other(format!("{:?}", value))
+    }
+}
+
+impl fmt::Display for RdbError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RdbError::Incomplete => "unexpected end of RDB file".fmt(fmt),
+            RdbError::Other(err) => err.fmt(fmt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,362 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::prelude::*;
+use bytes::Bytes;
+
+use crate::redis::db::{Db, Hash, Value, WrongType};
+
+use super::*;
+
+fn header() -> Vec<u8> {
+    let mut buff = b"REDIS0011".to_vec();
+    // aux field redis-ver
+    buff.push(0xFA);
+    buff.push(9);
+    buff.extend(b"redis-ver");
+    buff.push(5);
+    buff.extend(b"7.2.0");
+    // SELECTDB 0, RESIZEDB 3 1
+    buff.extend([0xFE, 0x00, 0xFB, 0x03, 0x01]);
+
+    buff
+}
+
+fn eof(buff: &mut Vec<u8>) {
+    buff.push(0xFF);
+    buff.extend([0; 8]);
+}
+
+fn string_entry(buff: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    buff.push(0x00);
+    buff.push(key.len() as u8);
+    buff.extend(key);
+    buff.push(value.len() as u8);
+    buff.extend(value);
+}
+
+// empty dump produced by redis 7.2.0
+const EMPTY_RDB: &str = "UkVESVMwMDEx+glyZWRpcy12ZXIFNy4yLjD6CnJlZGlzLWJpdHPAQPoFY3RpbWXCbQi8ZfoIdXNlZC1tZW3CsMQQAPoIYW9mLWJhc2XAAP/wbjv+wP9aog==";
+
+#[test]
+fn test_parse_empty_rdb() {
+    let content = BASE64_STANDARD.decode(EMPTY_RDB).unwrap();
+
+    let rdb = Rdb::parse(&content).unwrap();
+
+    assert_eq!(rdb.version, 11);
+    assert!(rdb.entries.is_empty());
+    assert!(rdb.aux.contains(&(String::from("redis-ver"), Bytes::from_static(b"7.2.0"))));
+}
+
+#[test]
+fn test_parse_strings_and_expiries() {
+    let mut input = header();
+    string_entry(&mut input, b"foo", b"bar");
+
+    input.push(0xFC);
+    input.extend(1_000u64.to_le_bytes());
+    string_entry(&mut input, b"old", b"x");
+
+    input.push(0xFD);
+    input.extend(4_000_000_000u32.to_le_bytes());
+    string_entry(&mut input, b"fresh", b"y");
+
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+
+    assert_eq!(
+        rdb.entries,
+        vec![
+            RdbEntry {
+                key: Bytes::from_static(b"foo"),
+                value: Value::String(Bytes::from_static(b"bar")),
+                expires_at: None,
+            },
+            RdbEntry {
+                key: Bytes::from_static(b"old"),
+                value: Value::String(Bytes::from_static(b"x")),
+                expires_at: Some(UNIX_EPOCH + Duration::from_millis(1_000)),
+            },
+            RdbEntry {
+                key: Bytes::from_static(b"fresh"),
+                value: Value::String(Bytes::from_static(b"y")),
+                expires_at: Some(UNIX_EPOCH + Duration::from_secs(4_000_000_000)),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_encoded_strings() {
+    let mut input = header();
+
+    // int8
+    input.extend([0x00, 0x01, b'a', 0xC0, 0x7B]);
+    // int16
+    input.extend([0x00, 0x01, b'b', 0xC1, 0x39, 0x30]);
+    // int32
+    input.extend([0x00, 0x01, b'c']);
+    input.push(0xC2);
+    input.extend((-70_000i32).to_le_bytes());
+    // lzf compressed "aaaaaaaaaa"
+    input.extend([0x00, 0x01, b'd', 0xC3, 0x05, 0x0A, 0x00, b'a', 0xE0, 0x00, 0x00]);
+
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+    let values: Vec<Value> = rdb.entries.into_iter().map(|entry| entry.value).collect();
+
+    assert_eq!(
+        values,
+        vec![
+            Value::String(Bytes::from_static(b"123")),
+            Value::String(Bytes::from_static(b"12345")),
+            Value::String(Bytes::from_static(b"-70000")),
+            Value::String(Bytes::from_static(b"aaaaaaaaaa")),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_lzf_length_out_of_range() {
+    let mut input = header();
+
+    // lzf compressed "aaaaaaaaaa" claiming to expand to u64::MAX bytes
+    input.extend([0x00, 0x01, b'd', 0xC3, 0x05, 0x81]);
+    input.extend(u64::MAX.to_be_bytes());
+    input.extend([0x00, b'a', 0xE0, 0x00, 0x00]);
+    eof(&mut input);
+
+    assert!(matches!(Rdb::parse(&input), Err(RdbError::Other(_))));
+}
+
+#[tokio::test]
+async fn test_snapshot_roundtrip() {
+    let mut db = Db::new();
+    db.set(Bytes::from_static(b"foo"), Bytes::from_static(b"bar"), None);
+    db.set(Bytes::from_static(b"ttl"), Bytes::from_static(b"baz"), Some(Duration::from_secs(60)));
+
+    let rdb = Rdb::parse(&db.build_rdb_frame()).unwrap();
+
+    let mut restored = Db::new();
+    restored.load_rdb(rdb);
+
+    assert_eq!(restored.get(b"foo"), Ok(Some(Bytes::from_static(b"bar"))));
+    assert_eq!(restored.get(b"ttl"), Ok(Some(Bytes::from_static(b"baz"))));
+}
+
+#[tokio::test]
+async fn test_snapshot_roundtrip_binary_key() {
+    let mut db = Db::new();
+    db.set(Bytes::from_static(b"\xde\xad\xbe\xef"), Bytes::from_static(b"bar"), None);
+
+    let mut restored = Db::new();
+    restored.load_rdb(Rdb::parse(&db.build_rdb_frame()).unwrap());
+
+    assert_eq!(restored.get(b"\xde\xad\xbe\xef"), Ok(Some(Bytes::from_static(b"bar"))));
+}
+
+#[test]
+fn test_parse_checksum_mismatch() {
+    let mut input = header();
+    string_entry(&mut input, b"foo", b"bar");
+    input.push(0xFF);
+    input.extend(1u64.to_le_bytes());
+
+    assert!(Rdb::parse(&input).is_err());
+}
+
+#[test]
+fn test_parse_truncated() {
+    let mut input = header();
+    string_entry(&mut input, b"foo", b"bar");
+
+    assert!(matches!(Rdb::parse(&input), Err(RdbError::Incomplete)));
+}
+
+#[test]
+fn test_parse_wrong_magic() {
+    assert!(Rdb::parse(b"RDB0011\xFF").is_err());
+}
+
+#[tokio::test]
+async fn test_load_rdb_skips_expired() {
+    let mut db = Db::new();
+
+    let rdb = Rdb {
+        entries: vec![
+            RdbEntry {
+                key: Bytes::from_static(b"old"),
+                value: Value::String(Bytes::from_static(b"x")),
+                expires_at: Some(SystemTime::now() - Duration::from_secs(1)),
+            },
+            RdbEntry {
+                key: Bytes::from_static(b"fresh"),
+                value: Value::String(Bytes::from_static(b"y")),
+                expires_at: Some(SystemTime::now() + Duration::from_secs(60)),
+            },
+        ],
+        ..Default::default()
+    };
+
+    db.load_rdb(rdb);
+
+    assert_eq!(db.get(b"old"), Ok(None));
+    assert_eq!(db.get(b"fresh"), Ok(Some(Bytes::from_static(b"y"))));
+}
+
+#[test]
+fn test_load_missing_file() {
+    let path = std::env::temp_dir().join("no-such-dump.rdb");
+
+    assert!(Rdb::load(&path).unwrap().is_none());
+}
+
+#[test]
+fn test_failed_save_removes_temp_file() {
+    let dir = std::env::temp_dir().join(format!("failed-save-{}", std::process::id()));
+    // a non-empty directory in the way of the dump makes the rename fail
+    let path = dir.join("dump.rdb");
+    std::fs::create_dir_all(path.join("taken")).unwrap();
+
+    let rdb = Rdb::parse(&BASE64_STANDARD.decode(EMPTY_RDB).unwrap()).unwrap();
+    assert!(rdb.save(&path).is_err());
+
+    let left: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(left, vec!["dump.rdb"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_snapshot_roundtrip_list() {
+    let db = Db::new();
+    let list: VecDeque<Bytes> = [&b"a"[..], b"bb", b""].into_iter().map(Bytes::from_static).collect();
+    db.with_keyspace(|keyspace| keyspace.insert(Bytes::from_static(b"list"), Value::List(list.clone())));
+
+    let content = db.build_rdb_frame();
+    let rdb = Rdb::parse(&content).unwrap();
+    assert_eq!(rdb.version, RDB_VERSION);
+    assert_eq!(rdb.entries[0].value, Value::List(list.clone()));
+
+    let restored = Db::new();
+    let mut restored_mut = restored.clone();
+    restored_mut.load_rdb(rdb);
+
+    assert_eq!(
+        restored.with_keyspace(|keyspace| keyspace.get(b"list").cloned()),
+        Some(Value::List(list)),
+    );
+    assert_eq!(restored.get(b"list"), Err(WrongType));
+}
+
+#[test]
+fn test_parse_list() {
+    let mut input = header();
+    // type 1: key, element count, elements
+    input.extend([0x01, 0x01, b'l', 0x02, 0x01, b'x', 0xC0, 0x07]);
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+
+    assert_eq!(
+        rdb.entries[0].value,
+        Value::List(VecDeque::from([Bytes::from_static(b"x"), Bytes::from_static(b"7")])),
+    );
+}
+
+#[test]
+fn test_parse_set() {
+    let mut input = header();
+    // type 2: key, member count, members
+    input.extend([0x02, 0x01, b's', 0x02, 0x01, b'x', 0xC0, 0x07]);
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+
+    assert_eq!(
+        rdb.entries[0].value,
+        Value::Set(HashSet::from([Bytes::from_static(b"x"), Bytes::from_static(b"7")])),
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_roundtrip_set() {
+    let db = Db::new();
+    let set: HashSet<Bytes> = [&b"a"[..], b"bb", b""].into_iter().map(Bytes::from_static).collect();
+    db.with_keyspace(|keyspace| keyspace.insert(Bytes::from_static(b"set"), Value::Set(set.clone())));
+
+    let rdb = Rdb::parse(&db.build_rdb_frame()).unwrap();
+
+    assert_eq!(rdb.entries[0].value, Value::Set(set));
+}
+
+#[tokio::test]
+async fn test_parse_hash() {
+    let mut input = header();
+    // type 4: key, pair count, field and value of each pair
+    input.extend([0x04, 0x01, b'h', 0x02, 0x01, b'a', 0x01, b'1', 0x01, b'b', 0xC0, 0x02]);
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+
+    assert_eq!(
+        rdb.entries[0].value,
+        Value::Hash(Hash::from_iter([
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"b"), Bytes::from_static(b"2")),
+        ])),
+    );
+
+    let mut db = Db::new();
+    db.load_rdb(rdb);
+    let rdb = Rdb::parse(&db.build_rdb_frame()).unwrap();
+    assert!(matches!(&rdb.entries[0].value, Value::Hash(hash) if hash.len() == 2));
+}
+
+#[tokio::test]
+async fn test_snapshot_roundtrip_hash_field_expiries() {
+    let db = Db::new();
+    let mut hash = Hash::from_iter([
+        (Bytes::from_static(b"kept"), Bytes::from_static(b"1")),
+        (Bytes::from_static(b"ttl"), Bytes::from_static(b"2")),
+    ]);
+    let at = Instant::now() + Duration::from_secs(100);
+    hash.set_expire(Bytes::from_static(b"ttl"), at);
+    db.with_keyspace(|keyspace| keyspace.insert(Bytes::from_static(b"h"), Value::Hash(hash)));
+
+    let content = db.build_rdb_frame();
+    let rdb = Rdb::parse(&content).unwrap();
+    // v11 readers reject the type these hashes are written with
+    assert_eq!(rdb.version, 12);
+
+    let restored = match &rdb.entries[0].value {
+        Value::Hash(hash) => hash.clone(),
+        value => panic!("not a hash: {:?}", value),
+    };
+    assert_eq!(restored.len(), 2);
+    assert_eq!(restored.expires_at(b"kept"), None);
+    // deadlines go through unix milliseconds
+    let restored_at = restored.expires_at(b"ttl").unwrap();
+    assert!(restored_at.max(at) - restored_at.min(at) < Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_parse_hash_drops_expired_fields() {
+    let mut input = header();
+    // type 24: key, minimum deadline, pair count, then ttl, field and value of each pair
+    input.extend([0x18, 0x01, b'h']);
+    input.extend(1000u64.to_le_bytes());
+    input.extend([0x02, 0x01, 0x01, b'a', 0x01, b'1', 0x00, 0x01, b'b', 0x01, b'2']);
+    eof(&mut input);
+
+    let rdb = Rdb::parse(&input).unwrap();
+
+    // `a` expired in 1970, `b` has no TTL
+    assert_eq!(
+        rdb.entries[0].value,
+        Value::Hash(Hash::from_iter([(Bytes::from_static(b"b"), Bytes::from_static(b"2"))])),
+    );
+}
@@ -1,67 +1,282 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
 
 use anyhow::{bail, Result};
+use bytes::Bytes;
 use tokio::net::TcpStream;
 use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant, MissedTickBehavior};
 
+use crate::redis::backlog::Backlog;
+use crate::redis::db::{Db, ExpireHook};
 use crate::redis::frame::Frame;
+use crate::redis::rdb::Rdb;
+use crate::redis::replicas::Replicas;
 
 use super::{
     cmd::{
         ClientCmd,
+        Del,
+        Hdel,
         Ping,
         Psync,
         replconf::{Replconf, ReplconfParam},
     },
-    connection::Connection,
+    connection::{Connection, Handler},
+    Role,
     ServerInfo,
-    utils::{Addr, Named},
+    utils::{Addr, Named, random_id},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum ReplicationMsg {
     Propagate(Frame),
-    Wait(u64),
+    // ask replicas for their offset on behalf of WAIT
+    Getack,
+}
+
+impl ReplicationMsg {
+    // bytes the message takes in a replica output buffer
+    pub(crate) fn byte_len(&self) -> usize {
+        match self {
+            ReplicationMsg::Propagate(frame) => frame.byte_len(),
+            ReplicationMsg::Getack => Replconf::getack().to_frame().byte_len(),
+        }
+    }
+}
+
+// delays between attempts to reach a lost master
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+pub(crate) struct LinkState {
+    pub up: bool,
+    pub sync_in_progress: bool,
+    // last time anything was received from the master
+    pub last_io: Option<Instant>,
+    // replication id and offset are valid for a partial resync
+    pub synced: bool,
 }
 
 #[derive(Clone)]
 pub(crate) struct Replinfo {
-    pub id: String,
-    pub offset: Arc<Mutex<i64>>,
-    // number of connected replicas
-    pub count: Arc<RwLock<i8>>,
-    pub master: Option<Addr>,
-
-    pub wait_lock: Arc<Mutex<bool>>,
-    pub repl_completed: Arc<RwLock<i8>>,
-    pub pending_commands: Arc<RwLock<bool>>,
+    pub id: Arc<RwLock<String>>,
+    // previous replication id and the first offset not covered by it,
+    // lets replicas of the former master partially resync after a promotion
+    pub second_id: Arc<RwLock<Option<(String, u64)>>>,
+    // propagated stream on a master, stream received from the master on a replica
+    pub backlog: Arc<StdMutex<Backlog>>,
+    // output buffers of attached replicas
+    pub replicas: Replicas,
+    pub master: Arc<StdMutex<Option<Addr>>>,
+    // state of the link to the master on a replica
+    pub link: Arc<StdMutex<LinkState>>,
+    pub link_task: Arc<StdMutex<Option<JoinHandle<()>>>>,
+
+    // held while applying and propagating a write, and while a replica
+    // takes its snapshot, so every write lands either in the RDB or in the stream
+    pub sync_lock: Arc<Mutex<()>>,
+
+    // see `Config::repl_timeout` and `Config::repl_ping_period`
+    pub timeout: Duration,
+    pub ping_period: Duration,
 }
 
 impl Replinfo {
-    pub(crate) async fn add_replica(&mut self) {
-        let mut count = self.count.write().await;
-        *count += 1
+    pub(crate) fn offset(&self) -> u64 {
+        self.backlog.lock().unwrap().offset()
+    }
+
+    // append to the replication stream; the backlog stays locked while
+    // replicas are fed, so both see messages in the same order
+    pub(crate) fn propagate(&self, msg: ReplicationMsg) {
+        let mut backlog = self.backlog.lock().unwrap();
+
+        let frame = match &msg {
+            ReplicationMsg::Propagate(frame) => frame.clone(),
+            ReplicationMsg::Getack => Replconf::getack().to_frame(),
+        };
+        backlog.push(&frame.to_response());
+
+        self.replicas.propagate(msg);
+    }
+
+    pub(crate) fn touch_link(&self) {
+        self.link.lock().unwrap().last_io = Some(Instant::now());
+    }
+
+    // when the link to the master is considered dead unless something arrives
+    pub(crate) fn link_deadline(&self) -> Instant {
+        self.link.lock().unwrap().last_io.unwrap_or_else(Instant::now) + self.timeout
+    }
+
+    pub(crate) fn master(&self) -> Option<Addr> {
+        self.master.lock().unwrap().clone()
+    }
+
+    // new history starts here, the old one stays valid up to the current offset
+    pub(crate) async fn shift_id(&self, new_id: String) {
+        let mut id = self.id.write().await;
+        let old_id = std::mem::replace(&mut *id, new_id);
+
+        *self.second_id.write().await = Some((old_id, self.offset() + 1));
+    }
+}
+
+// keys and hash fields expired on a master are deleted on its replicas through the stream
+pub(crate) fn expire_hook(replinfo: &Replinfo) -> ExpireHook {
+    let replinfo = replinfo.clone();
+
+    ExpireHook {
+        lock: replinfo.sync_lock.clone(),
+        expired: Box::new(move |key, field| {
+            let key = Bytes::copy_from_slice(key);
+            let frame = match field {
+                None => Del::new(vec![key]).to_frame(),
+                Some(field) => Hdel::new(key, vec![Bytes::copy_from_slice(field)]).to_frame(),
+            };
+            replinfo.propagate(ReplicationMsg::Propagate(frame));
+        }),
+    }
+}
+
+// PINGs go through the replication stream like any write, replicas
+// seeing nothing for `repl-timeout` know the master is gone
+pub(crate) async fn ping_replicas(server_info: ServerInfo) {
+    let replinfo = &server_info.replinfo;
+
+    let mut interval = time::interval_at(Instant::now() + replinfo.ping_period, replinfo.ping_period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if !server_info.is_master() || replinfo.replicas.list().is_empty() {
+            continue;
+        }
+
+        let _guard = replinfo.sync_lock.lock().await;
+        replinfo.propagate(ReplicationMsg::Propagate(Ping::new(None).to_frame()));
+    }
+}
+
+pub(crate) fn start_link(server_info: &ServerInfo, db: &Db) {
+    let task = tokio::spawn(replication_link(server_info.clone(), db.clone()));
+
+    if let Some(previous) = server_info.replinfo.link_task.lock().unwrap().replace(task) {
+        previous.abort();
+    }
+}
+
+fn stop_link(server_info: &ServerInfo) {
+    if let Some(task) = server_info.replinfo.link_task.lock().unwrap().take() {
+        task.abort();
     }
+}
+
+// REPLICAOF host port
+pub(crate) fn replicate(
+    server_info: &ServerInfo,
+    db: &mut Db,
+    master_addr: Addr,
+) {
+    stop_link(server_info);
+
+    let was_master = server_info.is_master();
 
-    pub(crate) async fn drop_replica(&mut self) {
-        let mut count = self.count.write().await;
-        *count -= 1
+    *server_info.role.write().unwrap() = Role::Slave;
+    db.set_active_expire(false);
+    *server_info.replinfo.master.lock().unwrap() = Some(master_addr);
+
+    {
+        // a former master has no cached master to continue from
+        let mut link = server_info.replinfo.link.lock().unwrap();
+        *link = LinkState { synced: link.synced && !was_master, ..Default::default() };
     }
-    
-    pub(crate) async fn has_pending(&self) -> bool {
-        let pending = self.pending_commands.read().await;
 
-        *pending
+    if was_master {
+        db.flush();
+        server_info.replinfo.replicas.disconnect_all();
     }
 
-    // pub(crate) fn blocking_drop_replica(&mut self) {
-    //     let mut count = self.count.blocking_write();
-    //     *count -= 1
-    // }
+    start_link(server_info, db);
 }
 
-pub async fn handshake(slave_info: &ServerInfo, master_addr: &Addr) -> Result<Connection> {
-    let socket = TcpStream::connect(master_addr.to_string()).await?;
+// REPLICAOF NO ONE
+pub(crate) async fn promote(server_info: &ServerInfo, db: &Db) {
+    stop_link(server_info);
+
+    if server_info.is_master() {
+        return;
+    }
+
+    db.set_active_expire(true);
+
+    *server_info.role.write().unwrap() = Role::Master;
+    *server_info.replinfo.master.lock().unwrap() = None;
+    *server_info.replinfo.link.lock().unwrap() = LinkState::default();
+
+    server_info.replinfo.shift_id(random_id()).await;
+}
+
+// keeps the replica attached to its master, reconnecting with exponential backoff
+async fn replication_link(
+    server_info: ServerInfo,
+    mut db: Db,
+) {
+    let master_addr = match server_info.replinfo.master() {
+        Some(addr) => addr,
+        None => return,
+    };
+
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        server_info.replinfo.link.lock().unwrap().sync_in_progress = true;
+
+        match handshake(&server_info, &master_addr, &mut db).await {
+            Ok(conn) => {
+                {
+                    let mut link = server_info.replinfo.link.lock().unwrap();
+                    link.up = true;
+                    link.synced = true;
+                    link.sync_in_progress = false;
+                    link.last_io = Some(Instant::now());
+                }
+                backoff = MIN_BACKOFF;
+
+                let mut handler = Handler::new(conn, db.clone(), server_info.clone());
+                if let Err(e) = handler.handle_connection().await {
+                    eprintln!("Error while handling master connection: {}", e);
+                }
+
+                eprintln!("Connection with master lost");
+            }
+            Err(e) => eprintln!("Error while connecting to master {}: {}", master_addr, e),
+        }
+
+        {
+            let mut link = server_info.replinfo.link.lock().unwrap();
+            link.up = false;
+            link.sync_in_progress = false;
+        }
+
+        time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+pub async fn handshake(
+    slave_info: &ServerInfo,
+    master_addr: &Addr,
+    db: &mut Db,
+) -> Result<Connection> {
+    let socket = time::timeout(
+        CONNECT_TIMEOUT,
+        TcpStream::connect(master_addr.to_string()),
+    ).await??;
     let mut conn = Connection::new(socket);
     conn.is_repl_conn = true;
 
@@ -80,16 +295,73 @@ pub async fn handshake(slave_info: &ServerInfo, master_addr: &Addr) -> Result<Co
         &mut conn,
     ).await?;
 
-    sequence_step(
-        &Psync::default(),
-        &mut conn,
-    ).await?;
+    // prefer continuing from where the previous link stopped
+    let synced = slave_info.replinfo.link.lock().unwrap().synced;
+    let cached = if synced {
+        Some((slave_info.replinfo.id.read().await.clone(), slave_info.replinfo.offset()))
+    } else {
+        None
+    };
 
-    let _ = conn.read_rdb().await;
+    psync(slave_info, &mut conn, db, cached).await?;
 
     Ok(conn)
 }
 
+// `cached` is the replication id and offset of a previous sync, if any
+async fn psync(
+    slave_info: &ServerInfo,
+    conn: &mut Connection,
+    db: &mut Db,
+    cached: Option<(String, u64)>,
+) -> Result<()> {
+    let psync = match cached {
+        Some((replication_id, offset)) => Psync { replication_id, offset: offset as i64 + 1 },
+        None => Psync::default(),
+    };
+
+    conn.write_frame(&psync.to_frame()).await?;
+
+    let response = match conn.read_frame().await? {
+        Some(Frame::Simple(response)) => response,
+        Some(frame) => bail!(format!("Unexpected response to PSYNC: {:?}", frame)),
+        None => bail!(format!("No response from master to {}", Psync::NAME)),
+    };
+
+    let parts: Vec<&str> = response.split_whitespace().collect();
+
+    match parts[..] {
+        ["FULLRESYNC", id, offset] => {
+            let offset = offset.parse::<u64>()?;
+
+            match conn.read_rdb().await? {
+                Some(rdb) => {
+                    db.flush();
+                    db.load_rdb(Rdb::parse(&rdb)?);
+                }
+                None => bail!("No RDB from master"),
+            }
+
+            *slave_info.replinfo.id.write().await = id.to_string();
+            *slave_info.replinfo.second_id.write().await = None;
+            slave_info.replinfo.backlog.lock().unwrap().reset(offset);
+
+            // our own replicas hold a dataset that no longer exists
+            slave_info.replinfo.replicas.disconnect_all();
+        }
+        ["CONTINUE"] => {}
+        // master may have switched to a new replication id
+        ["CONTINUE", id] => {
+            if *slave_info.replinfo.id.read().await != id {
+                slave_info.replinfo.shift_id(id.to_string()).await;
+            }
+        }
+        _ => bail!(format!("Unexpected response to PSYNC: {}", response)),
+    }
+
+    Ok(())
+}
+
 async fn sequence_step(cmd: &(impl ClientCmd + Named), conn: &mut Connection) -> Result<()> {
     let cmd_as_frame = cmd.to_frame();
 
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Mutex,
+};
+
+use anyhow::{bail, Result};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+
+use super::config::OutputBufferLimit;
+use super::replica::ReplicationMsg;
+
+// output buffers of the replicas attached to this server
+#[derive(Clone)]
+pub(crate) struct Replicas {
+    inner: Arc<Mutex<Inner>>,
+    limit: OutputBufferLimit,
+    // woken on every ACK, used by WAIT
+    acked: Arc<Notify>,
+}
+
+struct Inner {
+    next_id: u64,
+    replicas: HashMap<u64, ReplicaHandle>,
+}
+
+struct ReplicaHandle {
+    tx: UnboundedSender<ReplicationMsg>,
+    state: Arc<BufferState>,
+    // since when the soft limit is exceeded
+    soft_since: Option<Instant>,
+    info: ReplicaInfo,
+}
+
+// what the master knows about one of its replicas
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ReplicaInfo {
+    pub ip: String,
+    // announced with REPLCONF listening-port
+    pub listening_port: Option<u16>,
+    // announced with REPLCONF capa
+    pub capa: Vec<String>,
+    pub state: ReplicaState,
+    // last offset confirmed with REPLCONF ACK
+    pub ack_offset: u64,
+    pub last_ack: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum ReplicaState {
+    // RDB or backlog tail is being sent
+    #[default]
+    Sync,
+    Online,
+}
+
+#[derive(Default)]
+struct BufferState {
+    // bytes queued and not yet written to the socket
+    queued: AtomicUsize,
+    overrun: AtomicBool,
+    closed: AtomicBool,
+}
+
+// receiving side of a replica output buffer, owned by its connection handler
+pub(crate) struct ReplicaStream {
+    pub id: u64,
+    rx: UnboundedReceiver<ReplicationMsg>,
+    state: Arc<BufferState>,
+}
+
+impl Replicas {
+    pub fn new(limit: OutputBufferLimit) -> Replicas {
+        Replicas {
+            inner: Arc::new(Mutex::new(Inner {
+                next_id: 0,
+                replicas: HashMap::new(),
+            })),
+            limit,
+            acked: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn register(&self, info: ReplicaInfo) -> ReplicaStream {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let state = Arc::new(BufferState::default());
+
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+
+        inner.replicas.insert(id, ReplicaHandle { tx, state: state.clone(), soft_since: None, info });
+
+        ReplicaStream { id, rx, state }
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.inner.lock().unwrap().replicas.remove(&id);
+    }
+
+    // registered replicas in the order they attached
+    pub fn list(&self) -> Vec<ReplicaInfo> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut ids: Vec<&u64> = inner.replicas.keys().collect();
+        ids.sort();
+
+        ids.into_iter().map(|id| inner.replicas[id].info.clone()).collect()
+    }
+
+    pub fn set_state(&self, id: u64, state: ReplicaState) {
+        if let Some(replica) = self.inner.lock().unwrap().replicas.get_mut(&id) {
+            replica.info.state = state;
+        }
+    }
+
+    pub fn ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.inner.lock().unwrap().replicas.get_mut(&id) {
+            // acks never go backwards, a stale one may arrive after a newer one
+            replica.info.ack_offset = replica.info.ack_offset.max(offset);
+            replica.info.last_ack = Some(Instant::now());
+        }
+
+        self.acked.notify_waiters();
+    }
+
+    // online replicas that acknowledged at least `offset`
+    pub fn count_acked(&self, offset: u64) -> usize {
+        self.inner.lock().unwrap().replicas.values()
+            .filter(|replica| {
+                replica.info.state == ReplicaState::Online && replica.info.ack_offset >= offset
+            })
+            .count()
+    }
+
+    // waits until `numreplicas` replicas acknowledged `offset` or the deadline passes,
+    // returns how many did
+    pub async fn wait_acked(&self, offset: u64, numreplicas: usize, deadline: Option<Instant>) -> usize {
+        loop {
+            // register for wakeups before counting, so no ack slips in between
+            let notified = self.acked.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let acked = self.count_acked(offset);
+            if acked >= numreplicas {
+                return acked;
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if time::timeout_at(deadline, notified).await.is_err() {
+                        return self.count_acked(offset);
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    // queue a message for every replica, dropping those over their output buffer limits
+    pub fn propagate(&self, msg: ReplicationMsg) {
+        let size = msg.byte_len();
+        let now = Instant::now();
+
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.replicas.retain(|id, replica| {
+            let queued = replica.state.queued.fetch_add(size, Ordering::SeqCst) + size;
+
+            if self.limit.is_exceeded(queued, &mut replica.soft_since, now) {
+                eprintln!("Replica {} is over its output buffer limit ({} bytes), disconnecting", id, queued);
+                replica.state.overrun.store(true, Ordering::SeqCst);
+                return false;
+            }
+
+            replica.tx.send(msg.clone()).is_ok()
+        });
+    }
+
+    // close every stream, replicas will have to resync
+    pub fn disconnect_all(&self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        for replica in inner.replicas.values() {
+            replica.state.closed.store(true, Ordering::SeqCst);
+        }
+
+        inner.replicas.clear();
+    }
+}
+
+impl ReplicaStream {
+    // next message to send, None when the stream was closed
+    pub async fn recv(&mut self) -> Result<Option<ReplicationMsg>> {
+        let msg = self.rx.recv().await;
+
+        if self.state.overrun.load(Ordering::SeqCst) {
+            bail!("replica output buffer limit reached");
+        }
+
+        if self.state.closed.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        Ok(msg)
+    }
+
+    pub fn written(&self, msg: &ReplicationMsg) {
+        self.state.queued.fetch_sub(msg.byte_len(), Ordering::SeqCst);
+    }
+}
+
+impl fmt::Display for ReplicaState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplicaState::Sync => write!(f, "wait_bgsave"),
+            ReplicaState::Online => write!(f, "online"),
+        }
+    }
+}
+
+impl OutputBufferLimit {
+    fn is_exceeded(&self, queued: usize, soft_since: &mut Option<Instant>, now: Instant) -> bool {
+        if self.hard > 0 && queued > self.hard {
+            return true;
+        }
+
+        if self.soft == 0 || queued <= self.soft {
+            *soft_since = None;
+            return false;
+        }
+
+        let since = *soft_since.get_or_insert(now);
+
+        now.duration_since(since) >= Duration::from_secs(self.soft_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::frame::Frame;
+
+    use super::*;
+
+    fn msg(len: usize) -> ReplicationMsg {
+        // `$<len>\r\n<data>\r\n`
+        ReplicationMsg::Propagate(Frame::Bulk(vec![b'x'; len].into()))
+    }
+
+    #[tokio::test]
+    async fn test_propagate_to_every_replica() {
+        let replicas = Replicas::new(OutputBufferLimit::default());
+        let mut first = replicas.register(ReplicaInfo::default());
+        let mut second = replicas.register(ReplicaInfo::default());
+
+        replicas.propagate(msg(3));
+
+        assert_eq!(first.recv().await.unwrap(), Some(msg(3)));
+        assert_eq!(second.recv().await.unwrap(), Some(msg(3)));
+        assert_eq!(replicas.list().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hard_limit_disconnects() {
+        let limit = OutputBufferLimit { hard: 20, soft: 0, soft_seconds: 0 };
+        let replicas = Replicas::new(limit);
+        let mut slow = replicas.register(ReplicaInfo::default());
+
+        replicas.propagate(msg(3));
+        replicas.propagate(msg(3));
+        assert_eq!(replicas.list().len(), 1);
+
+        // 8 bytes each, the third one goes over
+        replicas.propagate(msg(3));
+        assert_eq!(replicas.list().len(), 0);
+
+        assert!(slow.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_written_frees_buffer() {
+        let limit = OutputBufferLimit { hard: 20, soft: 0, soft_seconds: 0 };
+        let replicas = Replicas::new(limit);
+        let mut fast = replicas.register(ReplicaInfo::default());
+
+        for _ in 0..5 {
+            replicas.propagate(msg(3));
+            let msg = fast.recv().await.unwrap().unwrap();
+            fast.written(&msg);
+        }
+
+        assert_eq!(replicas.list().len(), 1);
+    }
+
+    #[test]
+    fn test_soft_limit() {
+        let limit = OutputBufferLimit { hard: 0, soft: 10, soft_seconds: 1 };
+        let now = Instant::now();
+        let mut soft_since = None;
+
+        assert!(!limit.is_exceeded(11, &mut soft_since, now));
+        assert!(!limit.is_exceeded(11, &mut soft_since, now + Duration::from_millis(500)));
+        assert!(limit.is_exceeded(11, &mut soft_since, now + Duration::from_secs(1)));
+
+        // going back under the soft limit resets the timer
+        let mut soft_since = None;
+        assert!(!limit.is_exceeded(11, &mut soft_since, now));
+        assert!(!limit.is_exceeded(5, &mut soft_since, now + Duration::from_millis(500)));
+        assert!(!limit.is_exceeded(11, &mut soft_since, now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_registry() {
+        let replicas = Replicas::new(OutputBufferLimit::default());
+        let info = ReplicaInfo {
+            ip: "127.0.0.1".to_string(),
+            listening_port: Some(6380),
+            capa: vec!["psync2".to_string()],
+            ..Default::default()
+        };
+
+        let first = replicas.register(info.clone());
+        let second = replicas.register(ReplicaInfo::default());
+
+        replicas.set_state(first.id, ReplicaState::Online);
+        replicas.ack(first.id, 42);
+        replicas.ack(first.id, 40);
+
+        let list = replicas.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].listening_port, Some(6380));
+        assert_eq!(list[0].state, ReplicaState::Online);
+        assert_eq!(list[0].ack_offset, 42);
+        assert!(list[0].last_ack.is_some());
+        assert_eq!(list[1].state, ReplicaState::Sync);
+
+        replicas.unregister(second.id);
+        assert_eq!(replicas.list().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_acked() {
+        let replicas = Replicas::new(OutputBufferLimit::default());
+        let first = replicas.register(ReplicaInfo::default());
+        let second = replicas.register(ReplicaInfo::default());
+        replicas.set_state(first.id, ReplicaState::Online);
+        replicas.set_state(second.id, ReplicaState::Online);
+
+        let waiter = {
+            let replicas = replicas.clone();
+            tokio::spawn(async move { replicas.wait_acked(10, 2, None).await })
+        };
+
+        time::sleep(Duration::from_millis(10)).await;
+        replicas.ack(first.id, 10);
+        replicas.ack(second.id, 5);
+        assert!(!waiter.is_finished());
+
+        replicas.ack(second.id, 12);
+        assert_eq!(waiter.await.unwrap(), 2);
+
+        // on timeout the replicas that made it are reported
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert_eq!(replicas.wait_acked(11, 2, Some(deadline)).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_all() {
+        let replicas = Replicas::new(OutputBufferLimit::default());
+        let mut stream = replicas.register(ReplicaInfo::default());
+
+        replicas.propagate(msg(3));
+        replicas.disconnect_all();
+
+        assert_eq!(stream.recv().await.unwrap(), None);
+        assert_eq!(replicas.list().len(), 0);
+    }
+}
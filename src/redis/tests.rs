@@ -1,23 +1,23 @@
-use std::io::Cursor;
-
-use bytes::Bytes;
-use tokio::net::TcpStream;
+use bytes::{Bytes, BytesMut};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::task::JoinHandle;
-use tokio::time::{Duration, sleep};
+use tokio::time::{Duration, Instant, sleep};
 
 use super::cmd::ClientCmd;
 use super::cmd::get::Get;
-use super::cmd::Wait;
+use super::cmd::info::Info;
+use super::cmd::{Ping, Psync, Replicaof, Wait};
 use super::config::Config;
 use super::Connection;
-use super::frame::Frame;
+use super::db::Db;
+use super::frame::{Decoder, Frame};
 use super::Server;
 use super::utils::Addr;
 
 pub fn make_frame(input: &[u8]) -> Frame {
-    let mut cursor = Cursor::new(&input[..]);
+    let mut buf = BytesMut::from(input);
 
-    Frame::parse(&mut cursor).unwrap()
+    Decoder::new().decode(&mut buf).unwrap().unwrap()
 }
 
 
@@ -61,12 +61,11 @@ impl TestSetup {
                 host: host.to_string(),
                 port: port.to_string(),
             },
-            master_addr: match master {
-                Some(addr) => Some(addr.clone()),
-                None => None
-            },
+            master_addr: master.cloned(),
             dir: String::from("/tmp/"),
             dbfilename: String::from("redis.rdb"),
+            save: vec![],
+            ..Default::default()
         }
     }
 
@@ -99,7 +98,7 @@ async fn test_replication() {
     master_conn.write_frame(&wait.to_frame()).await.unwrap();
     master_conn.read_frame().await.unwrap();
 
-    let get = Get::new("grape".to_string());
+    let get = Get::new(Bytes::from_static(b"grape"));
 
     let repl1_socket = TcpStream::connect(setup.replica1_cfg.addr.to_string()).await.unwrap();
     let mut repl1_conn = Connection::new(repl1_socket);
@@ -146,7 +145,7 @@ async fn test_replication() {
         expected
     );
 
-    let get = Get::new("foo".to_string());
+    let get = Get::new(Bytes::from_static(b"foo"));
 
     repl1_conn.write_frame(&get.to_frame()).await.unwrap();
     let response_from_repl1 = repl1_conn.read_frame().await.unwrap().unwrap();
@@ -189,3 +188,314 @@ async fn test_wait_no_commands() {
         expected
     );
 }
+
+#[tokio::test]
+async fn test_full_resync_ships_dataset() {
+    let master_cfg = TestSetup::config("127.0.0.1", "6390", None);
+    let replica_cfg = TestSetup::config("127.0.0.1", "6391", Some(&master_cfg.addr));
+
+    let mut master = TestSetup::setup_server(master_cfg.clone()).await;
+    tokio::spawn(async move { master.run().await.unwrap() });
+
+    let master_socket = TcpStream::connect(master_cfg.addr.to_string()).await.unwrap();
+    let mut master_conn = Connection::new(master_socket);
+
+    let input = b"*3\r\n$3\r\nSET\r\n$5\r\ngrape\r\n$9\r\nraspberry\r\n";
+    master_conn.write_frame(&make_frame(input)).await.unwrap();
+    master_conn.read_frame().await.unwrap();
+
+    let mut replica = TestSetup::setup_server(replica_cfg.clone()).await;
+    tokio::spawn(async move { replica.run().await.unwrap() });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let replica_socket = TcpStream::connect(replica_cfg.addr.to_string()).await.unwrap();
+    let mut replica_conn = Connection::new(replica_socket);
+
+    let get = Get::new(Bytes::from_static(b"grape"));
+    replica_conn.write_frame(&get.to_frame()).await.unwrap();
+
+    let response = replica_conn.read_frame().await.unwrap().unwrap();
+
+    assert_eq!(
+        response,
+        Frame::Bulk(Bytes::from_static(b"raspberry")),
+    );
+}
+
+async fn serve_handshake(master: &TcpListener, psync_response: &str, rdb: Option<&Vec<u8>>) -> (Connection, Frame) {
+    let (socket, _) = master.accept().await.unwrap();
+    let mut conn = Connection::new(socket);
+
+    // PING, REPLCONF listening-port, REPLCONF capa
+    conn.read_frame().await.unwrap().unwrap();
+    conn.write_frame(&Frame::Simple("PONG".to_string())).await.unwrap();
+    for _ in 0..2 {
+        conn.read_frame().await.unwrap().unwrap();
+        conn.write_frame(&Frame::Simple("OK".to_string())).await.unwrap();
+    }
+
+    let psync = conn.read_frame().await.unwrap().unwrap();
+    conn.write_frame(&Frame::Simple(psync_response.to_string())).await.unwrap();
+    if let Some(rdb) = rdb {
+        conn.write_rdb(rdb).await.unwrap();
+    }
+
+    (conn, psync)
+}
+
+#[tokio::test]
+async fn test_replica_reconnects_with_partial_resync() {
+    let master = TcpListener::bind("127.0.0.1:6392").await.unwrap();
+    let master_addr = Addr { host: "127.0.0.1".to_string(), port: "6392".to_string() };
+    let replica_cfg = TestSetup::config("127.0.0.1", "6393", Some(&master_addr));
+
+    let mut replica = TestSetup::setup_server(replica_cfg.clone()).await;
+    tokio::spawn(async move { replica.run().await.unwrap() });
+
+    let replid = "8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb";
+    let rdb = Db::new().build_rdb_frame();
+    let (mut conn, psync) = serve_handshake(
+        &master,
+        &format!("FULLRESYNC {} 0", replid),
+        Some(&rdb),
+    ).await;
+
+    assert_eq!(psync, Psync::default().to_frame());
+
+    // commands the replica doesn't know still count in its offset
+    let select = make_frame(b"*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n");
+    conn.write_frame(&select).await.unwrap();
+
+    let set = make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n");
+    conn.write_frame(&set).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+
+    // master goes away, replica should come back asking for the tail only
+    drop(conn);
+
+    let (_conn, psync) = serve_handshake(&master, "CONTINUE", None).await;
+
+    let expected = Psync {
+        replication_id: replid.to_string(),
+        offset: (select.byte_len() + set.byte_len()) as i64 + 1,
+    };
+    assert_eq!(psync, expected.to_frame());
+
+    sleep(Duration::from_millis(50)).await;
+
+    let replica_socket = TcpStream::connect(replica_cfg.addr.to_string()).await.unwrap();
+    let mut replica_conn = Connection::new(replica_socket);
+
+    replica_conn.write_frame(&Get::new(Bytes::from_static(b"foo")).to_frame()).await.unwrap();
+    assert_eq!(
+        replica_conn.read_frame().await.unwrap().unwrap(),
+        Frame::Bulk(Bytes::from_static(b"bar")),
+    );
+
+    replica_conn.write_frame(&Info::new().to_frame()).await.unwrap();
+    let info = match replica_conn.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("unexpected response {:?}", frame),
+    };
+
+    assert!(info.contains("role:slave"));
+    assert!(info.contains("master_link_status:up"));
+    assert!(info.contains("master_sync_in_progress:0"));
+}
+
+async fn info_field(conn: &mut Connection, field: &str) -> String {
+    conn.write_frame(&Info::new().to_frame()).await.unwrap();
+
+    let info = match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("unexpected response {:?}", frame),
+    };
+
+    info.lines()
+        .find_map(|line| line.strip_prefix(&format!("{}:", field)))
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_replicaof_and_promotion() {
+    let master_cfg = TestSetup::config("127.0.0.1", "6394", None);
+    let node_cfg = TestSetup::config("127.0.0.1", "6395", None);
+
+    let mut master = TestSetup::setup_server(master_cfg.clone()).await;
+    tokio::spawn(async move { master.run().await.unwrap() });
+    let mut node = TestSetup::setup_server(node_cfg.clone()).await;
+    tokio::spawn(async move { node.run().await.unwrap() });
+
+    let mut master_conn = Connection::new(TcpStream::connect(master_cfg.addr.to_string()).await.unwrap());
+    let mut node_conn = Connection::new(TcpStream::connect(node_cfg.addr.to_string()).await.unwrap());
+
+    master_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")).await.unwrap();
+    master_conn.read_frame().await.unwrap();
+    node_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nown\r\n$3\r\nkey\r\n")).await.unwrap();
+    node_conn.read_frame().await.unwrap();
+
+    let replicaof = Replicaof { master: Some(master_cfg.addr.clone()) };
+    node_conn.write_frame(&replicaof.to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Simple("OK".to_string()));
+
+    sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(info_field(&mut node_conn, "role").await, "slave");
+    assert_eq!(info_field(&mut node_conn, "master_link_status").await, "up");
+
+    node_conn.write_frame(&Get::new(Bytes::from_static(b"foo")).to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Bulk(Bytes::from_static(b"bar")));
+    node_conn.write_frame(&Get::new(Bytes::from_static(b"own")).to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Null);
+
+    // clients can only read from a replica
+    node_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nnew\r\n")).await.unwrap();
+    assert_eq!(
+        node_conn.read_frame().await.unwrap().unwrap(),
+        Frame::Error("READONLY You can't write against a read only replica.".to_string()),
+    );
+    node_conn.write_frame(&make_frame(b"*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$1\r\n0\r\n")).await.unwrap();
+    assert!(matches!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Error(e) if e.starts_with("READONLY")));
+    node_conn.write_frame(&Get::new(Bytes::from_static(b"foo")).to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Bulk(Bytes::from_static(b"bar")));
+
+    let master_replid = info_field(&mut master_conn, "master_replid").await;
+    let offset: u64 = info_field(&mut node_conn, "master_repl_offset").await.parse().unwrap();
+
+    node_conn.write_frame(&Replicaof { master: None }.to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Simple("OK".to_string()));
+
+    assert_eq!(info_field(&mut node_conn, "role").await, "master");
+    assert_eq!(info_field(&mut node_conn, "master_replid2").await, master_replid);
+    assert_ne!(info_field(&mut node_conn, "master_replid").await, master_replid);
+
+    // dataset is kept and writes are accepted
+    node_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nnew\r\n$3\r\nval\r\n")).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Simple("OK".to_string()));
+    node_conn.write_frame(&Get::new(Bytes::from_static(b"foo")).to_frame()).await.unwrap();
+    assert_eq!(node_conn.read_frame().await.unwrap().unwrap(), Frame::Bulk(Bytes::from_static(b"bar")));
+
+    // a sibling replica of the old master continues with the old replication id
+    let mut sibling = Connection::new(TcpStream::connect(node_cfg.addr.to_string()).await.unwrap());
+    let psync = Psync { replication_id: master_replid, offset: offset as i64 + 1 };
+    sibling.write_frame(&psync.to_frame()).await.unwrap();
+
+    match sibling.read_frame().await.unwrap().unwrap() {
+        Frame::Simple(response) => assert!(response.starts_with("CONTINUE")),
+        frame => panic!("unexpected response {:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn test_master_tracks_replica_acks() {
+    let master_cfg = TestSetup::config("127.0.0.1", "6396", None);
+    let replica_cfg = TestSetup::config("127.0.0.1", "6397", Some(&master_cfg.addr));
+
+    let mut master = TestSetup::setup_server(master_cfg.clone()).await;
+    tokio::spawn(async move { master.run().await.unwrap() });
+    let mut replica = TestSetup::setup_server(replica_cfg.clone()).await;
+    tokio::spawn(async move { replica.run().await.unwrap() });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let mut master_conn = Connection::new(TcpStream::connect(master_cfg.addr.to_string()).await.unwrap());
+    master_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")).await.unwrap();
+    master_conn.read_frame().await.unwrap();
+
+    assert_eq!(info_field(&mut master_conn, "connected_slaves").await, "1");
+
+    // replica acks on its own once a second
+    sleep(Duration::from_millis(1200)).await;
+
+    let offset = info_field(&mut master_conn, "master_repl_offset").await;
+    let slave = info_field(&mut master_conn, "slave0").await;
+
+    assert!(slave.starts_with("ip=127.0.0.1,port=6397,state=online,"));
+    assert!(slave.contains(&format!("offset={},", offset)));
+}
+
+#[tokio::test]
+async fn test_wait_returns_once_enough_replicas_acked() {
+    let master_cfg = TestSetup::config("127.0.0.1", "6398", None);
+    let replica_cfg = TestSetup::config("127.0.0.1", "6399", Some(&master_cfg.addr));
+
+    let mut master = TestSetup::setup_server(master_cfg.clone()).await;
+    tokio::spawn(async move { master.run().await.unwrap() });
+    let mut replica = TestSetup::setup_server(replica_cfg.clone()).await;
+    tokio::spawn(async move { replica.run().await.unwrap() });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let mut master_conn = Connection::new(TcpStream::connect(master_cfg.addr.to_string()).await.unwrap());
+    let mut other_conn = Connection::new(TcpStream::connect(master_cfg.addr.to_string()).await.unwrap());
+
+    master_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")).await.unwrap();
+    master_conn.read_frame().await.unwrap();
+
+    // a WAIT that can't be satisfied runs alongside and must not disturb the other one
+    other_conn.write_frame(&Wait { numreplicas: 2, timeout: 300 }.to_frame()).await.unwrap();
+
+    let started = Instant::now();
+    master_conn.write_frame(&Wait { numreplicas: 1, timeout: 5000 }.to_frame()).await.unwrap();
+    assert_eq!(master_conn.read_frame().await.unwrap().unwrap(), Frame::Integer(1));
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    assert_eq!(other_conn.read_frame().await.unwrap().unwrap(), Frame::Integer(1));
+    assert!(started.elapsed() >= Duration::from_millis(300));
+
+    // no timeout blocks until the ack arrives
+    master_conn.write_frame(&make_frame(b"*3\r\n$3\r\nSET\r\n$3\r\nbaz\r\n$3\r\nqux\r\n")).await.unwrap();
+    master_conn.read_frame().await.unwrap();
+    master_conn.write_frame(&Wait { numreplicas: 1, timeout: 0 }.to_frame()).await.unwrap();
+    assert_eq!(master_conn.read_frame().await.unwrap().unwrap(), Frame::Integer(1));
+}
+
+#[tokio::test]
+async fn test_master_pings_replicas() {
+    let mut master_cfg = TestSetup::config("127.0.0.1", "6400", None);
+    master_cfg.repl_ping_period = Duration::from_millis(100);
+
+    let mut master = TestSetup::setup_server(master_cfg.clone()).await;
+    tokio::spawn(async move { master.run().await.unwrap() });
+
+    let socket = TcpStream::connect(master_cfg.addr.to_string()).await.unwrap();
+    let mut replica = Connection::new(socket);
+    replica.write_frame(&Psync::default().to_frame()).await.unwrap();
+    replica.read_frame().await.unwrap().unwrap();
+    replica.read_rdb().await.unwrap().unwrap();
+
+    // an idle master still shows it is alive
+    let ping = tokio::time::timeout(Duration::from_secs(1), replica.read_frame()).await.unwrap();
+    assert_eq!(ping.unwrap().unwrap(), Ping::new(None).to_frame());
+}
+
+#[tokio::test]
+async fn test_replica_drops_silent_master() {
+    let master = TcpListener::bind("127.0.0.1:6401").await.unwrap();
+    let master_addr = Addr { host: "127.0.0.1".to_string(), port: "6401".to_string() };
+    let mut replica_cfg = TestSetup::config("127.0.0.1", "6402", Some(&master_addr));
+    replica_cfg.repl_timeout = Duration::from_millis(300);
+
+    let mut replica = TestSetup::setup_server(replica_cfg.clone()).await;
+    tokio::spawn(async move { replica.run().await.unwrap() });
+
+    let replid = "8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb";
+    let rdb = Db::new().build_rdb_frame();
+    let (_conn, _) = serve_handshake(&master, &format!("FULLRESYNC {} 0", replid), Some(&rdb)).await;
+    sleep(Duration::from_millis(50)).await;
+
+    let replica_socket = TcpStream::connect(replica_cfg.addr.to_string()).await.unwrap();
+    let mut replica_conn = Connection::new(replica_socket);
+    assert_eq!(info_field(&mut replica_conn, "master_link_status").await, "up");
+
+    // the socket stays open but nothing comes through it
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(info_field(&mut replica_conn, "master_link_status").await, "down");
+
+    let started = Instant::now();
+    let (_conn, psync) = serve_handshake(&master, "CONTINUE", None).await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(psync, Psync { replication_id: replid.to_string(), offset: 1 }.to_frame());
+}
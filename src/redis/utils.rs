@@ -1,6 +1,8 @@
+use std::collections::hash_map::RandomState;
 use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Addr {
     pub host: String,
     pub port: String,
@@ -29,6 +31,171 @@ pub trait Named {
     }
 }
 
+// 40 hex chars, the format redis uses for replication ids
+pub fn random_id() -> String {
+    (0..3).map(|_| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_nanos());
+        format!("{:016x}", hasher.finish())
+    }).collect::<String>()[..40].to_string()
+}
+
+// random enough for sampling, not for anything secret
+pub fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(std::time::UNIX_EPOCH.elapsed().unwrap_or_default().as_nanos());
+    hasher.finish()
+}
+
+// index into a collection of `len` items, `len` can't be 0
+pub fn random_index(len: usize) -> usize {
+    (random_u64() % len as u64) as usize
+}
+
+// replies are built whole before being sent, so a count allowing repeats is
+// held to as many elements as a request may carry by default
+pub const MAX_PICKS: u64 = 1024 * 1024;
+
+// `count` distinct items in random order, all of them when there are fewer
+pub fn sample<T>(mut items: Vec<T>, count: usize) -> Vec<T> {
+    let count = count.min(items.len());
+
+    // partial Fisher-Yates shuffle
+    for i in 0..count {
+        let j = i + random_index(items.len() - i);
+        items.swap(i, j);
+    }
+    items.truncate(count);
+
+    items
+}
+
+// `count` distinct items in random order taken in one pass, holding no
+// more of them than that, for collections that can't be indexed
+pub fn sample_iter<T>(items: impl Iterator<Item = T>, count: usize) -> Vec<T> {
+    let mut reservoir = Vec::new();
+
+    for (i, item) in items.enumerate() {
+        if i < count {
+            reservoir.push(item);
+        } else {
+            let j = random_index(i + 1);
+            if j < count {
+                reservoir[j] = item;
+            }
+        }
+    }
+
+    // the first items kept their places so far
+    let len = reservoir.len();
+    sample(reservoir, len)
+}
+
+// `count` items drawn independently out of the `len` ones, the same one may
+// come up several times; one pass holding only the picked items
+pub fn pick_iter<T: Copy>(items: impl Iterator<Item = T>, len: usize, count: usize) -> Vec<T> {
+    let mut positions: Vec<usize> = (0..count).map(|_| random_index(len)).collect();
+    positions.sort_unstable();
+
+    let mut positions = positions.into_iter().peekable();
+    let mut picked = Vec::with_capacity(count);
+    for (i, item) in items.enumerate() {
+        while positions.next_if_eq(&i).is_some() {
+            picked.push(item);
+        }
+        if positions.peek().is_none() {
+            break;
+        }
+    }
+
+    // still in the order of the collection
+    let len = picked.len();
+    sample(picked, len)
+}
+
+// glob-style matching as in redis MATCH options: `*`, `?`, `[a-z]`, `[^a]` and `\` escapes;
+// only the last `*` is backtracked to, which keeps it within O(pattern * string)
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // the pattern after the last `*` seen, and where in the string it was tried
+    let mut star: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, s));
+            continue;
+        }
+
+        if let Some(len) = match_one(&pattern[p..], string[s]) {
+            p += len;
+            s += 1;
+            continue;
+        }
+
+        // let the last `*` take one more byte
+        match star {
+            Some((after, tried)) => {
+                p = after;
+                s = tried + 1;
+                star = Some((after, s));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+// how much of the pattern matched `c` when its start is anything but `*`
+fn match_one(pattern: &[u8], c: u8) -> Option<usize> {
+    match pattern {
+        [] | [b'*', ..] => None,
+        [b'?', ..] => Some(1),
+        [b'[', rest @ ..] => match match_class(rest, c) {
+            Some((true, left)) => Some(pattern.len() - left.len()),
+            _ => None,
+        },
+        [b'\\', escaped, ..] => (*escaped == c).then_some(2),
+        [p, ..] => (*p == c).then_some(1),
+    }
+}
+
+// whether `c` is in the class at the start of `pattern`, with the pattern
+// left after the closing `]`; an unclosed class runs to the end
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let (negate, mut pattern) = match pattern.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+    let mut matched = false;
+
+    loop {
+        match pattern {
+            [] => break,
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
+            }
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == c;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end { (start, end) } else { (end, start) };
+                matched |= (*low..=*high).contains(&c);
+                pattern = rest;
+            }
+            [other, rest @ ..] => {
+                matched |= *other == c;
+                pattern = rest;
+            }
+        }
+    }
+
+    Some((matched != negate, pattern))
+}
+
 pub fn int_as_bytes(i: &usize) -> Vec<u8> {
     let mut buff = Vec::new();
 
@@ -47,6 +214,25 @@ pub fn add_cr(buff: &mut Vec<u8>) {
     buff.extend([b'\r', b'\n']);
 }
 
+// memory sizes as written in redis.conf: `1024`, `64kb`, `1mb`, `2gb`
+pub fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let digits_end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits_end);
+
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
 
 #[cfg(test)]
 mod tests {
@@ -55,18 +241,82 @@ mod tests {
     #[test]
     fn test_count_digits() {
         assert_eq!(
-            count_digits(&(123 as usize)),
+            count_digits(&123_usize),
             3
         );
 
         assert_eq!(
-            count_digits(&(12 as usize)),
+            count_digits(&12_usize),
             2
         );
 
         assert_eq!(
-            count_digits(&(1 as usize)),
+            count_digits(&1_usize),
             1
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"user:*", b"user:42"));
+        assert!(!glob_match(b"user:*", b"item:42"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"a\\*b", b"a*b"));
+        assert!(!glob_match(b"a\\*b", b"axb"));
+        assert!(glob_match(b"*:*:end", b"a:b:c:end"));
+        assert!(glob_match(b"[abc", b"b"));
+        assert!(glob_match(b"a\\", b"a\\"));
+        assert!(!glob_match(b"a?", b"a"));
+    }
+
+    // patterns that used to take exponential time or a frame per pattern byte
+    #[test]
+    fn test_glob_match_pathological() {
+        let string = [b'a'; 200];
+        assert!(!glob_match(b"*a*a*a*a*a*a*b", &string));
+        assert!(glob_match(b"*a*a*a*a*a*a*a", &string));
+
+        let literal = vec![b'x'; 10_000];
+        assert!(glob_match(&literal, &literal));
+        assert!(!glob_match(&literal, &literal[1..]));
+    }
+
+    #[test]
+    fn test_sample_iter() {
+        let mut picked = sample_iter(0..100, 10);
+        assert_eq!(picked.len(), 10);
+        picked.sort_unstable();
+        picked.dedup();
+        assert_eq!(picked.len(), 10);
+
+        let mut all = sample_iter(0..5, 10);
+        all.sort_unstable();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+        assert!(sample_iter(0..5, 0).is_empty());
+    }
+
+    #[test]
+    fn test_pick_iter() {
+        let picked = pick_iter(0..3, 3, 100);
+        assert_eq!(picked.len(), 100);
+        assert!(picked.iter().all(|item| *item < 3));
+
+        assert_eq!(pick_iter(0..1, 1, 3), vec![0, 0, 0]);
+        assert!(pick_iter(0..5, 5, 0).is_empty());
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("1024"), Some(1024));
+        assert_eq!(parse_memory("64kb"), Some(64 * 1024));
+        assert_eq!(parse_memory("1MB"), Some(1024 * 1024));
+        assert_eq!(parse_memory("2g"), Some(2_000_000_000));
+        assert_eq!(parse_memory("mb"), None);
+        assert_eq!(parse_memory("12xb"), None);
+    }
+}